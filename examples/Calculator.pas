@@ -0,0 +1,53 @@
+PROGRAM Calculator; { CASE on chars with ranges, on an enumeration and on integers }
+
+TYPE
+	kind = (digit, operator, space, other);
+
+VAR
+	c: char;
+	k: kind;
+	value, operand: integer;
+	op: char;
+
+BEGIN
+	value := 0;
+	operand := 0;
+	op := '+';
+	read(c);
+	WHILE c <> '=' DO BEGIN
+		CASE c OF
+			'0'..'9': k := digit;
+			'+', '-', '*', '/': k := operator;
+			' ': k := space
+		OTHERWISE
+			k := other
+		END;
+		IF k <> space THEN
+			CASE k OF
+				digit: operand := operand * 10 + ord(c) - ord('0');
+				operator: BEGIN
+					CASE op OF
+						'+': value := value + operand;
+						'-': value := value - operand;
+						'*': value := value * operand;
+						'/': value := value DIV operand
+					END;
+					operand := 0;
+					op := c
+				END;
+				other: writeln('Skipped ', c)
+			END;
+		read(c)
+	END;
+	CASE op OF
+		'+': value := value + operand;
+		'-': value := value - operand;
+		'*': value := value * operand;
+		'/': value := value DIV operand
+	END;
+	writeln('Result: ', value);
+	CASE value MOD 3 OF
+		0: writeln('Divisible by 3');
+		1, 2: writeln('Not divisible by 3')
+	END
+END.
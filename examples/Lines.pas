@@ -0,0 +1,26 @@
+PROGRAM Lines (input, output); { counts the words on each line of the input }
+
+VAR
+	line, words: integer;
+	c: char;
+	inword: boolean;
+
+BEGIN
+	line := 0;
+	WHILE NOT eof DO BEGIN
+		line := line + 1;
+		words := 0;
+		inword := false;
+		WHILE NOT eoln DO BEGIN
+			read(c);
+			IF c = ' ' THEN
+				inword := false
+			ELSE IF NOT inword THEN BEGIN
+				words := words + 1;
+				inword := true
+			END
+		END;
+		readln;
+		writeln('line ', line, ': ', words, ' words')
+	END
+END.
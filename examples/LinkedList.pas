@@ -0,0 +1,50 @@
+PROGRAM LinkedList; { a sorted list of numbers built from pointers }
+
+TYPE
+	link = ^node;
+	node = RECORD
+		value: integer;
+		next: link
+	END;
+
+VAR
+	head, p: link;
+	n: integer;
+
+PROCEDURE insert(VAR list: link; value: integer);
+VAR
+	q: link;
+BEGIN
+	IF list = NIL THEN BEGIN
+		new(q);
+		q^.value := value;
+		q^.next := NIL;
+		list := q
+	END ELSE IF value < list^.value THEN BEGIN
+		new(q);
+		q^.value := value;
+		q^.next := list;
+		list := q
+	END ELSE
+		insert(list^.next, value)
+END;
+
+BEGIN
+	head := NIL;
+	read(n);
+	WHILE n <> 0 DO BEGIN
+		insert(head, n);
+		read(n)
+	END;
+	p := head;
+	WHILE p <> NIL DO BEGIN
+		write(p^.value, ' ');
+		p := p^.next
+	END;
+	writeln;
+	WHILE head <> NIL DO BEGIN
+		p := head;
+		head := head^.next;
+		dispose(p)
+	END
+END.
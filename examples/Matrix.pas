@@ -0,0 +1,39 @@
+PROGRAM Matrix; { multi-dimensional arrays, and arrays indexed by chars and enumerations }
+
+TYPE
+	axis = (rows, columns);
+	square = array[1..3, 1..3] of integer;
+
+VAR
+	a, b, product: square;
+	size: array[axis] of integer;
+	letters: array['a'..'e'] of integer;
+	i, j, k: integer;
+	c: char;
+
+BEGIN
+	FOR i := 1 TO 3 DO
+		FOR j := 1 TO 3 DO BEGIN
+			read(k);
+			a[i, j] := k
+		END;
+	FOR i := 1 TO 3 DO
+		FOR j := 1 TO 3 DO
+			b[i][j] := a[j, i];
+	FOR i := 1 TO 3 DO
+		FOR j := 1 TO 3 DO BEGIN
+			product[i, j] := 0;
+			FOR k := 1 TO 3 DO
+				product[i, j] := product[i, j] + a[i, k] * b[k][j]
+		END;
+	size[rows] := 3;
+	size[columns] := 3;
+	FOR i := 1 TO size[rows] DO BEGIN
+		FOR j := 1 TO size[columns] DO
+			write(product[i, j], ' ');
+		writeln
+	END;
+	FOR c := 'a' TO 'e' DO
+		letters[c] := ord(c) - ord('a') + 1;
+	writeln('Sum of letters: ', letters['a'] + letters['e'])
+END.
@@ -0,0 +1,29 @@
+PROGRAM Names (input, output); { tidies up a list of names given one per line as first and last }
+
+VAR
+	line, first, last, initials, number: string;
+	space, count, code: integer;
+
+BEGIN
+	count := 0;
+	WHILE NOT eof DO BEGIN
+		readln(line);
+		space := pos(' ', line);
+		IF space > 0 THEN BEGIN
+			count := count + 1;
+			first := copy(line, 1, space - 1);
+			last := copy(line, space + 1, length(line) - space);
+			first[1] := upcase(first[1]);
+			initials := concat(upcase(first[1]), '.', upcase(last[1]), '.');
+			str(count, number);
+			insert(', ', last, length(last) + 1);
+			writeln(number + ': ' + last + first, ' (', initials, ')');
+			IF last < 'm' THEN
+				writeln('   in the first half of the alphabet')
+		END
+	END;
+	line := 'count 42';
+	delete(line, 1, 6);
+	val(line, count, code);
+	writeln(count * 2, ' ', code)
+END.
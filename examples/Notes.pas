@@ -0,0 +1,39 @@
+PROGRAM Notes (input, output, notes); { writes lines to a text file, then reads them back }
+
+VAR
+	notes: text;
+	copy: text;
+	n, i, k, total: integer;
+	c: char;
+
+BEGIN
+	read(n);
+	rewrite(notes);
+	FOR i := 1 TO n DO
+		write(notes, i * i, ' ');
+	writeln(notes);
+	close(notes);
+
+	reset(notes);
+	total := 0;
+	FOR i := 1 TO n DO BEGIN
+		read(notes, k);
+		total := total + k
+	END;
+	writeln('Sum of squares: ', total);
+
+	assign(copy, 'copy.txt');
+	rewrite(copy);
+	write(copy, 'ab');
+	writeln(copy);
+	write(copy, 'c');
+	close(copy);
+	reset(copy);
+	WHILE NOT eof(copy) DO BEGIN
+		IF eoln(copy) THEN
+			writeln('<line break>');
+		read(copy, c);
+		writeln(c)
+	END;
+	close(copy)
+END.
@@ -8,7 +8,7 @@ VAR
 	i: integer;
 BEGIN
 	{ initialize row to all 0s }
-	FOR i := 1 TO ROWCT + 1 DO
+	FOR i := 0 TO ROWCT + 1 DO
 		arr[i] := 0;
 
 	{ create first row (1) }
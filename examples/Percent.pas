@@ -0,0 +1,21 @@
+PROGRAM Percent (input, output); { shows each amount read as a share of their total }
+
+CONST
+	count = 5;
+
+VAR
+	amounts: ARRAY [1..count] OF real;
+	amount, total: real;
+	i: integer;
+
+BEGIN
+	total := 0;
+	FOR i := 1 TO count DO BEGIN
+		read(amount);
+		amounts[i] := amount;
+		total := total + amount
+	END;
+	FOR i := 1 TO count DO
+		writeln(i:2, amounts[i]:10:2, 100 * amounts[i] / total:8:1, '%');
+	writeln('total', total:9:2, total:14)
+END.
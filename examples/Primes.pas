@@ -0,0 +1,43 @@
+PROGRAM Primes (input, output, primes); { keeps the primes up to a limit in a file of integers }
+
+VAR
+	primes: FILE OF integer;
+	limit, n, d, count, sum: integer;
+	prime: boolean;
+
+BEGIN
+	read(limit);
+	rewrite(primes);
+	FOR n := 2 TO limit DO BEGIN
+		prime := true;
+		d := 2;
+		WHILE prime AND (d * d <= n) DO BEGIN
+			IF n MOD d = 0 THEN
+				prime := false;
+			d := d + 1
+		END;
+		IF prime THEN BEGIN
+			primes^ := n;
+			put(primes)
+		END
+	END;
+	close(primes);
+
+	reset(primes);
+	count := 0;
+	sum := 0;
+	WHILE NOT eof(primes) DO BEGIN
+		write(primes^, ' ');
+		sum := sum + primes^;
+		count := count + 1;
+		get(primes)
+	END;
+	writeln;
+	writeln(count, ' primes adding up to ', sum);
+
+	rewrite(primes);
+	write(primes, 7, 11);
+	reset(primes);
+	read(primes, n, d);
+	writeln(n, ' and ', d, ' read back, at the end: ', eof(primes))
+END.
@@ -7,11 +7,11 @@ VAR
 BEGIN
 	writeln('Welcome to the questionnaire!');
 	write('Enter your name: ');
-	read(name);
+	readln(name);
 	write('Enter your age: ');
-	read(age);
+	readln(age);
 	write('Enter an animal: ');
-	read(animal);
+	readln(animal);
 	writeln('Hello, ', name, '! ', animal, ' is a good choice.');
 	if age < 18 then
 		writeln('You will be 18 in ', 18 - age, ' years.')
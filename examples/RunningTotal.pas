@@ -0,0 +1,28 @@
+PROGRAM RunningTotal; { procedures and functions using the program's own variables }
+
+VAR
+	total, count: integer;
+	n: integer;
+
+PROCEDURE add(value: integer);
+BEGIN
+	total := total + value;
+	count := count + 1
+END;
+
+FUNCTION average: integer;
+BEGIN
+	average := total DIV count
+END;
+
+BEGIN
+	total := 0;
+	count := 0;
+	write('Enter the number of values: ');
+	read(n);
+	WHILE n > 0 DO BEGIN
+		add(n * n);
+		n := n - 1
+	END;
+	writeln('Total: ', total, ', average: ', average)
+END.
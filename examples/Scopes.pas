@@ -0,0 +1,50 @@
+PROGRAM Scopes; { nested routines using the variables of the blocks around them }
+
+VAR
+	depth, calls: integer;
+
+PROCEDURE outer(n: integer);
+VAR
+	total: integer;
+
+	PROCEDURE inner(m: integer);
+	VAR
+		depth: integer; { hides the program's depth }
+	BEGIN
+		depth := m;
+		total := total + depth;
+		calls := calls + 1;
+		IF m > 1 THEN
+			inner(m - 1)
+	END;
+
+BEGIN
+	total := 0;
+	inner(n);
+	writeln('Sum of 1 to ', n, ': ', total)
+END;
+
+FUNCTION power(base, exponent: integer): integer;
+
+	FUNCTION square(x: integer): integer;
+	BEGIN
+		square := x * x
+	END;
+
+BEGIN
+	IF exponent = 0 THEN
+		power := 1
+	ELSE IF exponent MOD 2 = 0 THEN
+		power := square(power(base, exponent DIV 2))
+	ELSE
+		power := base * power(base, exponent - 1)
+END;
+
+BEGIN
+	depth := 0;
+	calls := 0;
+	read(depth);
+	outer(depth);
+	writeln('Calls: ', calls, ', depth is still ', depth);
+	writeln('2 to the ', depth, ': ', power(2, depth))
+END.
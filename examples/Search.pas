@@ -0,0 +1,48 @@
+PROGRAM Search; { GOTO within a block and out of nested routines }
+
+LABEL 99;
+
+VAR
+	grid: array[1..3] of array[1..3] of integer;
+	i, j, target: integer;
+
+PROCEDURE find(target: integer);
+VAR
+	i, j: integer;
+
+	PROCEDURE found(i, j: integer);
+	BEGIN
+		writeln('Found ', target, ' at ', i, ', ', j);
+		GOTO 99
+	END;
+
+BEGIN
+	FOR i := 1 TO 3 DO
+		FOR j := 1 TO 3 DO
+			IF grid[i][j] = target THEN
+				found(i, j);
+	writeln(target, ' is not in the grid')
+END;
+
+PROCEDURE countdown(n: integer);
+LABEL 1;
+BEGIN
+1:
+	write(n, ' ');
+	n := n - 1;
+	IF n > 0 THEN
+		GOTO 1;
+	writeln
+END;
+
+BEGIN
+	FOR i := 1 TO 3 DO
+		FOR j := 1 TO 3 DO
+			grid[i][j] := i * j;
+	countdown(3);
+	read(target);
+	find(target);
+	writeln('Not reached when found');
+99:
+	writeln('Done')
+END.
@@ -0,0 +1,40 @@
+PROGRAM Sets; { sets of chars and of an enumeration }
+
+TYPE
+	colour = (red, orange, yellow, green, blue);
+	palette = SET OF colour;
+
+VAR
+	vowels, seen: SET OF char;
+	warm, cool, mixed: palette;
+	c: char;
+	count: integer;
+
+BEGIN
+	vowels := ['a', 'e', 'i', 'o', 'u'];
+	seen := [];
+	count := 0;
+	read(c);
+	WHILE c <> '.' DO BEGIN
+		IF (c IN vowels) AND NOT (c IN seen) THEN BEGIN
+			seen := seen + [c];
+			count := count + 1
+		END;
+		read(c)
+	END;
+	writeln('Different vowels: ', count);
+	IF seen = vowels THEN
+		writeln('Every vowel was used');
+	FOR c := 'a' TO 'z' DO
+		IF c IN vowels - seen THEN
+			write(c);
+	writeln;
+
+	warm := [red..yellow];
+	cool := [green, blue];
+	mixed := warm * [yellow, green] + cool - [blue];
+	IF mixed <= warm + cool THEN
+		writeln('Mixed colours are warm or cool');
+	IF NOT (blue IN mixed) AND (green IN mixed) THEN
+		writeln('Mixed has green but not blue')
+END.
@@ -0,0 +1,50 @@
+PROGRAM Shapes; { records with a variant part, nested records and WITH }
+
+TYPE
+	kind = (circle, rectangle);
+	point = RECORD
+		x, y: real
+	END;
+	shape = RECORD
+		centre: point;
+		CASE form: kind OF
+			circle: (radius: real);
+			rectangle: (width, height: real)
+	END;
+
+VAR
+	shapes: array[1..2] of shape;
+	s: shape;
+	i: integer;
+	total, w, h: real;
+
+FUNCTION area(s: shape): real;
+BEGIN
+	WITH s DO
+		IF form = circle THEN
+			area := 3.14159 * radius * radius
+		ELSE
+			area := width * height
+END;
+
+BEGIN
+	WITH s DO BEGIN
+		form := circle;
+		read(radius);
+		centre.x := 0;
+		centre.y := 0
+	END;
+	shapes[1] := s;
+	s.form := rectangle;
+	read(w, h);
+	s.width := w;
+	s.height := h;
+	s.centre.x := s.width / 2;
+	s.centre.y := s.height / 2;
+	shapes[2] := s;
+	total := 0;
+	FOR i := 1 TO 2 DO
+		total := total + area(shapes[i]);
+	writeln('Total area: ', total);
+	writeln('Centre of the rectangle: ', shapes[2].centre.x, ', ', shapes[2].centre.y)
+END.
@@ -0,0 +1,42 @@
+PROGRAM Swap; { VAR parameters change the variables passed in }
+
+VAR
+	a, b: integer;
+	values: array[1..3] of integer;
+	i: integer;
+
+PROCEDURE exchange(VAR x, y: integer);
+VAR
+	t: integer;
+BEGIN
+	t := x;
+	x := y;
+	y := t
+END;
+
+PROCEDURE double(VAR n: integer);
+BEGIN
+	n := n * 2
+END;
+
+PROCEDURE doubleboth;
+BEGIN
+	double(a);
+	double(b)
+END;
+
+BEGIN
+	write('Enter two numbers: ');
+	read(a, b);
+	exchange(a, b);
+	writeln('Swapped: ', a, ' ', b);
+	doubleboth;
+	writeln('Doubled: ', a, ' ', b);
+	FOR i := 1 TO 3 DO
+		values[i] := i;
+	exchange(values[1], values[3]);
+	double(values[2]);
+	FOR i := 1 TO 3 DO
+		write(values[i], ' ');
+	writeln
+END.
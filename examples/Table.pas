@@ -0,0 +1,15 @@
+PROGRAM Table; { field widths and fraction digits in WRITE and WRITELN }
+
+VAR
+	i, n: integer;
+	x: real;
+
+BEGIN
+	read(n);
+	writeln('n':3, 'sqr':6, 'root':10, 'inverse':12);
+	FOR i := 1 TO n DO BEGIN
+		x := sqrt(i);
+		writeln(i:3, sqr(i):6, x:10:4, 1.0 / i:12)
+	END;
+	writeln('[', true:6, ']', '[', 'x':3, ']', '[', 42:1, ']', '[', -2.5:0:1, ']')
+END.
@@ -0,0 +1,24 @@
+PROGRAM Trigonometry; { the required math functions, folded when their arguments are constant }
+
+CONST
+	pi = 3.14159265;
+	e = 2.71828183;
+
+VAR
+	x: real;
+	n: integer;
+
+BEGIN
+	read(x, n);
+	writeln('sin: ', sin(x):0:4, ' cos: ', cos(x):0:4);
+	writeln('arctan(1) * 4: ', arctan(1) * 4:0:6);
+	writeln('exp(ln(x)): ', exp(ln(x)):0:4, ' ln(e): ', ln(e):0:4);
+	writeln('abs: ', abs(-n), ' ', abs(-x):0:2);
+	writeln('trunc: ', trunc(-x), ' round: ', round(x), ' ', round(-2.5));
+	IF odd(n) THEN
+		writeln(n, ' is odd')
+	ELSE
+		writeln(n, ' is even');
+	writeln('succ: ', succ(n), ' pred: ', pred(n), ' sqr: ', sqr(n));
+	writeln('constant: ', sqrt(16):0:1, ' ', cos(pi):0:1, ' ', trunc(e))
+END.
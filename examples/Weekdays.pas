@@ -0,0 +1,36 @@
+PROGRAM Weekdays; { enumerations, subranges and type aliases }
+
+TYPE
+	day = (monday, tuesday, wednesday, thursday, friday, saturday, sunday);
+	workday = monday..friday;
+	hours = 0..24;
+	count = integer;
+
+VAR
+	d: day;
+	w: workday;
+	worked: array[monday..sunday] of hours;
+	total: count;
+	letter: 'a'..'z';
+	n: integer;
+
+BEGIN
+	FOR d := monday TO sunday DO BEGIN
+		read(n);
+		worked[d] := n
+	END;
+	total := 0;
+	FOR w := monday TO friday DO
+		total := total + worked[w];
+	writeln('Hours on workdays: ', total);
+	d := succ(monday);
+	writeln('Day after monday: ', ord(d));
+	d := pred(sunday);
+	IF d = saturday THEN
+		writeln('Day before sunday is saturday');
+	IF worked[saturday] + worked[sunday] > 0 THEN
+		writeln('Worked on the weekend');
+	FOR letter := 'x' TO 'z' DO
+		write(letter);
+	writeln
+END.
@@ -0,0 +1,15 @@
+PROGRAM IndexOutOfRange; { counts down past the start of an array, caught by --range-checks }
+
+VAR
+	squares: ARRAY[1..5] OF integer;
+	i: integer;
+
+BEGIN
+	FOR i := 1 TO 5 DO
+		squares[i] := i * i;
+	i := 5;
+	WHILE i >= 0 DO BEGIN
+		writeln(squares[i]);
+		i := i - 1
+	END
+END.
@@ -0,0 +1,13 @@
+PROGRAM JsonMessages; { mistakes like those in VeryBad, printed with --message-format=json }
+
+VAR
+	count: integer;
+	done: boolean;
+
+BEGIN
+	count := 'none';
+	IF done THEN
+		writeln(count);
+	IF count THEN
+		writeln('some')
+END.
@@ -0,0 +1,15 @@
+PROGRAM Misspelled; { names that are close to declared ones get a suggestion }
+
+CONST
+	limit = 5;
+
+VAR
+	count, total: integer;
+
+BEGIN
+	total := 0;
+	FOR count := 1 TO limit DO
+		total := total + cuont;
+	limit := 10;
+	witeln(total)
+END.
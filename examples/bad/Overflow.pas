@@ -0,0 +1,14 @@
+PROGRAM Overflow; { doubles a number until it no longer fits, caught by --overflow-checks }
+
+VAR
+	n, steps: integer;
+
+BEGIN
+	n := 1;
+	steps := 0;
+	WHILE n > 0 DO BEGIN
+		n := n * 2;
+		steps := steps + 1
+	END;
+	writeln('stopped after ', steps, ' steps')
+END.
@@ -0,0 +1,13 @@
+PROGRAM ReadAfterRewrite;
+
+VAR
+	f: text;
+	i: integer;
+
+BEGIN
+	assign(f, 'numbers.txt');
+	rewrite(f);
+	writeln(f, '12');
+	read(f, i);
+	writeln(i)
+END.
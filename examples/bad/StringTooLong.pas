@@ -0,0 +1,13 @@
+PROGRAM StringTooLong; { a string that keeps doubling soon has more than 255 characters }
+
+VAR
+	s: string;
+	i: integer;
+
+BEGIN
+	s := 'abcdefgh';
+	FOR i := 1 TO 8 DO BEGIN
+		s := s + s;
+		writeln(length(s))
+	END
+END.
@@ -0,0 +1,14 @@
+PROGRAM UnclosedParameters;
+
+VAR
+	a: integer;
+
+PROCEDURE show(x: integer;
+BEGIN
+	writeln(x)
+END;
+
+BEGIN
+	a := 1 +;
+	show(a)
+END.
@@ -0,0 +1,23 @@
+PROGRAM Uninitialized; { sums scores of which one was never set, caught by --uninitialized-checks }
+
+TYPE
+	player = RECORD
+		name: char;
+		scores: ARRAY[1..3] OF integer
+	END;
+
+VAR
+	p: player;
+	i, total: integer;
+
+BEGIN
+	p.name := 'a';
+	p.scores[1] := 10;
+	p.scores[3] := 30;
+	total := 0;
+	FOR i := 1 TO 3 DO BEGIN
+		writeln('adding score ', i);
+		total := total + p.scores[i]
+	END;
+	writeln(p.name, ' scored ', total)
+END.
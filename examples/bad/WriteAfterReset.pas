@@ -0,0 +1,13 @@
+PROGRAM WriteAfterReset;
+
+VAR
+	f: text;
+
+BEGIN
+	assign(f, 'empty.txt');
+	rewrite(f);
+	close(f);
+	reset(f);
+	writeln('Writing to a file opened for reading');
+	writeln(f, 'x')
+END.
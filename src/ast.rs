@@ -1,13 +1,21 @@
 use crate::definitions::*;
+use crate::diagnostics::*;
 use crate::tokenizer::*;
 
-/// Reports a syntax error to the user if condition is true.
+/// A syntax error, which is collected as a diagnostic by the statement or declaration it is in.
+///
+/// Parsing gives up on the statement or declaration, and carries on after it, so every syntax
+/// error in the program is reported.
+pub struct SyntaxError(Diagnostic);
+
+/// Returns a syntax error if condition is false.
 ///
 /// # Arguments
 ///
-/// * `cond` - If true, report as error
+/// * `cond` - If false, report as error
 /// * `code` - A string representing the user program.
 /// * `i` - An index in the code from which the next token is erroneous.
+/// * `error_code` - The stable code of the error, such as "P0002".
 /// * `err` - The error to report to the user.
 ///
 /// # Examples
@@ -17,13 +25,133 @@ use crate::tokenizer::*;
 /// let code = "PROGRAM a BEGIN END;\n";
 /// let mut token = next_token(code, &mut i);
 /// token = next_token(code, &mut i);
-/// syntax_check(last_token(code, &mut i) == ";", code, 10, "Expected ;");
+/// syntax_check(last_token(code, &mut i) == ";", code, 10, "P0002", "Expected ;")?;
 /// ```
 ///
-fn syntax_check(cond: bool, code: &str, mut i: usize, err: &str) {
-    let length = next_token(code, &mut i).len();
-    if !cond {
-        report(code, i - length, i, err, "syntax");
+fn syntax_check(
+    cond: bool,
+    code: &str,
+    mut i: usize,
+    error_code: &'static str,
+    err: &str,
+) -> Result<(), SyntaxError> {
+    if cond {
+        return Ok(());
+    }
+    let start = i + code[i..].len() - code[i..].trim_start().len();
+    let token = next_token(code, &mut i);
+    let diagnostic = if token == "{" || token == "(*" {
+        // the tokenizer only leaves comment openers with no end, and skips to the end of the input
+        Diagnostic::syntax("P0004", start, start + token.len(), "Unclosed comment")
+    } else if !token.is_empty() {
+        Diagnostic::syntax(error_code, i - token.len(), i, err)
+    } else {
+        // point at the last character, if there is one
+        let end = code.trim_end().len();
+        let start = code[..end]
+            .char_indices()
+            .last()
+            .map_or(0, |(start, _)| start);
+        Diagnostic::syntax("P0001", start, end, "Unexpected end of input")
+    };
+    Err(SyntaxError(diagnostic))
+}
+
+/// Skips tokens until one of `stops` is next, or the end of the input is reached.
+///
+/// Tokens inside BEGIN, CASE or RECORD ... END, REPEAT ... UNTIL and parentheses are skipped
+/// whole, so parsing can carry on after a statement or declaration with a syntax error in it. A
+/// missing ) is closed by the end of the statement, or the start of the next declaration.
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+/// * `stops` - The tokens to stop before.
+///
+fn skip_to(code: &str, i: &mut usize, stops: &[&str]) {
+    let mut nesting: Vec<String> = Vec::new();
+    // only parameter lists and variant parts have a ; inside parentheses
+    let in_statement = stops.contains(&"END") || stops.contains(&"UNTIL");
+    loop {
+        let peeker = last_token(code, i);
+        // so in a statement, a ; closes a missing ), and in a declaration, the start of the next
+        // one or of the body does
+        let closes_parentheses = if in_statement {
+            peeker == ";"
+        } else {
+            DECLARATION_STOPS[1..].contains(&peeker.as_str())
+        };
+        if closes_parentheses {
+            while nesting.last().is_some_and(|opener| opener == "(") {
+                nesting.pop();
+            }
+        }
+        if peeker.is_empty() || (nesting.is_empty() && stops.contains(&peeker.as_str())) {
+            return;
+        }
+        match peeker.as_str() {
+            // the variant part of a record shares its END
+            "CASE" if nesting.last().is_some_and(|opener| opener == "RECORD") => {}
+            "BEGIN" | "CASE" | "RECORD" | "REPEAT" | "(" => nesting.push(peeker.clone()),
+            ")" if nesting.last().is_some_and(|opener| opener == "(") => {
+                nesting.pop();
+            }
+            // a missing ) is closed by the end of the statement around it
+            "END" | "UNTIL" => {
+                while nesting.last().is_some_and(|opener| opener == "(") {
+                    nesting.pop();
+                }
+                let closes = if peeker == "END" {
+                    ["BEGIN", "CASE", "RECORD"].as_slice()
+                } else {
+                    ["REPEAT"].as_slice()
+                };
+                if nesting
+                    .last()
+                    .is_some_and(|opener| closes.contains(&opener.as_str()))
+                {
+                    nesting.pop();
+                } else if nesting.is_empty() && stops.contains(&peeker.as_str()) {
+                    return;
+                }
+            }
+            _ => {}
+        }
+        next_token(code, i);
+    }
+}
+
+/// Returns the result of parsing a statement or declaration, collecting a syntax error in it if
+/// parsing can carry on after it.
+///
+/// On an error, tokens are skipped from `start`, the beginning of the statement or declaration,
+/// until one of `stops` is next. At the end of the input, the error is passed on instead.
+///
+/// # Arguments
+/// * `result` - The result of parsing the statement or declaration.
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+/// * `start` - The index `i` was at before parsing the statement or declaration.
+/// * `stops` - The tokens to stop skipping before.
+/// * `diagnostics` - The syntax errors found so far.
+///
+fn recover<T>(
+    result: Result<T, SyntaxError>,
+    code: &str,
+    i: &mut usize,
+    start: usize,
+    stops: &[&str],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Option<T>, SyntaxError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if last_token(code, i).is_empty() => Err(error),
+        Err(SyntaxError(diagnostic)) => {
+            diagnostics.push(diagnostic);
+            *i = start;
+            skip_to(code, i, stops);
+            Ok(None)
+        }
     }
 }
 
@@ -31,138 +159,620 @@ fn syntax_check(cond: bool, code: &str, mut i: usize, err: &str) {
 ///
 /// "PROGRAM" ("(" identifier ("," identifer)* ")")? ";" block "."
 ///
+/// Every syntax error in the program is found, and if there are any, they are returned.
+///
 /// # Arguments
 /// * `code` - A string representing the user program.
 ///
-pub fn parse_program(code: &str) -> Program {
-    // return value
-    let mut program = Program {
-        body: Block {
-            constants: Vec::new(),
-            local_variables: Vec::new(),
-            body: Statement::StatementList(Vec::new()),
-        },
-    };
+pub fn parse_program(code: &str) -> Result<Program, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    match parse_program_runner(code, &mut diagnostics) {
+        Ok(program) if diagnostics.is_empty() => Ok(program),
+        Ok(_) => Err(diagnostics),
+        // an error parsing can't carry on after, which hasn't been collected yet
+        Err(SyntaxError(diagnostic)) => {
+            diagnostics.push(diagnostic);
+            Err(diagnostics)
+        }
+    }
+}
+
+// tokens that can start a declaration, or the body of a block after them
+const DECLARATION_STOPS: [&str; 8] = [
+    ";",
+    "LABEL",
+    "CONST",
+    "TYPE",
+    "VAR",
+    "PROCEDURE",
+    "FUNCTION",
+    "BEGIN",
+];
+
+fn parse_program_runner(
+    code: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Program, SyntaxError> {
     let mut i: usize = 0;
+    let mut parameters = Vec::new();
 
-    // this variable holds a single token to view what it is
-    let mut peeker = last_token(code, &mut i);
-    syntax_check(peeker == "PROGRAM", code, i, "Missing PROGRAM keyword");
-    next_token(code, &mut i);
+    // a broken heading is skipped, up to the block
+    let heading = parse_program_heading(code, &mut i, &mut parameters);
+    if recover(heading, code, &mut i, 0, &DECLARATION_STOPS, diagnostics)?.is_none()
+        && last_token(code, &mut i) == ";"
+    {
+        next_token(code, &mut i);
+    }
+
+    let body = parse_block(code, &mut i, diagnostics)?;
 
     syntax_check(
-        is_valid_identifier(&last_token(code, &mut i)),
+        last_token(code, &mut i) == ".",
+        code,
+        i,
+        "P0003",
+        "Invalid program terminator",
+    )?;
+    next_token(code, &mut i);
+    // anything after the . is ignored, but an unclosed comment is still a mistake
+    let rest = last_token(code, &mut i);
+    syntax_check(
+        rest != "{" && rest != "(*",
         code,
         i,
+        "P0004",
+        "Unclosed comment",
+    )?;
+
+    Ok(Program { parameters, body })
+}
+
+/// Parse program heading, up to and including its ";".
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing the start of the program.
+/// * `parameters` - The files named in the heading, which are added to.
+///
+fn parse_program_heading(
+    code: &str,
+    i: &mut usize,
+    parameters: &mut Vec<(String, usize, usize)>,
+) -> Result<(), SyntaxError> {
+    // this variable holds a single token to view what it is
+    let mut peeker = last_token(code, i);
+    syntax_check(
+        peeker == "PROGRAM",
+        code,
+        *i,
+        "P0005",
+        "Missing PROGRAM keyword",
+    )?;
+    next_token(code, i);
+
+    syntax_check(
+        is_valid_identifier(&last_token(code, i)),
+        code,
+        *i,
+        "P0006",
         "Invalid identifier",
-    );
-    next_token(code, &mut i); // This is the program keyword. Currently not used.
+    )?;
+    next_token(code, i); // This is the program keyword. Currently not used.
 
-    peeker = last_token(code, &mut i);
+    peeker = last_token(code, i);
     if peeker == "(" {
+        next_token(code, i);
         while peeker != ")" {
-            peeker = next_token(code, &mut i); // throw away file args for now
+            let start = *i;
+            let parameter = next_token(code, i);
+            syntax_check(
+                is_valid_identifier(&parameter),
+                code,
+                start,
+                "P0006",
+                "Invalid identifier",
+            )?;
+            parameters.push((parameter.clone(), *i - parameter.len(), *i));
+            peeker = next_token(code, i);
+            syntax_check(
+                peeker == "," || peeker == ")",
+                code,
+                *i - peeker.len(),
+                "P0007",
+                "Expected , or )",
+            )?;
         }
     }
 
-    peeker = last_token(code, &mut i);
-    syntax_check(peeker == ";", code, i, "Expected ;");
-    next_token(code, &mut i);
-
-    program.body = parse_block(code, &mut i);
-
-    syntax_check(
-        last_token(code, &mut i) == ".",
-        code,
-        i,
-        "Invalid program terminator",
-    );
-
-    program
+    peeker = last_token(code, i);
+    syntax_check(peeker == ";", code, *i, "P0002", "Expected ;")?;
+    next_token(code, i);
+    Ok(())
 }
 
 /// Parse block.
 ///
+/// Syntax errors in declarations and statements are collected in `diagnostics`, and parsing
+/// carries on after them.
+///
 /// # Arguments
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
+/// * `diagnostics` - The syntax errors found so far.
 ///
-fn parse_block(code: &str, i: &mut usize) -> Block {
+fn parse_block(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Block, SyntaxError> {
     let mut peeker = next_token(code, i);
+    let mut labels = Vec::new();
     let mut constants = Vec::new();
+    let mut types = Vec::new();
     let mut local_variables = Vec::new();
+    let mut procedures: Vec<Procedure> = Vec::new();
+    // headings declared FORWARD, waiting for their body
+    let mut forward_declarations: Vec<Procedure> = Vec::new();
 
+    let mut seen_label = false;
     let mut seen_const = false;
+    let mut seen_type = false;
     let mut seen_var = false;
     // search for body (only required part of a block)
     while peeker != "BEGIN" {
-        // TODO - Parse labels, types, procedures, functions
-        // parse constant block
-        if peeker == "CONST" {
-            syntax_check(!seen_const, code, *i, "Multiple CONST blocks encountered");
-            seen_const = true;
-            while is_valid_identifier(&last_token(code, i)) {
-                let name = next_token(code, i);
-                syntax_check(last_token(code, i) == "=", code, *i, "Expected =");
-                next_token(code, i);
-                let value = parse_expression(code, i);
-                syntax_check(last_token(code, i) == ";", code, *i, "Expected ;");
-                next_token(code, i);
-                constants.push(Constant { name, value });
+        match peeker.as_str() {
+            // parse label block
+            "LABEL" => {
+                if let Err(SyntaxError(diagnostic)) = syntax_check(
+                    !seen_label,
+                    code,
+                    *i,
+                    "P0008",
+                    "Multiple LABEL blocks encountered",
+                ) {
+                    diagnostics.push(diagnostic);
+                }
+                seen_label = true;
+                loop {
+                    let label = next_token(code, i);
+                    let end = *i;
+                    let start = end - label.len();
+                    match label.parse::<u64>() {
+                        Ok(n) => labels.push((n, start, end)),
+                        Err(_) => {
+                            let label = syntax_check(false, code, start, "P0009", "Expected label");
+                            recover(label, code, i, end, &[",", ";"], diagnostics)?;
+                        }
+                    }
+                    peeker = next_token(code, i);
+                    if peeker != "," {
+                        break;
+                    }
+                }
+                // the next declaration is found from where the ; should be
+                let start = *i - peeker.len();
+                let semicolon = syntax_check(peeker == ";", code, start, "P0002", "Expected ;");
+                recover(
+                    semicolon,
+                    code,
+                    i,
+                    start,
+                    &DECLARATION_STOPS[1..],
+                    diagnostics,
+                )?;
             }
-        }
 
-        // parse variable block
-        if peeker == "VAR" {
-            syntax_check(!seen_var, code, *i, "Multiple VAR blocks encountered");
-            seen_var = true;
-            while is_valid_identifier(&last_token(code, i)) {
-                let mut identifiers = Vec::new();
-                identifiers.push(next_token(code, i));
-                while last_token(code, i) == "," {
-                    next_token(code, i);
-                    let identifier = last_token(code, i);
-                    syntax_check(
-                        is_valid_identifier(&identifier),
-                        code,
-                        *i,
-                        "Invalid identifier",
-                    );
-                    next_token(code, i);
-                    identifiers.push(identifier);
+            // parse constant block
+            "CONST" => {
+                if let Err(SyntaxError(diagnostic)) = syntax_check(
+                    !seen_const,
+                    code,
+                    *i,
+                    "P0010",
+                    "Multiple CONST blocks encountered",
+                ) {
+                    diagnostics.push(diagnostic);
                 }
-                syntax_check(last_token(code, i) == ":", code, *i, "Expected : or ,");
-                next_token(code, i);
-                let tipe = parse_type(code, i);
-                syntax_check(last_token(code, i) == ";", code, *i, "Expected ;");
-                next_token(code, i);
-                for identifier in identifiers {
-                    local_variables.push(Variable {
-                        name: identifier,
-                        tipe: tipe.clone(),
-                    });
+                seen_const = true;
+                while is_valid_identifier(&last_token(code, i)) {
+                    let start = *i;
+                    let constant = parse_constant_definition(code, i);
+                    match recover(constant, code, i, start, &DECLARATION_STOPS, diagnostics)? {
+                        Some(constant) => constants.push(constant),
+                        None => skip_semicolon(code, i),
+                    }
+                }
+            }
+
+            // parse type block
+            "TYPE" => {
+                if let Err(SyntaxError(diagnostic)) = syntax_check(
+                    !seen_type,
+                    code,
+                    *i,
+                    "P0011",
+                    "Multiple TYPE blocks encountered",
+                ) {
+                    diagnostics.push(diagnostic);
+                }
+                seen_type = true;
+                while is_valid_identifier(&last_token(code, i)) {
+                    let start = *i;
+                    let definition = parse_type_definition(code, i);
+                    match recover(definition, code, i, start, &DECLARATION_STOPS, diagnostics)? {
+                        Some(definition) => types.push(definition),
+                        None => skip_semicolon(code, i),
+                    }
+                }
+            }
+
+            // parse variable block
+            "VAR" => {
+                if let Err(SyntaxError(diagnostic)) = syntax_check(
+                    !seen_var,
+                    code,
+                    *i,
+                    "P0012",
+                    "Multiple VAR blocks encountered",
+                ) {
+                    diagnostics.push(diagnostic);
+                }
+                seen_var = true;
+                while is_valid_identifier(&last_token(code, i)) {
+                    let start = *i;
+                    let variables = parse_variable_declaration(code, i);
+                    match recover(variables, code, i, start, &DECLARATION_STOPS, diagnostics)? {
+                        Some(variables) => local_variables.extend(variables),
+                        None => skip_semicolon(code, i),
+                    }
+                }
+            }
+
+            // parse procedure or function declaration
+            "PROCEDURE" | "FUNCTION" => {
+                let start = *i;
+                let heading = parse_procedure_heading(code, i, peeker == "FUNCTION");
+                let Some(mut procedure) =
+                    recover(heading, code, i, start, &DECLARATION_STOPS, diagnostics)?
+                else {
+                    // the block of a broken heading is still checked for syntax errors
+                    skip_semicolon(code, i);
+                    if last_token(code, i) == "FORWARD" {
+                        next_token(code, i);
+                        skip_semicolon(code, i);
+                    } else {
+                        parse_block(code, i, diagnostics)?;
+                        skip_semicolon(code, i);
+                    }
+                    peeker = next_token(code, i);
+                    continue;
+                };
+                if last_token(code, i) == "FORWARD" {
+                    next_token(code, i);
+                    let semicolon =
+                        syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;");
+                    if recover(semicolon, code, i, *i, &DECLARATION_STOPS, diagnostics)?.is_some() {
+                        next_token(code, i);
+                    } else {
+                        skip_semicolon(code, i);
+                    }
+                    forward_declarations.push(procedure);
+                } else {
+                    // the body of a forward declared procedure may leave out the heading
+                    if let Some(idx) = forward_declarations
+                        .iter()
+                        .position(|heading| heading.name == procedure.name)
+                    {
+                        let heading = forward_declarations.remove(idx);
+                        if procedure.parameters.is_empty() && procedure.return_type.is_none() {
+                            procedure.parameters = heading.parameters;
+                            procedure.return_type = heading.return_type;
+                        }
+                    }
+                    if peeker == "FUNCTION" && procedure.return_type.is_none() {
+                        diagnostics.push(Diagnostic::syntax(
+                            "P0041",
+                            procedure.start,
+                            procedure.end,
+                            "Function is missing a return type",
+                        ));
+                    }
+                    procedure.body = parse_block(code, i, diagnostics)?;
+                    let semicolon =
+                        syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;");
+                    if recover(semicolon, code, i, *i, &DECLARATION_STOPS, diagnostics)?.is_some() {
+                        next_token(code, i);
+                    } else {
+                        skip_semicolon(code, i);
+                    }
+                    procedures.push(procedure);
                 }
             }
+
+            _ => {
+                // skip to the next declaration
+                let unexpected = syntax_check(
+                    false,
+                    code,
+                    *i - peeker.len(),
+                    "P0013",
+                    "Expected declaration or BEGIN",
+                );
+                recover(
+                    unexpected,
+                    code,
+                    i,
+                    *i,
+                    &DECLARATION_STOPS[1..],
+                    diagnostics,
+                )?;
+            }
         }
         peeker = next_token(code, i);
     }
+    if let Some(heading) = forward_declarations.first() {
+        diagnostics.push(Diagnostic::syntax(
+            "P0042",
+            heading.start,
+            heading.end,
+            "FORWARD declaration has no matching body",
+        ));
+    }
 
-    Block {
+    Ok(Block {
+        labels,
         constants,
+        types,
         local_variables,
-        body: parse_statement_list(code, i),
+        procedures,
+        body: parse_statement_list(code, i, diagnostics)?,
+    })
+}
+
+/// Skips the ";" after a declaration with a syntax error in it, if it's there.
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn skip_semicolon(code: &str, i: &mut usize) {
+    if last_token(code, i) == ";" {
+        next_token(code, i);
+    }
+}
+
+/// Parse constant definition.
+///
+/// identifier "=" expression ";"
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_constant_definition(code: &str, i: &mut usize) -> Result<Constant, SyntaxError> {
+    let name = next_token(code, i);
+    let end = *i;
+    let start = end - name.len();
+    syntax_check(last_token(code, i) == "=", code, *i, "P0014", "Expected =")?;
+    next_token(code, i);
+    let value = parse_expression(code, i)?;
+    syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;")?;
+    next_token(code, i);
+    Ok(Constant {
+        name,
+        value,
+        start,
+        end,
+    })
+}
+
+/// Parse type definition.
+///
+/// identifier "=" type ";"
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_type_definition(code: &str, i: &mut usize) -> Result<TypeDefinition, SyntaxError> {
+    let name = next_token(code, i);
+    syntax_check(last_token(code, i) == "=", code, *i, "P0014", "Expected =")?;
+    next_token(code, i);
+    let tipe = parse_type(code, i)?;
+    syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;")?;
+    next_token(code, i);
+    Ok(TypeDefinition { name, tipe })
+}
+
+/// Parse variable declaration.
+///
+/// identifier ("," identifier)* ":" type ";"
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_variable_declaration(code: &str, i: &mut usize) -> Result<Vec<Variable>, SyntaxError> {
+    let mut identifiers = Vec::new();
+    identifiers.push(next_token(code, i));
+    while last_token(code, i) == "," {
+        next_token(code, i);
+        let identifier = last_token(code, i);
+        syntax_check(
+            is_valid_identifier(&identifier),
+            code,
+            *i,
+            "P0006",
+            "Invalid identifier",
+        )?;
+        next_token(code, i);
+        identifiers.push(identifier);
+    }
+    syntax_check(
+        last_token(code, i) == ":",
+        code,
+        *i,
+        "P0015",
+        "Expected : or ,",
+    )?;
+    next_token(code, i);
+    let tipe = parse_type(code, i)?;
+    syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;")?;
+    next_token(code, i);
+    Ok(identifiers
+        .into_iter()
+        .map(|identifier| Variable {
+            name: identifier,
+            tipe: tipe.clone(),
+        })
+        .collect())
+}
+
+/// Parse procedure or function heading.
+///
+/// "PROCEDURE" identifier ("(" parameters ")")? ";"
+/// "FUNCTION" identifier ("(" parameters ")")? (":" type)? ";"
+/// parameters: "VAR"? identifier ("," identifier)* ":" type (";" parameters)?
+///
+/// The return type of a function may only be left out when it was declared FORWARD.
+/// The body of the returned procedure is empty, and must be filled in by the caller.
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the PROCEDURE or FUNCTION keyword.
+/// * `is_function` - True iff the heading is for a function.
+///
+fn parse_procedure_heading(
+    code: &str,
+    i: &mut usize,
+    is_function: bool,
+) -> Result<Procedure, SyntaxError> {
+    let name = last_token(code, i);
+    syntax_check(
+        is_valid_identifier(&name),
+        code,
+        *i,
+        "P0006",
+        "Invalid identifier",
+    )?;
+    next_token(code, i);
+    let end = *i;
+    let start = end - name.len();
+
+    let mut parameters = Vec::new();
+    if last_token(code, i) == "(" {
+        next_token(code, i);
+        let mut peeker = String::new();
+        while peeker != ")" {
+            let by_reference = last_token(code, i) == "VAR";
+            if by_reference {
+                next_token(code, i);
+            }
+            let mut identifiers = Vec::new();
+            syntax_check(
+                is_valid_identifier(&last_token(code, i)),
+                code,
+                *i,
+                "P0006",
+                "Invalid identifier",
+            )?;
+            identifiers.push(next_token(code, i));
+            while last_token(code, i) == "," {
+                next_token(code, i);
+                syntax_check(
+                    is_valid_identifier(&last_token(code, i)),
+                    code,
+                    *i,
+                    "P0006",
+                    "Invalid identifier",
+                )?;
+                identifiers.push(next_token(code, i));
+            }
+            syntax_check(
+                last_token(code, i) == ":",
+                code,
+                *i,
+                "P0015",
+                "Expected : or ,",
+            )?;
+            next_token(code, i);
+            let tipe = parse_type(code, i)?;
+            for identifier in identifiers {
+                parameters.push(Parameter {
+                    name: identifier,
+                    tipe: tipe.clone(),
+                    by_reference,
+                });
+            }
+            peeker = last_token(code, i);
+            syntax_check(
+                peeker == ";" || peeker == ")",
+                code,
+                *i,
+                "P0016",
+                "Expected ; or )",
+            )?;
+            next_token(code, i);
+        }
     }
+
+    let return_type = if is_function && last_token(code, i) == ":" {
+        next_token(code, i);
+        Some(parse_type(code, i)?)
+    } else {
+        None
+    };
+    syntax_check(last_token(code, i) == ";", code, *i, "P0002", "Expected ;")?;
+    next_token(code, i);
+
+    Ok(Procedure {
+        name,
+        parameters,
+        return_type,
+        body: Block {
+            labels: Vec::new(),
+            constants: Vec::new(),
+            types: Vec::new(),
+            local_variables: Vec::new(),
+            procedures: Vec::new(),
+            body: Statement::StatementList(Vec::new()),
+        },
+        start,
+        end,
+    })
 }
 
 /// Parse type.
 ///
+/// A type is a built-in type, an array "ARRAY" "[" type ("," type)* "]" "OF" type, a set, a file, an enumeration "(" identifier ("," identifier)* ")",
+/// a subrange constant ".." constant, or the name of a type from a TYPE block.
+///
 /// # Arguments
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_type(code: &str, i: &mut usize) -> SuperType {
+fn parse_type(code: &str, i: &mut usize) -> Result<SuperType, SyntaxError> {
+    // a subrange starts with a constant, which can't be told apart from a type name until the ..
+    let mut j = *i;
+    next_token(code, &mut j);
+    let is_named = is_valid_identifier(&last_token(code, i)) && last_token(code, &mut j) != "..";
+    let peeker = last_token(code, i);
+    if !is_builtin_type(&peeker) && peeker != "(" && peeker != "^" && !is_named {
+        let start = parse_expression(code, i)?;
+        syntax_check(
+            last_token(code, i) == "..",
+            code,
+            *i,
+            "P0017",
+            "Expected ..",
+        )?;
+        next_token(code, i);
+        let end = parse_expression(code, i)?;
+        return Ok(SuperType::Subrange(start, end));
+    }
+
     let peeker = next_token(code, i);
-    match peeker.as_str() {
+    Ok(match peeker.as_str() {
         "INTEGER" => SuperType::Integer,
         "BOOLEAN" => SuperType::Boolean,
         "REAL" => SuperType::Real,
@@ -172,93 +782,411 @@ fn parse_type(code: &str, i: &mut usize) -> SuperType {
         "PACKED" => {
             // For now I'm not worried about implementing packed, but functionally
             // it's about the same so I'll leave this
-            syntax_check(last_token(code, i) == "ARRAY", code, *i, "Expected ARRAY");
-            parse_type(code, i)
+            syntax_check(
+                matches!(
+                    last_token(code, i).as_str(),
+                    "ARRAY" | "RECORD" | "SET" | "FILE"
+                ),
+                code,
+                *i,
+                "P0018",
+                "Expected ARRAY, RECORD, SET or FILE",
+            )?;
+            parse_type(code, i)?
         }
         "ARRAY" => {
-            syntax_check(last_token(code, i) == "[", code, *i, "Expected [");
+            syntax_check(last_token(code, i) == "[", code, *i, "P0019", "Expected [")?;
             next_token(code, i);
 
-            let start_idx = parse_expression(code, i);
-            syntax_check(last_token(code, i) == "..", code, *i, "Expected ..");
+            // (index type, start, end) for each dimension
+            let mut indices = Vec::new();
+            loop {
+                *i += code[*i..].len() - code[*i..].trim_start().len();
+                let start = *i;
+                indices.push((parse_type(code, i)?, start, *i));
+                if last_token(code, i) != "," {
+                    break;
+                }
+                next_token(code, i);
+            }
+            syntax_check(last_token(code, i) == "]", code, *i, "P0020", "Expected ]")?;
+            next_token(code, i);
+            syntax_check(
+                last_token(code, i) == "OF",
+                code,
+                *i,
+                "P0021",
+                "Expected OF",
+            )?;
             next_token(code, i);
 
-            let end_idx = parse_expression(code, i);
-            syntax_check(last_token(code, i) == "]", code, *i, "Expected ]");
+            // array[a, b] of t is short for array[a] of array[b] of t
+            let mut tipe = parse_type(code, i)?;
+            for (index, start, end) in indices.into_iter().rev() {
+                tipe = SuperType::Array(Box::new(tipe), Box::new(index), start, end);
+            }
+            tipe
+        }
+        "SET" => {
+            let start = *i - peeker.len();
+            syntax_check(
+                last_token(code, i) == "OF",
+                code,
+                *i,
+                "P0021",
+                "Expected OF",
+            )?;
+            next_token(code, i);
+            let tipe = parse_type(code, i)?;
+            SuperType::Set(Box::new(tipe), start, *i)
+        }
+        "FILE" => {
+            let start = *i - peeker.len();
+            syntax_check(
+                last_token(code, i) == "OF",
+                code,
+                *i,
+                "P0021",
+                "Expected OF",
+            )?;
             next_token(code, i);
-            syntax_check(last_token(code, i) == "OF", code, *i, "Expected OF");
+            let tipe = parse_type(code, i)?;
+            SuperType::File(Box::new(tipe), start, *i)
+        }
+        "^" => {
+            let name = next_token(code, i);
+            syntax_check(
+                is_valid_identifier(&name),
+                code,
+                *i - name.len(),
+                "P0022",
+                "Expected type identifier",
+            )?;
+            SuperType::Pointer(name)
+        }
+        "RECORD" => {
+            let fields = parse_field_list(code, i)?;
+            syntax_check(
+                last_token(code, i) == "END",
+                code,
+                *i,
+                "P0023",
+                "Expected END",
+            )?;
             next_token(code, i);
-            let tipe = parse_type(code, i);
-            SuperType::Array(Box::new(tipe), start_idx, end_idx)
+            SuperType::Record(fields)
+        }
+        "(" => {
+            let mut identifiers = Vec::new();
+            let mut peeker = String::new();
+            while peeker != ")" {
+                syntax_check(
+                    is_valid_identifier(&last_token(code, i)),
+                    code,
+                    *i,
+                    "P0006",
+                    "Invalid identifier",
+                )?;
+                identifiers.push(next_token(code, i));
+                peeker = last_token(code, i);
+                syntax_check(
+                    peeker == "," || peeker == ")",
+                    code,
+                    *i,
+                    "P0007",
+                    "Expected , or )",
+                )?;
+                next_token(code, i);
+            }
+            SuperType::Enumeration(identifiers)
         }
+        _ if is_named => SuperType::Named(peeker.clone(), *i - peeker.len(), *i),
         _ => {
-            report(
+            syntax_check(
+                false,
                 code,
                 *i - peeker.len(),
-                *i,
+                "P0024",
                 "Failed to parse type",
-                "syntax",
-            );
-            panic!()
+            )?;
+            unreachable!()
+        }
+    })
+}
+
+/// Parse field list.
+///
+/// (identifier ("," identifier)* ":" type ";")* variant_part?
+/// variant_part = "CASE" (identifier ":")? type "OF" (constant ("," constant)* ":" "(" field_list ")" ";")*
+/// The last ";" of each list is optional.
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_field_list(code: &str, i: &mut usize) -> Result<FieldList, SyntaxError> {
+    let mut fields = Vec::new();
+    let mut variant = None;
+    let mut peeker = last_token(code, i);
+    while peeker != "END" && peeker != ")" {
+        if peeker == "CASE" {
+            next_token(code, i);
+            // the tag field can be left out, leaving just its type
+            let mut j = *i;
+            next_token(code, &mut j);
+            let tag = if last_token(code, &mut j) == ":" {
+                let tag = next_token(code, i);
+                syntax_check(
+                    is_valid_identifier(&tag),
+                    code,
+                    *i,
+                    "P0006",
+                    "Invalid identifier",
+                )?;
+                next_token(code, i);
+                Some(tag)
+            } else {
+                None
+            };
+            let tag_type = parse_type(code, i)?;
+            syntax_check(
+                last_token(code, i) == "OF",
+                code,
+                *i,
+                "P0021",
+                "Expected OF",
+            )?;
+            next_token(code, i);
+
+            let mut variants = Vec::new();
+            while last_token(code, i) != "END" && last_token(code, i) != ")" {
+                let mut labels = vec![parse_expression(code, i)?];
+                while last_token(code, i) == "," {
+                    next_token(code, i);
+                    labels.push(parse_expression(code, i)?);
+                }
+                syntax_check(last_token(code, i) == ":", code, *i, "P0025", "Expected :")?;
+                next_token(code, i);
+                syntax_check(last_token(code, i) == "(", code, *i, "P0026", "Expected (")?;
+                next_token(code, i);
+                let variant_fields = parse_field_list(code, i)?;
+                syntax_check(last_token(code, i) == ")", code, *i, "P0027", "Expected )")?;
+                next_token(code, i);
+                variants.push((labels, variant_fields));
+                if last_token(code, i) == ";" {
+                    next_token(code, i);
+                }
+            }
+            variant = Some(Box::new(VariantPart {
+                tag,
+                tag_type,
+                variants,
+            }));
+            break;
+        }
+
+        let mut names = Vec::new();
+        loop {
+            syntax_check(
+                is_valid_identifier(&last_token(code, i)),
+                code,
+                *i,
+                "P0006",
+                "Invalid identifier",
+            )?;
+            names.push(next_token(code, i));
+            peeker = next_token(code, i);
+            if peeker != "," {
+                break;
+            }
+        }
+        syntax_check(peeker == ":", code, *i, "P0025", "Expected :")?;
+        let tipe = parse_type(code, i)?;
+        for name in names {
+            fields.push(Variable {
+                name,
+                tipe: tipe.clone(),
+            });
+        }
+        peeker = last_token(code, i);
+        if peeker == ";" {
+            next_token(code, i);
+            peeker = last_token(code, i);
+        } else {
+            break;
+        }
+    }
+    Ok(FieldList { fields, variant })
+}
+
+/// Parse selectors.
+///
+/// ("[" expression ("," expression)* "]" | "." identifier | "^")*
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_selectors(code: &str, i: &mut usize) -> Result<Vec<Selector>, SyntaxError> {
+    let mut selectors = Vec::new();
+    loop {
+        let peeker = last_token(code, i);
+        if peeker == "[" {
+            // a[i, j] is short for a[i][j]
+            next_token(code, i);
+            selectors.push(Selector::Index(parse_expression(code, i)?));
+            while last_token(code, i) == "," {
+                next_token(code, i);
+                selectors.push(Selector::Index(parse_expression(code, i)?));
+            }
+            syntax_check(last_token(code, i) == "]", code, *i, "P0020", "Expected ]")?;
+            next_token(code, i);
+        } else if peeker == "." {
+            next_token(code, i);
+            let field = next_token(code, i);
+            syntax_check(
+                is_valid_identifier(&field),
+                code,
+                *i - field.len(),
+                "P0028",
+                "Invalid field name",
+            )?;
+            selectors.push(Selector::Field(field.clone(), *i - field.len(), *i));
+        } else if peeker == "^" {
+            next_token(code, i);
+            selectors.push(Selector::Dereference(*i - 1, *i));
+        } else {
+            return Ok(selectors);
         }
     }
 }
 
+/// Returns the factor for a variable followed by `selectors`.
+///
+/// A single index keeps its own factor, since that's the common case for arrays.
+///
+/// # Arguments
+/// * `identifier` - The name of the variable.
+/// * `selectors` - The selectors following the name.
+/// * `start` - The start of the variable in the code.
+/// * `end` - The end of the last selector in the code.
+///
+fn make_variable_factor(
+    identifier: String,
+    mut selectors: Vec<Selector>,
+    start: usize,
+    end: usize,
+) -> Factor {
+    if selectors.is_empty() {
+        Factor::Identifier(identifier, Vec::new(), start, end)
+    } else if let [Selector::Index(_)] = selectors.as_slice() {
+        let Some(Selector::Index(index)) = selectors.pop() else {
+            unreachable!()
+        };
+        Factor::ArrayIndex(identifier, index, start, end)
+    } else {
+        Factor::Selection(identifier, selectors, start, end)
+    }
+}
+
 /// Parse statement.
 ///
 /// # Arguments
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_statement(code: &str, i: &mut usize) -> Statement {
+fn parse_statement(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
+    // the empty statement, such as after the last ; of a block
+    if matches!(
+        last_token(code, i).as_str(),
+        ";" | "END" | "UNTIL" | "ELSE" | "OTHERWISE"
+    ) {
+        return Ok(Statement::DoNothing);
+    }
     let peeker = next_token(code, i);
-    if peeker == "BEGIN" {
-        parse_statement_list(code, i)
+    Ok(if peeker == "BEGIN" {
+        parse_statement_list(code, i, diagnostics)?
     } else if peeker == "IF" {
-        parse_if_statement(code, i)
+        parse_if_statement(code, i, diagnostics)?
     } else if peeker == "WHILE" {
-        parse_while_loop(code, i)
+        parse_while_loop(code, i, diagnostics)?
     } else if peeker == "REPEAT" {
-        parse_repeat_loop(code, i)
+        parse_repeat_loop(code, i, diagnostics)?
     } else if peeker == "FOR" {
-        parse_for_loop(code, i)
+        parse_for_loop(code, i, diagnostics)?
+    } else if peeker == "WITH" {
+        parse_with_statement(code, i, diagnostics)?
+    } else if peeker == "CASE" {
+        parse_case_statement(code, i, diagnostics)?
+    } else if peeker == "GOTO" {
+        let label = next_token(code, i);
+        let end = *i;
+        let start = end - label.len();
+        match label.parse::<u64>() {
+            Ok(n) => Statement::Goto(n, start, end),
+            Err(_) => {
+                syntax_check(false, code, start, "P0009", "Expected label")?;
+                unreachable!()
+            }
+        }
+    } else if peeker.parse::<u64>().is_ok() && last_token(code, i) == ":" {
+        let end = *i;
+        let start = end - peeker.len();
+        next_token(code, i);
+        let statement = parse_statement(code, i, diagnostics)?;
+        Statement::LabeledStatement(peeker.parse().unwrap(), Box::new(statement), start, end)
     } else if is_valid_identifier(&peeker) {
         if last_token(code, i) == ":=" {
             let start = *i - peeker.len();
             next_token(code, i);
-            let expression = parse_expression(code, i);
+            let expression = parse_expression(code, i)?;
             let end = *i;
             Statement::Assignment(peeker, expression, start, end)
-        } else if last_token(code, i) == "[" {
+        } else if matches!(last_token(code, i).as_str(), "[" | "." | "^") {
             let start = *i - peeker.len();
+            let selectors = parse_selectors(code, i)?;
+            syntax_check(
+                last_token(code, i) == ":=",
+                code,
+                *i,
+                "P0029",
+                "Expected :=",
+            )?;
             next_token(code, i);
-            let index = parse_expression(code, i);
-            syntax_check(last_token(code, i) == "]", code, *i, "Expected ]");
-            next_token(code, i);
-            syntax_check(last_token(code, i) == ":=", code, *i, "Expected :=");
-            next_token(code, i);
-            let expression = parse_expression(code, i);
+            let expression = parse_expression(code, i)?;
             let end = *i;
-            Statement::ElementAssignment(peeker, index, expression, start, end)
+            match make_variable_factor(peeker, selectors, start, end) {
+                Factor::ArrayIndex(name, index, _, _) => {
+                    Statement::ElementAssignment(name, index, expression, start, end)
+                }
+                Factor::Selection(name, selectors, _, _) => {
+                    Statement::SelectionAssignment(name, selectors, expression, start, end)
+                }
+                _ => unreachable!(),
+            }
         } else {
-            // TODO - There's a difference between these, handle it
             if peeker == "READ" || peeker == "READLN" {
-                *i -= peeker.len();
-                parse_read_call(code, i)
+                parse_read_call(&peeker, code, i)?
+            } else if peeker == "WRITE" || peeker == "WRITELN" || peeker == "STR" {
+                parse_write_call(&peeker, code, i)?
             } else {
-                parse_procedure_call(&peeker, code, i)
+                parse_procedure_call(&peeker, code, i)?
             }
         }
     } else {
-        report(
+        syntax_check(
+            false,
             code,
             *i - peeker.len(),
-            *i,
+            "P0030",
             "Unrecognized statement",
-            "syntax",
-        );
-        panic!()
-    }
+        )?;
+        unreachable!()
+    })
 }
 
 /// Parse statement list.
@@ -270,25 +1198,37 @@ fn parse_statement(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_statement_list(code: &str, i: &mut usize) -> Statement {
+fn parse_statement_list(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
     // return value
     let mut statement_list = Vec::new();
 
-    let mut peeker = last_token(code, i);
-    syntax_check(peeker != "END", code, *i, "Empty statement list");
+    // the list always has a statement, which may be empty
+    let mut peeker = String::new();
     while peeker != "END" {
-        statement_list.push(parse_statement(code, i));
+        // a broken statement is skipped, up to the next one
+        let start = *i;
+        let statement = parse_statement(code, i, diagnostics);
+        if let Some(statement) = recover(statement, code, i, start, &[";", "END"], diagnostics)? {
+            statement_list.push(statement);
+        }
         peeker = last_token(code, i);
-        syntax_check(
+        let separator = syntax_check(
             peeker == ";" || peeker == "END",
             code,
             *i,
+            "P0031",
             "Expected ; or END",
         );
+        let start = *i;
+        recover(separator, code, i, start, &[";", "END"], diagnostics)?;
         peeker = next_token(code, i);
     }
 
-    Statement::StatementList(statement_list)
+    Ok(Statement::StatementList(statement_list))
 }
 
 /// Parse if statement.
@@ -299,30 +1239,40 @@ fn parse_statement_list(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_if_statement(code: &str, i: &mut usize) -> Statement {
+fn parse_if_statement(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
     let condition_start = *i;
-    let condition = parse_expression(code, i);
+    let condition = parse_expression(code, i)?;
     let condition_end = *i;
     let peeker = last_token(code, i);
-    syntax_check(peeker.as_str() == "THEN", code, *i, "Missing THEN after IF");
+    syntax_check(
+        peeker.as_str() == "THEN",
+        code,
+        *i,
+        "P0032",
+        "Missing THEN after IF",
+    )?;
     next_token(code, i);
 
-    let true_body = parse_statement(code, i);
+    let true_body = parse_statement(code, i, diagnostics)?;
 
     // check if it has an ELSE clause
     let false_body = if last_token(code, i) == "ELSE" {
         next_token(code, i);
-        parse_statement(code, i)
+        parse_statement(code, i, diagnostics)?
     } else {
         Statement::DoNothing
     };
-    Statement::IfStatement(
+    Ok(Statement::IfStatement(
         condition,
         Box::new(true_body),
         Box::new(false_body),
         condition_start,
         condition_end,
-    )
+    ))
 }
 
 /// Parse while loop.
@@ -333,18 +1283,33 @@ fn parse_if_statement(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_while_loop(code: &str, i: &mut usize) -> Statement {
+fn parse_while_loop(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
     // parse condition
     let condition_start = *i;
-    let condition = parse_expression(code, i);
+    let condition = parse_expression(code, i)?;
     let condition_end = *i;
     let peeker = last_token(code, i);
 
-    syntax_check(peeker.as_str() == "DO", code, *i, "Missing DO after WHILE");
+    syntax_check(
+        peeker.as_str() == "DO",
+        code,
+        *i,
+        "P0033",
+        "Missing DO after WHILE",
+    )?;
 
     next_token(code, i);
-    let body = parse_statement(code, i);
-    Statement::WhileLoop(condition, Box::new(body), condition_start, condition_end)
+    let body = parse_statement(code, i, diagnostics)?;
+    Ok(Statement::WhileLoop(
+        condition,
+        Box::new(body),
+        condition_start,
+        condition_end,
+    ))
 }
 
 /// Parse repeat loop.
@@ -355,33 +1320,45 @@ fn parse_while_loop(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_repeat_loop(code: &str, i: &mut usize) -> Statement {
+fn parse_repeat_loop(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
     // return value
     let mut statement_list = Vec::new();
 
-    let mut peeker = last_token(code, i);
-    syntax_check(peeker != "UNTIL", code, *i, "Empty statement list");
+    // the list always has a statement, which may be empty
+    let mut peeker = String::new();
     while peeker != "UNTIL" {
-        statement_list.push(parse_statement(code, i));
+        // a broken statement is skipped, up to the next one
+        let start = *i;
+        let statement = parse_statement(code, i, diagnostics);
+        if let Some(statement) = recover(statement, code, i, start, &[";", "UNTIL"], diagnostics)? {
+            statement_list.push(statement);
+        }
         peeker = last_token(code, i);
-        syntax_check(
+        let separator = syntax_check(
             peeker == ";" || peeker == "UNTIL",
             code,
             *i,
+            "P0034",
             "Expected ; or UNTIL",
         );
+        let start = *i;
+        recover(separator, code, i, start, &[";", "UNTIL"], diagnostics)?;
         peeker = next_token(code, i);
     }
 
     let condition_start = *i;
-    let condition = parse_expression(code, i);
+    let condition = parse_expression(code, i)?;
     let condition_end = *i;
-    Statement::RepeatLoop(
+    Ok(Statement::RepeatLoop(
         condition,
         Box::new(Statement::StatementList(statement_list)),
         condition_start,
         condition_end,
-    )
+    ))
 }
 
 /// Parse for loop.
@@ -392,42 +1369,65 @@ fn parse_repeat_loop(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_for_loop(code: &str, i: &mut usize) -> Statement {
+fn parse_for_loop(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
     let name_start = *i;
 
     // ensure valid identifier, save
     let mut peeker = last_token(code, i);
-    syntax_check(is_valid_identifier(&peeker), code, *i, "Invalid identifier");
+    syntax_check(
+        is_valid_identifier(&peeker),
+        code,
+        *i,
+        "P0006",
+        "Invalid identifier",
+    )?;
     next_token(code, i);
     let identifier = peeker;
     let name_end = *i;
 
     // ensure followed by :=
-    syntax_check(last_token(code, i) == ":=", code, *i, "Expected :=");
+    syntax_check(
+        last_token(code, i) == ":=",
+        code,
+        *i,
+        "P0029",
+        "Expected :=",
+    )?;
     next_token(code, i);
 
     // get range
     let range_start = *i;
-    let start = parse_expression(code, i);
+    let start = parse_expression(code, i)?;
     peeker = last_token(code, i);
     syntax_check(
         peeker == "TO" || peeker == "DOWNTO",
         code,
         *i,
+        "P0035",
         "Expected TO or DOWNTO",
-    );
+    )?;
     next_token(code, i);
     let ascending = peeker == "TO";
-    let end = parse_expression(code, i);
+    let end = parse_expression(code, i)?;
     let range_end = *i;
 
     // ensure followed by DO
-    syntax_check(last_token(code, i) == "DO", code, *i, "Expected DO");
+    syntax_check(
+        last_token(code, i) == "DO",
+        code,
+        *i,
+        "P0036",
+        "Expected DO",
+    )?;
     next_token(code, i);
 
     // get body, return
-    let body = parse_statement(code, i);
-    Statement::ForLoop(
+    let body = parse_statement(code, i, diagnostics)?;
+    Ok(Statement::ForLoop(
         identifier,
         name_start,
         name_end,
@@ -437,7 +1437,146 @@ fn parse_for_loop(code: &str, i: &mut usize) -> Statement {
         range_end,
         ascending,
         Box::new(body),
-    )
+    ))
+}
+
+/// Parse case statement.
+///
+/// "CASE" expression "OF" (label ("," label)* ":" statement ";")*
+/// (("OTHERWISE" | "ELSE") statement (";" statement)*)? "END"
+/// label = constant (".." constant)?
+/// The ";" before "OTHERWISE", "ELSE" or "END" is optional.
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_case_statement(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
+    let selector_start = *i;
+    let selector = parse_expression(code, i)?;
+    let selector_end = *i;
+    syntax_check(
+        last_token(code, i) == "OF",
+        code,
+        *i,
+        "P0021",
+        "Expected OF",
+    )?;
+    next_token(code, i);
+
+    let mut arms = Vec::new();
+    let mut otherwise = None;
+    let mut peeker = last_token(code, i);
+    while peeker != "END" {
+        if peeker == "OTHERWISE" || peeker == "ELSE" {
+            next_token(code, i);
+            let mut statement_list = Vec::new();
+            while last_token(code, i) != "END" {
+                statement_list.push(parse_statement(code, i, diagnostics)?);
+                peeker = last_token(code, i);
+                syntax_check(
+                    peeker == ";" || peeker == "END",
+                    code,
+                    *i,
+                    "P0031",
+                    "Expected ; or END",
+                )?;
+                if peeker == ";" {
+                    next_token(code, i);
+                }
+            }
+            otherwise = Some(Box::new(Statement::StatementList(statement_list)));
+            break;
+        }
+
+        let mut labels = Vec::new();
+        loop {
+            // start the label at its first character, so errors don't point at the line before
+            *i += code[*i..].len() - code[*i..].trim_start().len();
+            let first = parse_expression(code, i)?;
+            labels.push(if last_token(code, i) == ".." {
+                next_token(code, i);
+                ExpressionOrRange::Range(first, parse_expression(code, i)?)
+            } else {
+                ExpressionOrRange::Expression(first)
+            });
+            if last_token(code, i) != "," {
+                break;
+            }
+            next_token(code, i);
+        }
+        syntax_check(last_token(code, i) == ":", code, *i, "P0025", "Expected :")?;
+        next_token(code, i);
+        arms.push((labels, parse_statement(code, i, diagnostics)?));
+
+        peeker = last_token(code, i);
+        syntax_check(
+            matches!(peeker.as_str(), ";" | "END" | "OTHERWISE" | "ELSE"),
+            code,
+            *i,
+            "P0031",
+            "Expected ; or END",
+        )?;
+        if peeker == ";" {
+            next_token(code, i);
+            peeker = last_token(code, i);
+        }
+    }
+    next_token(code, i);
+    Ok(Statement::CaseStatement(
+        selector,
+        arms,
+        otherwise,
+        selector_start,
+        selector_end,
+    ))
+}
+
+/// Parse with statement.
+///
+/// "WITH" variable ("," variable)* "DO" statement
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_with_statement(
+    code: &str,
+    i: &mut usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Statement, SyntaxError> {
+    let mut records = Vec::new();
+    loop {
+        let start = *i;
+        let identifier = next_token(code, i);
+        syntax_check(
+            is_valid_identifier(&identifier),
+            code,
+            *i - identifier.len(),
+            "P0006",
+            "Invalid identifier",
+        )?;
+        let selectors = parse_selectors(code, i)?;
+        records.push(make_variable_factor(identifier, selectors, start, *i));
+        if last_token(code, i) != "," {
+            break;
+        }
+        next_token(code, i);
+    }
+    syntax_check(
+        last_token(code, i) == "DO",
+        code,
+        *i,
+        "P0036",
+        "Expected DO",
+    )?;
+    next_token(code, i);
+    let body = parse_statement(code, i, diagnostics)?;
+    Ok(Statement::WithStatement(records, Box::new(body)))
 }
 
 /// Parse procedure call.
@@ -448,15 +1587,20 @@ fn parse_for_loop(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_procedure_call(procedure_identifier: &str, code: &str, i: &mut usize) -> Statement {
+fn parse_procedure_call(
+    procedure_identifier: &str,
+    code: &str,
+    i: &mut usize,
+) -> Result<Statement, SyntaxError> {
     let start = *i - procedure_identifier.len();
 
     syntax_check(
         is_valid_identifier(procedure_identifier),
         code,
         *i - procedure_identifier.len(),
+        "P0006",
         "Invalid identifier",
-    );
+    )?;
 
     let mut arguments: Vec<Expression> = Vec::new();
     let mut peeker = next_token(code, i);
@@ -468,44 +1612,129 @@ fn parse_procedure_call(procedure_identifier: &str, code: &str, i: &mut usize) -
         while peeker != ")" {
             // could be a procedure identifier as well, but a single identifier is a valid
             // expression.
-            arguments.push(parse_expression(code, i));
+            arguments.push(parse_expression(code, i)?);
 
             // remove next comma or )
             peeker = next_token(code, i);
+            syntax_check(
+                peeker == "," || peeker == ")",
+                code,
+                *i - peeker.len(),
+                "P0007",
+                "Expected , or )",
+            )?;
         }
     }
     let end = *i;
-    Statement::ProcedureCall(procedure_identifier.to_string(), arguments, start, end)
+    Ok(Statement::ProcedureCall(
+        procedure_identifier.to_string(),
+        arguments,
+        start,
+        end,
+    ))
+}
+
+/// Parse write call.
+/// This is special since each argument can be followed by a field width, and then a number of
+/// fraction digits. STR writes to a string, so it takes them too.
+///
+/// ("WRITE" | "WRITELN" | "STR") ("(" write_argument ("," write_argument)* ")")?
+/// write_argument = expression (":" expression (":" expression)?)?
+///
+/// # Arguments
+/// * `code` - A string representing the user program.
+/// * `i` - An index within `code` representing a point after the last token processed.
+///
+fn parse_write_call(
+    procedure_identifier: &str,
+    code: &str,
+    i: &mut usize,
+) -> Result<Statement, SyntaxError> {
+    let start = *i - procedure_identifier.len();
+    let mut arguments = Vec::new();
+    let mut peeker = next_token(code, i);
+    if peeker != "(" {
+        *i -= peeker.len();
+    } else {
+        while peeker != ")" {
+            let value = parse_expression(code, i)?;
+            let mut width = None;
+            let mut precision = None;
+            peeker = next_token(code, i);
+            if peeker == ":" {
+                width = Some(parse_expression(code, i)?);
+                peeker = next_token(code, i);
+                if peeker == ":" {
+                    precision = Some(parse_expression(code, i)?);
+                    peeker = next_token(code, i);
+                }
+            }
+            syntax_check(
+                peeker == "," || peeker == ")",
+                code,
+                *i - peeker.len(),
+                "P0007",
+                "Expected , or )",
+            )?;
+            arguments.push((value, width, precision));
+        }
+    }
+    let end = *i;
+    Ok(Statement::WriteCall(
+        procedure_identifier.to_string(),
+        arguments,
+        start,
+        end,
+    ))
 }
 
 /// Parse read call.
 /// This is special since unlike other procedures, it accepts specifically variable names.
 ///
-/// "READ" "(" identifier ("," identifier)* ")"
+/// ("READ" | "READLN") ("(" identifier ("," identifier)* ")")?
 ///
 /// # Arguments
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_read_call(code: &str, i: &mut usize) -> Statement {
-    let start = *i;
-    next_token(code, i);
+fn parse_read_call(
+    procedure_identifier: &str,
+    code: &str,
+    i: &mut usize,
+) -> Result<Statement, SyntaxError> {
+    let start = *i - procedure_identifier.len();
     let mut variable_list = Vec::new();
-    syntax_check(last_token(code, i) == "(", code, *i, "Expected (");
     let mut peeker = next_token(code, i);
-    while peeker != ")" {
-        syntax_check(
-            is_valid_identifier(&last_token(code, i)),
-            code,
-            *i,
-            "Invalid identifier",
-        );
-        variable_list.push(next_token(code, i));
-        // consume comma or )
-        peeker = next_token(code, i);
+    if peeker != "(" {
+        *i -= peeker.len();
+    } else {
+        while peeker != ")" {
+            syntax_check(
+                is_valid_identifier(&last_token(code, i)),
+                code,
+                *i,
+                "P0006",
+                "Invalid identifier",
+            )?;
+            variable_list.push(next_token(code, i));
+            // consume comma or )
+            peeker = next_token(code, i);
+            syntax_check(
+                peeker == "," || peeker == ")",
+                code,
+                *i - peeker.len(),
+                "P0007",
+                "Expected , or )",
+            )?;
+        }
     }
     let end = *i;
-    Statement::ReadCall(variable_list, start, end)
+    Ok(Statement::ReadCall(
+        procedure_identifier.to_string(),
+        variable_list,
+        start,
+        end,
+    ))
 }
 
 /// Parse expression.
@@ -516,10 +1745,10 @@ fn parse_read_call(code: &str, i: &mut usize) -> Statement {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_expression(code: &str, i: &mut usize) -> Expression {
+fn parse_expression(code: &str, i: &mut usize) -> Result<Expression, SyntaxError> {
     let start = *i;
 
-    let operand1 = parse_simple_expression(code, i);
+    let operand1 = parse_simple_expression(code, i)?;
 
     // operator and operand2 are optional
     let operator = if is_equality_operator(&last_token(code, i)) {
@@ -530,19 +1759,19 @@ fn parse_expression(code: &str, i: &mut usize) -> Expression {
     let operand2 = if operator == "NONE" {
         operand1.clone()
     } else {
-        parse_simple_expression(code, i)
+        parse_simple_expression(code, i)?
     };
 
     let end = *i;
 
     // create and return expression
-    Expression {
+    Ok(Expression {
         start,
         end,
         operand1,
         operand2,
         operator,
-    }
+    })
 }
 
 /// Parse simple expression.
@@ -553,7 +1782,7 @@ fn parse_expression(code: &str, i: &mut usize) -> Expression {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_simple_expression(code: &str, i: &mut usize) -> SimpleExpression {
+fn parse_simple_expression(code: &str, i: &mut usize) -> Result<SimpleExpression, SyntaxError> {
     let start = *i;
     // take off + or - if present, set positive appropriately
     let positive = match last_token(code, i).as_str() {
@@ -569,21 +1798,21 @@ fn parse_simple_expression(code: &str, i: &mut usize) -> SimpleExpression {
     };
     let mut operators: Vec<String> = Vec::new();
     let mut operands: Vec<Term> = Vec::new();
-    operands.push(parse_term(code, i));
+    operands.push(parse_term(code, i)?);
 
     while matches!(last_token(code, i).as_str(), "+" | "-" | "OR") {
         operators.push(next_token(code, i));
-        operands.push(parse_term(code, i));
+        operands.push(parse_term(code, i)?);
     }
 
     let end = *i;
-    SimpleExpression {
+    Ok(SimpleExpression {
         start,
         end,
         positive,
         operands,
         operators,
-    }
+    })
 }
 
 /// Parse term.
@@ -594,28 +1823,28 @@ fn parse_simple_expression(code: &str, i: &mut usize) -> SimpleExpression {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_term(code: &str, i: &mut usize) -> Term {
+fn parse_term(code: &str, i: &mut usize) -> Result<Term, SyntaxError> {
     let start = *i;
 
     let mut operators: Vec<String> = Vec::new();
     let mut operands: Vec<Factor> = Vec::new();
     // must be at least one factor
-    operands.push(parse_factor(code, i));
+    operands.push(parse_factor(code, i)?);
     while matches!(
         last_token(code, i).as_str(),
         "*" | "/" | "DIV" | "MOD" | "AND"
     ) {
         operators.push(next_token(code, i));
-        operands.push(parse_factor(code, i));
+        operands.push(parse_factor(code, i)?);
     }
 
     let end = *i;
-    Term {
+    Ok(Term {
         start,
         end,
         operands,
         operators,
-    }
+    })
 }
 
 /// Parse factor.
@@ -626,26 +1855,30 @@ fn parse_term(code: &str, i: &mut usize) -> Term {
 /// * `code` - A string representing the user program.
 /// * `i` - An index within `code` representing a point after the last token processed.
 ///
-fn parse_factor(code: &str, i: &mut usize) -> Factor {
+fn parse_factor(code: &str, i: &mut usize) -> Result<Factor, SyntaxError> {
     let start = *i;
     let mut peeker = next_token(code, i);
 
-    if is_valid_identifier(&peeker) {
+    Ok(if is_valid_identifier(&peeker) {
         let identifier = peeker.clone();
-        if last_token(code, i) == "[" {
-            next_token(code, i);
-            let index = parse_expression(code, i);
-            syntax_check(last_token(code, i) == "]", code, *i, "Expected ]");
-            next_token(code, i);
+        if matches!(last_token(code, i).as_str(), "[" | "." | "^") {
+            let selectors = parse_selectors(code, i)?;
             let end = *i;
-            Factor::ArrayIndex(identifier, index, start, end)
+            make_variable_factor(identifier, selectors, start, end)
         } else {
             let mut arguments: Vec<Expression> = Vec::new();
             if last_token(code, i) == "(" {
                 next_token(code, i);
                 while peeker != ")" {
-                    arguments.push(parse_expression(code, i));
+                    arguments.push(parse_expression(code, i)?);
                     peeker = next_token(code, i);
+                    syntax_check(
+                        peeker == "," || peeker == ")",
+                        code,
+                        *i - peeker.len(),
+                        "P0007",
+                        "Expected , or )",
+                    )?;
                 }
             }
             let end = *i;
@@ -653,31 +1886,42 @@ fn parse_factor(code: &str, i: &mut usize) -> Factor {
         }
     // expression in parentheses
     } else if peeker == "(" {
-        let factor = Factor::Parenthetical(parse_expression(code, i));
+        let factor = Factor::Parenthetical(parse_expression(code, i)?);
         peeker = last_token(code, i);
-        syntax_check(peeker == ")", code, *i, "Unclosed (");
+        syntax_check(peeker == ")", code, *i, "P0037", "Unclosed (")?;
         next_token(code, i);
         factor
 
     // negated factor
     } else if peeker == "NOT" {
-        let factor = parse_factor(code, i);
+        let factor = parse_factor(code, i)?;
         let end = *i;
         Factor::NegatedFactor(Box::new(factor), start, end)
 
     // list of expressions / ranges
     } else if peeker == "[" {
         let mut expression_list: Vec<ExpressionOrRange> = Vec::new();
+        // the empty set
+        if last_token(code, i) == "]" {
+            peeker = next_token(code, i);
+        }
         while peeker != "]" {
-            let expression1 = parse_expression(code, i);
+            let expression1 = parse_expression(code, i)?;
             peeker = next_token(code, i);
             expression_list.push(if peeker.as_str() == ".." {
-                let pusher = ExpressionOrRange::Range(expression1, parse_expression(code, i));
-                next_token(code, i);
+                let pusher = ExpressionOrRange::Range(expression1, parse_expression(code, i)?);
+                peeker = next_token(code, i);
                 pusher
             } else {
                 ExpressionOrRange::Expression(expression1)
             });
+            syntax_check(
+                peeker == "," || peeker == "]",
+                code,
+                *i - peeker.len(),
+                "P0038",
+                "Expected , or ]",
+            )?;
         }
         Factor::List(expression_list)
 
@@ -696,9 +1940,8 @@ fn parse_factor(code: &str, i: &mut usize) -> Factor {
                                 f
                             }
                             Err(_) => {
-                                peeker = next_token(code, i);
-                                report(code, *i - peeker.len(), *i, "Expected number", "syntax");
-                                panic!()
+                                syntax_check(false, code, *i, "P0039", "Expected number")?;
+                                unreachable!()
                             }
                         };
                         let mut power = 1;
@@ -716,47 +1959,52 @@ fn parse_factor(code: &str, i: &mut usize) -> Factor {
                 }
                 Err(_) => {
                     syntax_check(
-                        (peeker.starts_with('\'') && peeker.ends_with('\''))
+                        (peeker.len() >= 2 && peeker.starts_with('\'') && peeker.ends_with('\''))
                             || peeker.ends_with('E'),
                         code,
                         *i - peeker.len(),
+                        "P0040",
                         "Failed to parse factor",
-                    );
+                    )?;
 
                     if peeker.ends_with('E') {
-                        let mantissa =
-                            peeker[0..peeker.len() - 1]
-                                .parse::<u64>()
-                                .unwrap_or_else(|_| {
-                                    report(
-                                        code,
-                                        *i - peeker.len(),
-                                        *i,
-                                        "Failed to parse factor",
-                                        "syntax",
-                                    );
-                                    0
-                                });
-                        let exponent = if last_token(code, i) == "-" {
+                        let mantissa = peeker[0..peeker.len() - 1].parse::<u64>();
+                        syntax_check(
+                            mantissa.is_ok(),
+                            code,
+                            *i - peeker.len(),
+                            "P0040",
+                            "Failed to parse factor",
+                        )?;
+                        let sign = if last_token(code, i) == "-" {
                             next_token(code, i);
                             -1
                         } else {
                             1
-                        } * next_token(code, i).parse::<u16>().unwrap_or_else(|_| {
-                            report(
-                                code,
-                                *i - peeker.len(),
-                                *i,
-                                "Failed to parse factor",
-                                "syntax",
-                            );
-                            0
-                        }) as i16;
+                        };
+                        let magnitude = last_token(code, i).parse::<u16>();
+                        syntax_check(
+                            magnitude.is_ok(),
+                            code,
+                            *i,
+                            "P0040",
+                            "Failed to parse factor",
+                        )?;
+                        next_token(code, i);
+                        let mantissa = mantissa.unwrap();
+                        let exponent = sign * magnitude.unwrap() as i16;
                         Factor::Constant(UnsignedConstant::UnsignedReal(
                             mantissa as f64 * 10f64.powf(exponent as f64),
                         ))
                     } else {
                         let quote = &peeker[1..peeker.len() - 1]; // remove the ''
+                        syntax_check(
+                            quote.len() <= 255,
+                            code,
+                            *i - peeker.len(),
+                            "P0043",
+                            "String longer than 255 characters",
+                        )?;
                         if quote.len() == 1 {
                             let ch = quote.chars().next().unwrap();
                             if ch.is_ascii() {
@@ -771,7 +2019,7 @@ fn parse_factor(code: &str, i: &mut usize) -> Factor {
                 }
             }
         }
-    }
+    })
 }
 
 /// Returns "true" iff `token` is an equality operator, given by the equality_operators vector.
@@ -781,10 +2029,23 @@ fn parse_factor(code: &str, i: &mut usize) -> Factor {
 ///
 fn is_equality_operator(token: &str) -> bool {
     // set up equality operators
-    let equality_operators = vec!["<", "<=", "=", "<>", ">=", ">", "IN"];
+    let equality_operators = ["<", "<=", "=", "<>", ">=", ">", "IN"];
     equality_operators.contains(&token)
 }
 
+/// Returns "true" iff `token` names a type which doesn't need to be declared.
+///
+/// # Arguments
+/// * `token` - A string representing the token to test.
+///
+fn is_builtin_type(token: &str) -> bool {
+    let builtin_types = [
+        "INTEGER", "BOOLEAN", "REAL", "CHAR", "STRING", "TEXT", "PACKED", "ARRAY", "RECORD", "SET",
+        "FILE",
+    ];
+    builtin_types.contains(&token)
+}
+
 /// Returns "true" iff `token` is a valid identifier, and not reserved.
 ///
 /// # Arguments
@@ -794,7 +2055,11 @@ fn is_valid_identifier(token: &str) -> bool {
     let mut is_valid = true;
 
     // ensure that first character is a letter A-Z
-    if !token.chars().next().unwrap().is_ascii_alphabetic() {
+    if !token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+    {
         is_valid = false;
     } else {
         for c in token.chars().skip(1) {
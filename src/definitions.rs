@@ -1,125 +1,130 @@
-use std::process;
-
-/// Reports an error to the user.
+/// Finds the line and character index (both from 0) of a position in the user program.
 ///
 /// # Arguments
 ///
 /// * `code` - A string representing the user program.
-/// * `start` - Start of erroneous section of code. (first char)
-/// * `end` - End of erroneous section of code. (after last char)
-/// * `err` - The error to report to the user.
-/// * `variant` - "syntax" (in which case panic), "error", or "warning"
-///
-/// # Examples
-///
-/// ```
-/// let code = "PROGRAM a BEGIN END;\n";
-/// report(code, 10, 15, "Expected ;", "syntax");
-///
-/// # Output:
+/// * `start` - Position in the code, counted in chars.
 ///
-/// Syntax error at line 1, character 10.
-/// Expected ;
-/// PROGRAM a BEGIN END
-///           ^^^^^
-///
-/// ```
-///
-pub fn report(code: &str, start: usize, end: usize, err: &str, variant: &str) {
-    assert!(end <= code.len(), "Invalid code index.");
-    assert!(start < end, "`start` must be less than `end`");
-
+pub fn get_position(code: &str, start: usize) -> (usize, usize) {
     let mut line_idx = 0;
     let mut char_idx = 0;
-    let mut chars_left = start;
-
-    // find the line and char index of start
-    for (_, c) in code.char_indices() {
-        if chars_left == 0 {
-            break;
-        }
+    for c in code.chars().take(start) {
         if c == '\n' {
             line_idx += 1;
             char_idx = 0;
         } else {
             char_idx += 1;
         }
-        chars_left -= 1;
-    }
-    // find index of end of the line after end
-    let mut end_of_line_idx: usize = end;
-    while end_of_line_idx < code.len() && code.chars().nth(end_of_line_idx).unwrap() != '\n' {
-        end_of_line_idx += 1;
-    }
-
-    let mut block = String::from(&code[start - char_idx..end_of_line_idx]);
-
-    block.insert_str(char_idx + end - start, "\x1b[38;5;208m"); // return to orange after error
-    block.insert_str(char_idx, "\x1b[31m"); // switch to red for error
-
-    match variant {
-        "syntax" => print!("\x1b[31m\nSyntax error \x1b[0m"), // red
-        "error" => print!("\x1b[31m\nError \x1b[0m"),         // red
-        "warning" => print!("\x1b[33m\nWarning \x1b[0m"),     // yellow
-        _ => panic!("Unknown error"),
-    }
-
-    println!(
-        "{}",
-        &format!(
-            "\
-            at line {}, character {}:\n\
-            {}\n\
-            \x1b[38;5;208m{}\x1b[0m",
-            line_idx + 1,
-            char_idx + 1,
-            err,
-            block
-        )
-    );
-
-    if variant == "syntax" {
-        process::exit(1);
     }
+    (line_idx, char_idx)
 }
 
 // these definitions are from Peter Grogono's Programming in Pascal (1978)
 
 pub struct Program {
+    // files named in the program heading, (name, start, end)
+    pub parameters: Vec<(String, usize, usize)>,
     pub body: Block,
 }
 pub struct Block {
+    // (label, start, end)
+    pub labels: Vec<(u64, usize, usize)>,
     pub constants: Vec<Constant>,
+    pub types: Vec<TypeDefinition>,
     pub local_variables: Vec<Variable>,
+    pub procedures: Vec<Procedure>,
     pub body: Statement,
 }
 
+// a function is a procedure with a return type
+pub struct Procedure {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<SuperType>,
+    pub body: Block,
+    // location of the name in the heading
+    pub start: usize,
+    pub end: usize,
+}
+
 pub struct Constant {
     pub name: String,
     pub value: Expression,
+    // location of the name
+    pub start: usize,
+    pub end: usize,
 }
 
+pub struct TypeDefinition {
+    pub name: String,
+    pub tipe: SuperType,
+}
+
+#[derive(Clone)]
 pub struct Variable {
     pub name: String,
     pub tipe: SuperType, // misspelt since Rust doesn't allow "type"
 }
 
+// fields of a record, followed by its variant part if it has one
+#[derive(Clone)]
+pub struct FieldList {
+    pub fields: Vec<Variable>,
+    pub variant: Option<Box<VariantPart>>,
+}
+
+// CASE tag : type OF labels : (fields); ...
+// every variant starts at the same offset, so they share memory
+#[derive(Clone)]
+pub struct VariantPart {
+    // tag field, if the variant part has one
+    pub tag: Option<String>,
+    pub tag_type: SuperType,
+    // (case labels, fields)
+    pub variants: Vec<(Vec<Expression>, FieldList)>,
+}
+
+#[derive(Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub tipe: SuperType,
+    pub by_reference: bool, // VAR parameter
+}
+
 #[derive(Clone, PartialEq)]
 pub enum Type {
     Integer,
     Boolean,
     Real,
     Char,
-    Stryng, // misspelt since Rust doesn't allow "String"
+    // misspelt since Rust doesn't allow "String"
+    // stored as a length byte followed by up to 255 characters
+    Stryng,
     Text,
-    // (type, start index, end index) (why tf does pascal allow this)
-    // TODO - Pascal arrays are way more complex. This is not sufficient.
-    Array(Box<Type>, isize, isize),
+    // (element type, index type, first index, last index), indices are ordinal numbers
+    // arrays with more than one dimension are arrays of arrays
+    Array(Box<Type>, Box<Type>, isize, isize),
+    // (identifiers), valued 0, 1, 2... in order
+    Enumeration(Vec<String>),
+    // (host type, first value, last value), values are ordinal numbers
+    Subrange(Box<Type>, i64, i64),
+    // (fields as (name, type, offset), size)
+    Record(Vec<(String, Type, usize)>, usize),
+    // (name of the type pointed to), which may be declared after the pointer type
+    // NIL points to the type named ""
+    Pointer(String),
+    // (element type), elements are stored as bits 0 to 255
+    // the empty set [] has elements of type Undefined
+    Set(Box<Type>),
+    // (element type), stored as the FILE pointer, the name given by ASSIGN, whether the buffer
+    // holds an element, then the buffer f^ at offset 24
+    File(Box<Type>),
     Undefined,
 }
 
 // for AST, Compiler changes to Type
 #[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum SuperType {
     Integer,
     Boolean,
@@ -127,10 +132,26 @@ pub enum SuperType {
     Char,
     Stryng,
     Text,
-    Array(Box<SuperType>, Expression, Expression),
+    // (element type, index type, index start, index end)
+    Array(Box<SuperType>, Box<SuperType>, usize, usize),
+    // (identifiers)
+    Enumeration(Vec<String>),
+    // (first value, last value)
+    Subrange(Expression, Expression),
+    // type identifier declared in a TYPE block
+    // (identifier, start, end)
+    Named(String, usize, usize),
+    Record(FieldList),
+    // (name of the type pointed to)
+    Pointer(String),
+    // (element type, start, end)
+    Set(Box<SuperType>, usize, usize),
+    // (element type, start, end)
+    File(Box<SuperType>, usize, usize),
 }
 
 #[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Statement {
     DoNothing, // this is for empty blocks
 
@@ -140,16 +161,46 @@ pub enum Statement {
     // this is for array indexing
     // (variable, index expression, expression, start, end
     ElementAssignment(String, Expression, Expression, usize, usize),
+    // this is for record fields, or anything else after the variable name
+    // (variable, selectors, expression, start, end)
+    SelectionAssignment(String, Vec<Selector>, Expression, usize, usize),
     // (identifier, arguments, start, end)
     ProcedureCall(String, Vec<Expression>, usize, usize),
-    // (variables, start, end)
-    ReadCall(Vec<String>, usize, usize), // this is its own thing since it can't take expressions...
+    // arguments may have a field width, and reals a number of fraction digits after that
+    // STR is written the same way, with the string variable last
+    // (identifier, arguments as (value, width, fraction digits), start, end)
+    WriteCall(
+        String,
+        Vec<(Expression, Option<Expression>, Option<Expression>)>,
+        usize,
+        usize,
+    ),
+    // READLN also skips the rest of the line
+    // (identifier, variables, start, end)
+    ReadCall(String, Vec<String>, usize, usize), // this is its own thing since it can't take expressions...
     // (condition, case:true, case:false, condition_start, condition_end)
     IfStatement(Expression, Box<Statement>, Box<Statement>, usize, usize),
     // (condition, body, condition_start, condition_end)
     WhileLoop(Expression, Box<Statement>, usize, usize),
     // (condition, body, condition_start, condition_end)
     RepeatLoop(Expression, Box<Statement>, usize, usize),
+    // a statement prefixed by a label declared in a LABEL block
+    // (label, statement, start, end)
+    LabeledStatement(u64, Box<Statement>, usize, usize),
+    // (label, start, end)
+    Goto(u64, usize, usize),
+    // arms have one or more constant labels or ranges of them, and OTHERWISE is optional
+    // (selector, arms as (labels, statement), otherwise, selector_start, selector_end)
+    CaseStatement(
+        Expression,
+        Vec<(Vec<ExpressionOrRange>, Statement)>,
+        Option<Box<Statement>>,
+        usize,
+        usize,
+    ),
+    // records are Identifier, ArrayIndex or Selection factors
+    // (records, body)
+    WithStatement(Vec<Factor>, Box<Statement>),
     // (identifier, identifier_start, identifier_end, starting expression, ending expression, range_start, range_end, ascending?, statement)
     ForLoop(
         String,
@@ -195,17 +246,33 @@ pub struct Term {
     pub operators: Vec<String>,
 }
 #[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Factor {
     Constant(UnsignedConstant),
     // this could be a function call or variable name
     // (identifier, arguments, start, end)
     Identifier(String, Vec<Expression>, usize, usize),
     ArrayIndex(String, Expression, usize, usize),
+    // (identifier, selectors, start, end)
+    Selection(String, Vec<Selector>, usize, usize),
     Parenthetical(Expression),
     // (factor, start, end)
     NegatedFactor(Box<Factor>, usize, usize),
+    // set constructor
     List(Vec<ExpressionOrRange>),
 }
+// selects a component of a variable
+#[derive(Clone)]
+pub enum Selector {
+    // "[" expression "]"
+    Index(Expression),
+    // "." identifier
+    // (identifier, start, end)
+    Field(String, usize, usize),
+    // "^"
+    // (start, end)
+    Dereference(usize, usize),
+}
 #[derive(Clone)]
 pub enum ExpressionOrRange {
     Expression(Expression),
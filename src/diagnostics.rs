@@ -0,0 +1,308 @@
+use crate::definitions::get_position;
+
+/// How bad a diagnostic is. Syntax errors and errors stop compilation, warnings don't.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Syntax,
+    Error,
+    Warning,
+}
+
+impl Severity {
+    /// Returns the name of the severity, as used in JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Syntax => "syntax",
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem found in the user program.
+///
+/// Codes are stable, so tools can match on them rather than the message. Syntax errors are
+/// numbered from P0001, other errors from P1001 and warnings from P2001.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    // primary span, from the first char to after the last char
+    pub start: usize,
+    pub end: usize,
+    // other spans related to the problem, (start, end, message)
+    pub labels: Vec<(usize, usize, String)>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with no labels or notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `severity` - How bad the problem is.
+    /// * `code` - The stable code of the problem, such as "P1012".
+    /// * `start` - Start of erroneous section of code. (first char)
+    /// * `end` - End of erroneous section of code. (after last char)
+    /// * `message` - The problem to report to the user.
+    ///
+    pub fn new(
+        severity: Severity,
+        code: &'static str,
+        start: usize,
+        end: usize,
+        message: &str,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            message: message.to_string(),
+            start,
+            end,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn syntax(code: &'static str, start: usize, end: usize, message: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Syntax, code, start, end, message)
+    }
+
+    pub fn error(code: &'static str, start: usize, end: usize, message: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, start, end, message)
+    }
+
+    pub fn warning(code: &'static str, start: usize, end: usize, message: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, start, end, message)
+    }
+
+    /// Adds a secondary span, pointing at code related to the problem.
+    pub fn with_label(mut self, start: usize, end: usize, message: &str) -> Diagnostic {
+        self.labels.push((start, end, message.to_string()));
+        self
+    }
+
+    /// Adds a note, such as a hint for fixing the problem.
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Adds a "Did you mean X?" note if one of the candidates is close to a misspelled name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name that couldn't be found.
+    /// * `candidates` - The names that could have been meant.
+    ///
+    pub fn with_suggestion(self, name: &str, candidates: &[String]) -> Diagnostic {
+        // allow roughly one typo per three chars, so short names don't match everything
+        let max_distance = name.len().max(3) / 3;
+        let closest = candidates
+            .iter()
+            .filter(|candidate| candidate.as_str() != name)
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            // ties go to the first name alphabetically, so the note is the same on every run
+            .min();
+        match closest {
+            Some((_, candidate)) => self.with_note(&format!("Did you mean {}?", candidate)),
+            None => self,
+        }
+    }
+}
+
+// the number of single char insertions, deletions, substitutions and swaps of neighbours turning
+// `a` into `b`, where no part is edited twice (the optimal string alignment distance)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before_previous = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1];
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            let mut distance = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(before_previous[j - 1] + 1);
+            }
+            current.push(distance);
+        }
+        before_previous = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// Returns the number of diagnostics of a severity.
+///
+/// # Arguments
+///
+/// * `diagnostics` - The diagnostics to count.
+/// * `severity` - The severity to count.
+///
+pub fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == severity)
+        .count()
+}
+
+/// Renders a diagnostic as colored text for a terminal.
+///
+/// # Arguments
+///
+/// * `diagnostic` - The diagnostic to render.
+/// * `code` - A string representing the user program.
+///
+/// # Examples
+///
+/// ```
+/// let code = "PROGRAM a BEGIN END;\n";
+/// print!("{}", render_human(&Diagnostic::syntax("P0003", 10, 15, "Expected ;"), code));
+///
+/// # Output:
+///
+/// Syntax error P0003 at line 1, character 11:
+/// Expected ;
+/// PROGRAM a BEGIN END
+///           ^^^^^
+///
+/// ```
+///
+pub fn render_human(diagnostic: &Diagnostic, code: &str) -> String {
+    let mut text = match diagnostic.severity {
+        Severity::Syntax => "\x1b[31m\nSyntax error \x1b[0m", // red
+        Severity::Error => "\x1b[31m\nError \x1b[0m",         // red
+        Severity::Warning => "\x1b[33m\nWarning \x1b[0m",     // yellow
+    }
+    .to_string();
+    text.push_str(diagnostic.code);
+    if diagnostic.start < diagnostic.end {
+        text.push(' ');
+        text.push_str(&render_span(
+            code,
+            diagnostic.start,
+            diagnostic.end,
+            &diagnostic.message,
+        ));
+    } else {
+        // there's no code to point at, such as in an empty program
+        text.push_str(&format!(":\n{}\n", diagnostic.message));
+    }
+    for (start, end, message) in &diagnostic.labels {
+        text.push_str("\x1b[36mRelated \x1b[0m"); // cyan
+        text.push_str(&render_span(code, *start, *end, message));
+    }
+    for note in &diagnostic.notes {
+        text.push_str(&format!("\x1b[36mNote:\x1b[0m {}\n", note));
+    }
+    text
+}
+
+// the location, message and highlighted lines of a span
+fn render_span(code: &str, start: usize, end: usize, message: &str) -> String {
+    assert!(end <= code.len(), "Invalid code index.");
+    assert!(start < end, "`start` must be less than `end`");
+
+    let (line_idx, char_idx) = get_position(code, start);
+    // find index of end of the line after end
+    let mut end_of_line_idx: usize = end;
+    while end_of_line_idx < code.len() && code.chars().nth(end_of_line_idx).unwrap() != '\n' {
+        end_of_line_idx += 1;
+    }
+
+    let mut block = String::from(&code[start - char_idx..end_of_line_idx]);
+
+    block.insert_str(char_idx + end - start, "\x1b[38;5;208m"); // return to orange after error
+    block.insert_str(char_idx, "\x1b[31m"); // switch to red for error
+
+    format!(
+        "\
+        at line {}, character {}:\n\
+        {}\n\
+        \x1b[38;5;208m{}\x1b[0m\n",
+        line_idx + 1,
+        char_idx + 1,
+        message,
+        block
+    )
+}
+
+/// Renders a diagnostic as a single line of JSON, for tools that read the compiler's output.
+///
+/// Spans have byte offsets into the program, and lines and columns counted from 1.
+///
+/// # Arguments
+///
+/// * `diagnostic` - The diagnostic to render.
+/// * `code` - A string representing the user program.
+///
+/// # Examples
+///
+/// ```
+/// let code = "PROGRAM a BEGIN END;\n";
+/// println!("{}", render_json(&Diagnostic::syntax("P0003", 10, 15, "Expected ;"), code));
+///
+/// # Output:
+///
+/// {"severity":"syntax","code":"P0003","message":"Expected ;","span":{"start":10,"end":15,"line":1,"column":11},"labels":[],"notes":[]}
+///
+/// ```
+///
+pub fn render_json(diagnostic: &Diagnostic, code: &str) -> String {
+    let labels: Vec<String> = diagnostic
+        .labels
+        .iter()
+        .map(|(start, end, message)| {
+            format!(
+                "{{\"span\":{},\"message\":{}}}",
+                json_span(code, *start, *end),
+                json_string(message)
+            )
+        })
+        .collect();
+    let notes: Vec<String> = diagnostic
+        .notes
+        .iter()
+        .map(|note| json_string(note))
+        .collect();
+    format!(
+        "{{\"severity\":\"{}\",\"code\":\"{}\",\"message\":{},\"span\":{},\"labels\":[{}],\"notes\":[{}]}}",
+        diagnostic.severity.name(),
+        diagnostic.code,
+        json_string(&diagnostic.message),
+        json_span(code, diagnostic.start, diagnostic.end),
+        labels.join(","),
+        notes.join(",")
+    )
+}
+
+fn json_span(code: &str, start: usize, end: usize) -> String {
+    let (line_idx, char_idx) = get_position(code, start);
+    format!(
+        "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}",
+        start,
+        end,
+        line_idx + 1,
+        char_idx + 1
+    )
+}
+
+// quotes and escapes a string for JSON
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
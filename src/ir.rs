@@ -0,0 +1,332 @@
+use std::fmt;
+
+// the intermediate representation that semantic analysis produces and backends lower: each
+// routine is three-address code over basic blocks, with every name resolved to a stack slot and
+// every value given a machine kind
+
+// a value computed while running, which is assigned once in straight-line code but may be
+// assigned on several paths that meet
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Temp(pub u32);
+
+// memory in a routine's stack frame, holding a variable or an intermediate string or set
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Slot(pub u32);
+
+// the start of a basic block, unique in the whole program
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Label(pub u32);
+
+// what a temporary holds, which decides the registers and instructions used for it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    // chars and booleans
+    Byte,
+    // integers, enumerations, pointers and addresses
+    Word,
+    Real,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operand {
+    Temp(Temp),
+    Integer(i64),
+    Real(f64),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Address {
+    // `offset` bytes into a slot of the routine `hops` static links out, where 0 is the current
+    // routine
+    Slot {
+        hops: usize,
+        slot: Slot,
+        offset: isize,
+    },
+    // `offset` bytes from the address held by the operand
+    Pointer(Operand, isize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOperator {
+    Negate,
+    // bitwise
+    Not,
+    // byte to word, with zeros
+    Extend,
+    // word to its lowest byte
+    Narrow,
+    // word to real
+    Convert,
+    // real to word, rounding towards zero
+    Truncate,
+    // of a real
+    Absolute,
+    SquareRoot,
+    // 1 iff negating the word overflows
+    NegateOverflows,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    // of words, rounding towards zero, or of reals
+    Divide,
+    // of words, with the sign of the dividend
+    Remainder,
+    And,
+    Or,
+    Xor,
+    // signed
+    Minimum,
+    Maximum,
+    UnsignedMinimum,
+    ShiftLeft,
+    // with zeros
+    ShiftRight,
+    // 1 iff the operation on words overflows
+    AddOverflows,
+    SubtractOverflows,
+    MultiplyOverflows,
+}
+
+// comparisons of bytes and words, which are signed from Less to GreaterEqual and unsigned from
+// Below to AboveEqual. Reals are ordered by the unsigned conditions, where an unordered comparison
+// (with a NaN) counts as below and equal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Below,
+    BelowEqual,
+    Above,
+    AboveEqual,
+}
+
+// the files the runtime provides
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stream {
+    Input,
+    Output,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Callee {
+    // a routine of the program, given the frame of the routine it's declared in, if it isn't the
+    // main program
+    Routine(String, Option<Operand>),
+    // a function of the runtime
+    Runtime(&'static str),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Instruction {
+    Move(Temp, Operand),
+    Unary(Temp, UnaryOperator, Operand),
+    // the kind of the result is the kind of both operands, except for overflow tests, which give
+    // a byte
+    Binary(Temp, BinaryOperator, Operand, Operand),
+    // sets the byte to 1 iff the condition holds between operands of the kind
+    Compare(Temp, Condition, Kind, Operand, Operand),
+    Load(Temp, Address),
+    Store(Kind, Address, Operand),
+    LoadAddress(Temp, Address),
+    // the address of the frame of the routine `hops` static links out
+    Frame(Temp, usize),
+    // the address of constant bytes
+    Data(Temp, Vec<u8>),
+    Stream(Temp, Stream),
+    // copies bytes from the second address to the first, in ascending order
+    Copy(Operand, Operand, Operand),
+    // sets bytes at the address to the value
+    Fill(Operand, u8, usize),
+    // arguments are classified by their kinds, and the result has the kind of its temporary
+    Call(Option<Temp>, Callee, Vec<Operand>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Terminator {
+    Jump(Label),
+    // to the first label iff the byte isn't 0
+    Branch(Operand, Label, Label),
+    // to the label at index (word - minimum), or the default if it's outside the table
+    Switch(Operand, i64, Vec<Label>, Label),
+    Return(Option<Operand>),
+    // stops the program with the message, followed by the text at the address if there is one
+    Error(String, Option<Operand>),
+    // jumps to a label of the routine `hops` static links out, discarding the frames in between
+    Unwind(usize, Label),
+}
+
+pub struct Block {
+    pub label: Label,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+pub struct Routine {
+    // assembly name
+    pub name: String,
+    // index of the routine it's declared in, which comes before it in the program
+    pub parent: Option<usize>,
+    // temporaries holding the arguments when the routine starts
+    pub parameters: Vec<Temp>,
+    // kind of each temporary
+    pub temps: Vec<Kind>,
+    // size and alignment of each slot
+    pub slots: Vec<(usize, usize)>,
+    // the first block is the entry
+    pub blocks: Vec<Block>,
+    pub result: Option<Kind>,
+}
+
+// every routine of the program, where the first is the main program
+pub struct Program {
+    pub routines: Vec<Routine>,
+}
+
+impl Routine {
+    pub fn kind(&self, temp: Temp) -> Kind {
+        self.temps[temp.0 as usize]
+    }
+
+    // the kind of an operand, where integers may be bytes or words
+    pub fn operand_kind(&self, operand: &Operand) -> Kind {
+        match operand {
+            Operand::Temp(temp) => self.kind(*temp),
+            Operand::Integer(_) => Kind::Word,
+            Operand::Real(_) => Kind::Real,
+        }
+    }
+}
+
+// a readable listing of the program, which --emit-ir writes
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Temp(temp) => write!(f, "t{}", temp.0),
+            Operand::Integer(n) => write!(f, "{}", n),
+            Operand::Real(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Slot { hops, slot, offset } => {
+                write!(f, "[s{}+{}", slot.0, offset)?;
+                if *hops > 0 {
+                    write!(f, " up {}", hops)?;
+                }
+                write!(f, "]")
+            }
+            Address::Pointer(operand, offset) => write!(f, "[{}+{}]", operand, offset),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Move(temp, operand) => write!(f, "t{} = {}", temp.0, operand),
+            Instruction::Unary(temp, operator, operand) => {
+                write!(f, "t{} = {:?} {}", temp.0, operator, operand)
+            }
+            Instruction::Binary(temp, operator, operand1, operand2) => {
+                write!(f, "t{} = {:?} {}, {}", temp.0, operator, operand1, operand2)
+            }
+            Instruction::Compare(temp, condition, kind, operand1, operand2) => write!(
+                f,
+                "t{} = {:?} {:?} {}, {}",
+                temp.0, condition, kind, operand1, operand2
+            ),
+            Instruction::Load(temp, address) => write!(f, "t{} = load {}", temp.0, address),
+            Instruction::Store(kind, address, operand) => {
+                write!(f, "store {:?} {}, {}", kind, address, operand)
+            }
+            Instruction::LoadAddress(temp, address) => {
+                write!(f, "t{} = address {}", temp.0, address)
+            }
+            Instruction::Frame(temp, hops) => write!(f, "t{} = frame {}", temp.0, hops),
+            Instruction::Data(temp, bytes) => write!(f, "t{} = data {:?}", temp.0, bytes),
+            Instruction::Stream(temp, stream) => write!(f, "t{} = {:?}", temp.0, stream),
+            Instruction::Copy(to, from, size) => write!(f, "copy {}, {}, {}", to, from, size),
+            Instruction::Fill(to, byte, size) => write!(f, "fill {}, {}, {}", to, byte, size),
+            Instruction::Call(result, callee, arguments) => {
+                if let Some(temp) = result {
+                    write!(f, "t{} = ", temp.0)?;
+                }
+                match callee {
+                    Callee::Routine(name, Some(link)) => write!(f, "call {} [{}]", name, link)?,
+                    Callee::Routine(name, None) => write!(f, "call {}", name)?,
+                    Callee::Runtime(name) => write!(f, "call {}", name)?,
+                }
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect();
+                write!(f, "({})", arguments.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Jump(label) => write!(f, "jump l{}", label.0),
+            Terminator::Branch(operand, label1, label2) => {
+                write!(f, "branch {}, l{}, l{}", operand, label1.0, label2.0)
+            }
+            Terminator::Switch(operand, minimum, table, default) => {
+                let table: Vec<String> =
+                    table.iter().map(|label| format!("l{}", label.0)).collect();
+                write!(
+                    f,
+                    "switch {} - {}, [{}], l{}",
+                    operand,
+                    minimum,
+                    table.join(", "),
+                    default.0
+                )
+            }
+            Terminator::Return(Some(operand)) => write!(f, "return {}", operand),
+            Terminator::Return(None) => write!(f, "return"),
+            Terminator::Error(message, Some(operand)) => {
+                write!(f, "error {:?}, {}", message, operand)
+            }
+            Terminator::Error(message, None) => write!(f, "error {:?}", message),
+            Terminator::Unwind(hops, label) => write!(f, "unwind {}, l{}", hops, label.0),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for routine in &self.routines {
+            let parameters: Vec<String> = (routine.parameters.iter())
+                .map(|temp| format!("t{}", temp.0))
+                .collect();
+            writeln!(f, "{}({}):", routine.name, parameters.join(", "))?;
+            for (i, (size, alignment)) in routine.slots.iter().enumerate() {
+                writeln!(f, "\ts{}: {} bytes, aligned to {}", i, size, alignment)?;
+            }
+            for block in &routine.blocks {
+                writeln!(f, "l{}:", block.label.0)?;
+                for instruction in &block.instructions {
+                    writeln!(f, "\t{}", instruction)?;
+                }
+                writeln!(f, "\t{}", block.terminator)?;
+            }
+        }
+        Ok(())
+    }
+}
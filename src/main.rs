@@ -1,59 +1,369 @@
 #![allow(dead_code)]
 
 use std::env;
-use std::fs;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use diagnostics::{Diagnostic, Severity};
 
 mod ast;
 mod definitions;
+mod diagnostics;
+mod ir;
+mod semantic;
 mod tokenizer;
 mod x86_64_compiler;
 
+const USAGE: &str = "\
+Usage:
+    pascal build <program.pas> [-o <output>] [-S | --emit-ir] [options]
+    pascal run <program.pas> [options]
+    pascal <program.pas> <program.s> [options]
+
+Commands:
+    build    Compile a program to an executable, or to assembly with -S, or to the
+             intermediate representation with --emit-ir
+    run      Compile a program to a temporary executable and run it
+    The last form writes assembly, the same as build -S -o <program.s>
+
+Options:
+    -o <output>                   Where to write the output, which defaults to the
+                                  program's name without .pas, or with .s for -S and .ir
+                                  for --emit-ir
+    -S                            Stop after writing assembly, without assembling or linking
+    --emit-ir                     Stop after analysis, writing a listing of the three-address
+                                  code that assembly is generated from
+    --range-checks                Stop with an error when an index or value is out of range
+    --overflow-checks             Stop with an error when integer arithmetic overflows or
+                                  anything is divided by zero
+    --uninitialized-checks        Stop with an error when a variable is read before it is
+                                  assigned
+    --message-format=<format>     Print errors and warnings as human (default) or json
+    -h, --help                    Print this message
+
+Exit status is 0 on success, 1 if the program has errors or can't be built, and 2 for invalid
+arguments. run exits with the status of the program.";
+
+// what the command line asked for
+struct Arguments {
+    // run the executable after building it
+    run: bool,
+    src: String,
+    output: Option<String>,
+    // stop at assembly
+    assembly_only: bool,
+    // stop at the intermediate representation
+    ir_only: bool,
+    options: semantic::Options,
+    json: bool,
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        panic!("Usage: cargo run -- src.pas dest.s");
+    let arguments = match parse_arguments(env::args().skip(1).collect()) {
+        Ok(Some(arguments)) => arguments,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    // with run, the program's own output goes to stdout, so the compiler's goes to stderr
+    let to_stderr = arguments.run;
+
+    let Some(program) = compile_file(&arguments, to_stderr) else {
+        process::exit(1);
+    };
+
+    if arguments.ir_only {
+        let dest = match &arguments.output {
+            Some(output) => PathBuf::from(output),
+            None => default_output(&arguments.src, "ir"),
+        };
+        write_file(&dest, &program.to_string());
+        print_status(
+            &format!("Successfully written to {}.", dest.display()),
+            arguments.json,
+        );
+        return;
     }
-    // read source file
-    let src = &args[1];
 
-    let code = fs::read_to_string(src).expect("Failed to read from file.");
+    let x86_64 = x86_64_compiler::compile(&program);
+    if arguments.assembly_only {
+        let dest = match &arguments.output {
+            Some(output) => PathBuf::from(output),
+            None => default_output(&arguments.src, "s"),
+        };
+        write_file(&dest, &x86_64);
+        print_status(
+            &format!("Successfully written to {}.", dest.display()),
+            arguments.json || to_stderr,
+        );
+        return;
+    }
 
-    // compile program, get destination file
-    let pascal_ast = ast::parse_program(&code);
-    let (x86_64, errors, warnings) = x86_64_compiler::compile(pascal_ast, &code);
+    // the files made on the way, and the executable for run, are kept where no one else can
+    // get at them
+    let dir = match make_private_dir() {
+        Ok(dir) => dir,
+        Err(error) => {
+            eprintln!("Failed to make a temporary directory: {}", error);
+            process::exit(1);
+        }
+    };
+    let dest = if arguments.run {
+        dir.join("program")
+    } else {
+        match &arguments.output {
+            Some(output) => PathBuf::from(output),
+            None => default_output(&arguments.src, ""),
+        }
+    };
+    if let Err(message) = link(&x86_64, &dest, &dir) {
+        let _ = fs::remove_dir_all(&dir);
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+    if !arguments.run {
+        let _ = fs::remove_dir_all(&dir);
+        print_status(
+            &format!("Successfully built {}.", dest.display()),
+            arguments.json,
+        );
+        return;
+    }
 
+    let status = Command::new(&dest).status();
+    let _ = fs::remove_dir_all(&dir);
+    match status {
+        // a program killed by a signal has no exit code
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(error) => {
+            eprintln!("Failed to run {}: {}", dest.display(), error);
+            process::exit(1);
+        }
+    }
+}
+
+// returns the requested command, or None if help was asked for. Flags may come anywhere.
+fn parse_arguments(args: Vec<String>) -> Result<Option<Arguments>, String> {
+    let mut arguments = Arguments {
+        run: false,
+        src: String::new(),
+        output: None,
+        assembly_only: false,
+        ir_only: false,
+        options: semantic::Options::default(),
+        json: false,
+    };
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" => match args.next() {
+                Some(output) => arguments.output = Some(output),
+                None => return Err(String::from("-o needs a file name")),
+            },
+            "-S" => arguments.assembly_only = true,
+            "--emit-ir" => arguments.ir_only = true,
+            "--message-format=human" => arguments.json = false,
+            "--message-format=json" => arguments.json = true,
+            "--range-checks" => arguments.options.range_checks = true,
+            "--overflow-checks" => arguments.options.overflow_checks = true,
+            "--uninitialized-checks" => arguments.options.uninitialized_checks = true,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    match positional.first().map(String::as_str) {
+        Some("build") | Some("run") if positional.len() == 2 => {
+            arguments.run = positional[0] == "run";
+            arguments.src = positional[1].clone();
+        }
+        Some("build") | Some("run") => {
+            return Err(format!("{} takes one program", positional[0]));
+        }
+        // the original form, which writes assembly
+        _ if positional.len() == 2 => {
+            arguments.src = positional[0].clone();
+            arguments.output = Some(positional[1].clone());
+            arguments.assembly_only = true;
+        }
+        None => return Err(String::from("No program given")),
+        _ => return Err(format!("Unknown command {}", positional[0])),
+    }
+    if arguments.run && (arguments.assembly_only || arguments.ir_only || arguments.output.is_some())
+    {
+        return Err(String::from("run doesn't take -o, -S or --emit-ir"));
+    }
+    if arguments.assembly_only && arguments.ir_only {
+        return Err(String::from("-S and --emit-ir can't be used together"));
+    }
+    Ok(Some(arguments))
+}
+
+// analyzes the program, printing its diagnostics and a summary, and returns its intermediate
+// representation if there were no errors
+fn compile_file(arguments: &Arguments, to_stderr: bool) -> Option<ir::Program> {
+    let json = arguments.json;
+    let code = match fs::read_to_string(&arguments.src) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", arguments.src, error);
+            return None;
+        }
+    };
+
+    let pascal_ast = match ast::parse_program(&code) {
+        Ok(pascal_ast) => pascal_ast,
+        Err(diagnostics) => {
+            print_diagnostics(&diagnostics, &code, json, to_stderr);
+            let errors = diagnostics.len();
+            let mut summary = format!("Compilation failed due to {} syntax ", errors);
+            if errors > 1 {
+                summary.push_str("errors.");
+            } else {
+                summary.push_str("error.");
+            }
+            print_status(&summary, json || to_stderr);
+            return None;
+        }
+    };
+    let (program, diagnostics) = semantic::analyze(pascal_ast, &code, arguments.options);
+    print_diagnostics(&diagnostics, &code, json, to_stderr);
+    let errors = diagnostics::count(&diagnostics, Severity::Error);
+    let warnings = diagnostics::count(&diagnostics, Severity::Warning);
+
+    let mut summary = String::new();
     if errors > 0 {
-        print!("Compilation failed due to {} ", errors);
+        summary.push_str(&format!("Compilation failed due to {} ", errors));
         if errors > 1 {
-            print!("errors");
+            summary.push_str("errors");
         } else {
-            print!("error");
+            summary.push_str("error");
         }
         if warnings > 0 {
-            print!(" and {} ", warnings);
+            summary.push_str(&format!(" and {} ", warnings));
             if warnings > 1 {
-                println!("warnings.");
+                summary.push_str("warnings.");
             } else {
-                println!("warning.");
+                summary.push_str("warning.");
             }
         } else {
-            println!(".");
+            summary.push('.');
         }
     } else if warnings > 0 {
-        print!("Compiled with {} ", warnings);
+        summary.push_str(&format!("Compiled with {} ", warnings));
         if warnings > 1 {
-            println!("warnings.");
+            summary.push_str("warnings.");
         } else {
-            println!("warning.");
+            summary.push_str("warning.");
         }
     } else {
-        println!("Compilation complete.");
+        summary.push_str("Compilation complete.");
+    }
+    print_status(&summary, json || to_stderr);
+    if errors > 0 {
+        None
+    } else {
+        Some(program)
+    }
+}
+
+// the program's path with its extension replaced, or removed if `extension` is empty
+fn default_output(src: &str, extension: &str) -> PathBuf {
+    let dest = Path::new(src).with_extension(extension);
+    if dest == Path::new(src) {
+        eprintln!(
+            "Writing the output would overwrite {}, so choose another name with -o",
+            src
+        );
+        process::exit(2);
+    }
+    dest
+}
+
+fn write_file(dest: &Path, contents: &str) {
+    if let Err(error) = fs::write(dest, contents) {
+        eprintln!("Failed to write {}: {}", dest.display(), error);
+        process::exit(1);
+    }
+}
+
+// makes a new directory in the system's temporary directory that only this user can use. It's
+// never one that was already there, which might be a link someone else put in place.
+fn make_private_dir() -> io::Result<PathBuf> {
+    let mut attempt = 0;
+    loop {
+        let dir = env::temp_dir().join(format!("pascal-{}-{}", process::id(), attempt));
+        match DirBuilder::new().mode(0o700).create(&dir) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            result => return result.map(|_| dir),
+        }
+    }
+}
+
+// assembles and links the program into a static executable with as and ld, using `dir` for the
+// files in between. The runtime in the assembly makes system calls itself, so no libraries are
+// needed.
+fn link(x86_64: &str, dest: &Path, dir: &Path) -> Result<(), String> {
+    let assembly = dir.join("program.s");
+    let object = dir.join("program.o");
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&assembly)
+        .and_then(|mut file| file.write_all(x86_64.as_bytes()))
+        .map_err(|error| format!("couldn't write {}: {}", assembly.display(), error))
+        .and_then(|_| run_tool(Command::new("as").arg("-o").arg(&object).arg(&assembly)))
+        .and_then(|_| run_tool(Command::new("ld").arg("-o").arg(dest).arg(&object)));
+    result.map_err(|error| format!("Failed to assemble and link {}: {}", dest.display(), error))
+}
+
+// runs an external tool, which prints its own errors
+fn run_tool(command: &mut Command) -> Result<(), String> {
+    let name = command.get_program().to_string_lossy().into_owned();
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(format!("{} failed", name)),
+        Err(error) => Err(format!("couldn't run {}: {}", name, error)),
     }
-    if errors == 0 {
-        let dest = &args[2];
+}
+
+// prints diagnostics as colored text, or as one JSON object per line, in the order they appear in
+// the program
+fn print_diagnostics(diagnostics: &[Diagnostic], code: &str, json: bool, to_stderr: bool) {
+    let mut diagnostics: Vec<&Diagnostic> = diagnostics.iter().collect();
+    // a block is checked for uninitialized variables before its statements are compiled
+    diagnostics.sort_by_key(|diagnostic| diagnostic.start);
+    for diagnostic in diagnostics {
+        let text = if json {
+            format!("{}\n", diagnostics::render_json(diagnostic, code))
+        } else {
+            diagnostics::render_human(diagnostic, code)
+        };
+        if to_stderr {
+            eprint!("{}", text);
+        } else {
+            print!("{}", text);
+        }
+    }
+}
 
-        // write output and exit
-        fs::write(dest, x86_64).expect("Failed to write to file.");
-        println!("Successfully written to {}.", dest);
+// with JSON output, stdout only has diagnostics, so anything else goes to stderr
+fn print_status(status: &str, to_stderr: bool) {
+    if to_stderr {
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
     }
 }
@@ -0,0 +1,1486 @@
+# Runtime for compiled programs, appended to the assembly of each one. It talks to Linux with
+# system calls instead of using the C library, so programs link with plain ld into static
+# executables. Functions follow the System V calling convention, but don't need %rsp to be
+# aligned.
+
+# layout of a file, which is a buffer for a file descriptor, or for a string
+	.set	FILE_FD, 0		# file descriptor, or -1 for a string
+	.set	FILE_POS, 8		# index of the next byte in the buffer
+	.set	FILE_LEN, 16		# number of bytes read into the buffer
+	.set	FILE_CAP, 24		# size of the buffer
+	.set	FILE_BUF, 32		# address of the buffer
+	.set	FILE_MODE, 40		# 0 to read, 1 to write, 2 to write and flush after each write
+	.set	FILE_NEXT, 48		# next file being written, to flush when the program ends
+	.set	FILE_SIZE, 56
+	.set	BUFFER_SIZE, 4096
+
+	.set	HEAP_CHUNK, 1048576	# memory is mapped in chunks of this size
+	.set	LARGE_BLOCK, 4096	# blocks bigger than this are mapped on their own
+
+	.data
+	.balign	8
+rt_stdin:
+	.quad	0, 0, 0, BUFFER_SIZE, rt_stdin_buffer, 0, 0
+rt_stdout:
+	.quad	1, 0, 0, BUFFER_SIZE, rt_stdout_buffer, 1, 0
+
+	.bss
+	.balign	8
+rt_stdin_buffer:
+	.zero	BUFFER_SIZE
+rt_stdout_buffer:
+	.zero	BUFFER_SIZE
+rt_open_files:
+	.zero	8
+# freed blocks of each size, a multiple of 16, linked through their first 8 bytes
+rt_free_lists:
+	.zero	8 * (LARGE_BLOCK / 16 + 1)
+rt_heap_next:
+	.zero	8
+rt_heap_end:
+	.zero	8
+
+	.section .rodata
+rt_not_open_message:
+	.string	"File is not open"
+rt_not_reading_message:
+	.string	"File is not open for reading"
+rt_not_writing_message:
+	.string	"File is not open for writing"
+rt_write_failed_message:
+	.string	"Failed to write output"
+rt_out_of_memory_message:
+	.string	"Out of memory"
+rt_newline:
+	.string	"\n"
+rt_inf:
+	.ascii	"inf"
+rt_nan:
+	.ascii	"nan"
+	.balign	8
+rt_half:
+	.double	0.5
+rt_minus_half:
+	.double	-0.5
+
+	.text
+	.globl	_start
+_start:
+	# stdout is flushed after every write if it's a terminal, so output shows up as it's written
+	subq	$64, %rsp
+	movl	$16, %eax		# ioctl
+	movl	$1, %edi
+	movl	$0x5401, %esi		# TCGETS
+	movq	%rsp, %rdx
+	syscall
+	addq	$64, %rsp
+	testq	%rax, %rax
+	jne	1f
+	movq	$2, rt_stdout+FILE_MODE(%rip)
+1:	call	main
+	movl	%eax, %edi
+	jmp	rt_exit
+
+# exits with the status in %edi, after flushing stdout and the files still being written
+rt_exit:
+	pushq	%rdi
+	leaq	rt_stdout(%rip), %rdi
+	call	rt_flush
+	movq	rt_open_files(%rip), %rdi
+1:	testq	%rdi, %rdi
+	je	2f
+	pushq	FILE_NEXT(%rdi)
+	call	rt_flush
+	popq	%rdi
+	jmp	1b
+2:	popq	%rdi
+	movl	$231, %eax		# exit_group
+	syscall
+
+# writes the message at %rdi to stderr, followed by the string at %rsi if it isn't 0, and exits
+# with status 1. Both end with a NUL. What was written to stdout before is shown first.
+rt_error:
+	pushq	%rsi
+	pushq	%rdi
+	leaq	rt_stdout(%rip), %rdi
+	call	rt_flush
+	popq	%rdi
+	call	rt_write_error
+	popq	%rdi
+	testq	%rdi, %rdi
+	je	1f
+	call	rt_write_error
+1:	leaq	rt_newline(%rip), %rdi
+	call	rt_write_error
+	movl	$1, %edi
+	jmp	rt_exit
+
+rt_not_open:
+	leaq	rt_not_open_message(%rip), %rdi
+	xorl	%esi, %esi
+	jmp	rt_error
+
+rt_not_reading:
+	leaq	rt_not_reading_message(%rip), %rdi
+	xorl	%esi, %esi
+	jmp	rt_error
+
+rt_not_writing:
+	leaq	rt_not_writing_message(%rip), %rdi
+	xorl	%esi, %esi
+	jmp	rt_error
+
+# writes the string at %rdi, which ends with a NUL, straight to stderr
+rt_write_error:
+	movq	%rdi, %rsi
+	xorl	%edx, %edx
+1:	cmpb	$0, (%rsi, %rdx)
+	je	2f
+	incq	%rdx
+	jmp	1b
+2:	movl	$1, %eax		# write
+	movl	$2, %edi
+	syscall
+	ret
+
+# writes what's in the buffer of the file at %rdi, if it's being written, and stops the program
+# with an error if that fails
+rt_flush:
+	cmpq	$0, FILE_MODE(%rdi)
+	je	3f
+	cmpq	$-1, FILE_FD(%rdi)
+	je	3f
+	pushq	%rbx
+	pushq	%r12
+	movq	%rdi, %rbx
+	xorl	%r12d, %r12d		# bytes written so far
+1:	movq	FILE_POS(%rbx), %rdx
+	subq	%r12, %rdx
+	jle	2f
+	movl	$1, %eax		# write
+	movq	FILE_FD(%rbx), %rdi
+	movq	FILE_BUF(%rbx), %rsi
+	addq	%r12, %rsi
+	syscall
+	testq	%rax, %rax
+	jle	4f
+	addq	%rax, %r12
+	jmp	1b
+2:	movq	$0, FILE_POS(%rbx)
+	popq	%r12
+	popq	%rbx
+3:	ret
+	# the buffer is emptied first, so flushing it again on the way out doesn't fail again
+4:	movq	$0, FILE_POS(%rbx)
+	leaq	rt_write_failed_message(%rip), %rdi
+	xorl	%esi, %esi
+	jmp	rt_error
+
+# refills the buffer of the file at %rdi, which has to be being read, and returns the number of
+# bytes read, which is 0 at its end
+rt_fill:
+	xorl	%eax, %eax
+	cmpq	$0, FILE_MODE(%rdi)
+	jne	rt_not_reading
+	cmpq	$-1, FILE_FD(%rdi)
+	je	2f
+	pushq	%rbx
+	movq	%rdi, %rbx
+	# anything waiting to be written to stdout, such as a prompt, is shown before reading
+	leaq	rt_stdout(%rip), %rdi
+	call	rt_flush
+	movq	$0, FILE_POS(%rbx)
+	movq	$0, FILE_LEN(%rbx)
+	xorl	%eax, %eax		# read
+	movq	FILE_FD(%rbx), %rdi
+	movq	FILE_BUF(%rbx), %rsi
+	movq	FILE_CAP(%rbx), %rdx
+	syscall
+	testq	%rax, %rax
+	jg	1f
+	xorl	%eax, %eax
+1:	movq	%rax, FILE_LEN(%rbx)
+	movq	%rbx, %rdi
+	popq	%rbx
+2:	ret
+
+# returns the next byte of the file at %rdi without taking it, or -1 at its end. %rdi is kept.
+rt_peekc:
+	testq	%rdi, %rdi
+	je	rt_not_open
+	movq	FILE_POS(%rdi), %rax
+	cmpq	FILE_LEN(%rdi), %rax
+	jb	1f
+	call	rt_fill
+	testq	%rax, %rax
+	je	2f
+	xorl	%eax, %eax
+1:	movq	FILE_BUF(%rdi), %rcx
+	movzbl	(%rcx, %rax), %eax
+	ret
+2:	movq	$-1, %rax
+	ret
+
+# returns the next byte of the file at %rdi, or -1 at its end. %rdi is kept.
+rt_getc:
+	call	rt_peekc
+	testl	%eax, %eax
+	js	1f
+	incq	FILE_POS(%rdi)
+1:	ret
+
+# writes %rdx bytes from %rsi to the file at %rdi, which has to be being written. Strings keep as
+# many as fit.
+rt_write:
+	testq	%rdi, %rdi
+	je	rt_not_open
+	cmpq	$0, FILE_MODE(%rdi)
+	je	rt_not_writing
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	movq	%rdi, %rbx
+	movq	%rsi, %r12
+	movq	%rdx, %r13
+1:	testq	%r13, %r13
+	jle	3f
+	movq	FILE_CAP(%rbx), %rcx
+	subq	FILE_POS(%rbx), %rcx	# space left in the buffer
+	jne	2f
+	cmpq	$-1, FILE_FD(%rbx)
+	je	3f
+	movq	%rbx, %rdi
+	call	rt_flush
+	jmp	1b
+2:	cmpq	%r13, %rcx
+	cmovaq	%r13, %rcx
+	subq	%rcx, %r13
+	movq	FILE_BUF(%rbx), %rdi
+	addq	FILE_POS(%rbx), %rdi
+	addq	%rcx, FILE_POS(%rbx)
+	movq	%r12, %rsi
+	rep movsb
+	movq	%rsi, %r12
+	jmp	1b
+3:	cmpq	$2, FILE_MODE(%rbx)
+	jne	4f
+	movq	%rbx, %rdi
+	call	rt_flush
+4:	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# writes the byte in %sil to the file at %rdi
+rt_putc:
+	pushq	%rsi
+	movq	%rsp, %rsi
+	movl	$1, %edx
+	call	rt_write
+	popq	%rsi
+	ret
+
+# writes %rsi spaces to the file at %rdi, or none if it isn't positive
+rt_pad:
+	pushq	%rbx
+	pushq	%r12
+	movq	%rdi, %rbx
+	movq	%rsi, %r12
+1:	testq	%r12, %r12
+	jle	2f
+	movq	%rbx, %rdi
+	movl	$32, %esi
+	call	rt_putc
+	decq	%r12
+	jmp	1b
+2:	popq	%r12
+	popq	%rbx
+	ret
+
+# writes %rdx bytes from %rsi to the file at %rdi, right justified in a field %rcx wide, or left
+# justified if it's negative
+rt_write_string:
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	movq	%rdi, %rbx
+	movq	%rsi, %r12
+	movq	%rdx, %r13
+	movq	%rcx, %r14
+	movq	%r14, %rsi
+	subq	%r13, %rsi
+	call	rt_pad
+	movq	%rbx, %rdi
+	movq	%r12, %rsi
+	movq	%r13, %rdx
+	call	rt_write
+	movq	%rbx, %rdi
+	movq	%r14, %rsi
+	negq	%rsi
+	subq	%r13, %rsi
+	call	rt_pad
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# writes the char in %sil to the file at %rdi, in a field %rdx wide
+rt_write_char:
+	pushq	%rsi
+	movq	%rdx, %rcx
+	movq	%rsp, %rsi
+	movl	$1, %edx
+	call	rt_write_string
+	popq	%rsi
+	ret
+
+# writes the integer in %rsi to the file at %rdi, in a field %rdx wide
+rt_write_int:
+	subq	$40, %rsp
+	movq	%rdx, %rcx
+	movq	%rsi, %rax
+	leaq	32(%rsp), %r8		# digits are written backwards from here
+	movq	%r8, %r9
+	# the magnitude is unsigned, so the lowest integer works
+	testq	%rax, %rax
+	jns	1f
+	negq	%rax
+1:	movl	$10, %r10d
+2:	xorl	%edx, %edx
+	divq	%r10
+	addb	$48, %dl
+	decq	%r8
+	movb	%dl, (%r8)
+	testq	%rax, %rax
+	jne	2b
+	testq	%rsi, %rsi
+	jns	3f
+	decq	%r8
+	movb	$45, (%r8)
+3:	movq	%r8, %rsi
+	movq	%r9, %rdx
+	subq	%r8, %rdx
+	call	rt_write_string
+	addq	$40, %rsp
+	ret
+
+# writes inf or nan for the magnitude in %rax, which has all exponent bits set, to the file at
+# %rdi
+rt_write_special:
+	leaq	rt_inf(%rip), %rsi
+	btsq	$63, %rax
+	movabsq	$0xFFF0000000000000, %rcx
+	cmpq	%rcx, %rax
+	je	1f
+	leaq	rt_nan(%rip), %rsi
+1:	movl	$3, %edx
+	jmp	rt_write
+
+# writes %rcx digits of the number in %rax to %rdi, with leading zeros, and moves %rdi past them
+rt_put_digits:
+	addq	%rcx, %rdi
+	movq	%rdi, %r9
+	movl	$10, %r8d
+1:	xorl	%edx, %edx
+	divq	%r8
+	addb	$48, %dl
+	decq	%r9
+	movb	%dl, (%r9)
+	decq	%rcx
+	jne	1b
+	ret
+
+# multiplies the number at %rdi, which is %rbx 8 byte words with the lowest first, by %rsi. %rbx
+# is updated if the number gets longer.
+rt_bignum_multiply:
+	xorl	%r8d, %r8d		# carry
+	xorl	%ecx, %ecx
+1:	cmpq	%rbx, %rcx
+	jae	2f
+	movq	(%rdi, %rcx, 8), %rax
+	mulq	%rsi
+	addq	%r8, %rax
+	adcq	$0, %rdx
+	movq	%rax, (%rdi, %rcx, 8)
+	movq	%rdx, %r8
+	incq	%rcx
+	jmp	1b
+2:	testq	%r8, %r8
+	je	3f
+	movq	%r8, (%rdi, %rbx, 8)
+	incq	%rbx
+3:	ret
+
+# writes the exact decimal digits of the magnitude of %xmm0, which must be finite, to the buffer
+# at %rdi, without leading zeros. Returns the number of digits in %rax (up to 767), and the power
+# of ten of the last one in %rdx. Zero is the single digit 0.
+#
+# A double is m * 2^e, so it's m << e if e isn't negative, and otherwise m * 5^-e / 10^-e. That
+# integer is turned into digits 19 at a time, by dividing it by 10^19.
+rt_decimal:
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	pushq	%r15
+	subq	$768, %rsp		# the integer at (%rsp), its digits by 19 at 384(%rsp)
+	movq	%rdi, %r15
+	movq	%xmm0, %rax
+	movq	%rax, %rcx
+	shrq	$52, %rcx
+	andl	$0x7FF, %ecx
+	movabsq	$0xFFFFFFFFFFFFF, %rdx
+	andq	%rdx, %rax
+	testq	%rcx, %rcx
+	je	1f
+	btsq	$52, %rax
+	subq	$1075, %rcx
+	jmp	2f
+1:	movq	$-1074, %rcx		# subnormal
+2:	testq	%rax, %rax
+	jne	3f
+	movb	$48, (%r15)
+	movl	$1, %eax
+	xorl	%edx, %edx
+	jmp	18f
+3:	movq	%rcx, %r12		# e
+	movq	%rax, %r13		# m
+	movq	%rsp, %rdi
+	movl	$48, %ecx
+	xorl	%eax, %eax
+	rep stosq
+	xorl	%r14d, %r14d		# the number is the integer / 10^%r14
+	testq	%r12, %r12
+	js	4f
+	movq	%r12, %rcx
+	andl	$63, %ecx
+	movq	%r13, %rax
+	xorl	%edx, %edx
+	shldq	%cl, %rax, %rdx
+	shlq	%cl, %rax
+	movq	%r12, %rsi
+	shrq	$6, %rsi
+	movq	%rax, (%rsp, %rsi, 8)
+	movq	%rdx, 8(%rsp, %rsi, 8)
+	leaq	2(%rsi), %rbx		# words in use
+	jmp	10f
+4:	movq	%r13, (%rsp)
+	movl	$1, %ebx
+	movq	%r12, %r14
+	negq	%r14
+	movq	%r14, %r12		# fives still to multiply by
+5:	cmpq	$27, %r12
+	jb	6f
+	movq	%rsp, %rdi
+	movabsq	$7450580596923828125, %rsi	# 5^27
+	call	rt_bignum_multiply
+	subq	$27, %r12
+	jmp	5b
+6:	movl	$1, %esi
+7:	testq	%r12, %r12
+	je	8f
+	imulq	$5, %rsi
+	decq	%r12
+	jmp	7b
+8:	movq	%rsp, %rdi
+	call	rt_bignum_multiply
+10:	xorl	%r13d, %r13d		# groups of 19 digits, lowest first
+11:	movabsq	$0x8AC7230489E80000, %rsi	# 10^19
+	xorl	%edx, %edx
+	movq	%rbx, %rcx
+12:	testq	%rcx, %rcx
+	je	13f
+	movq	-8(%rsp, %rcx, 8), %rax
+	divq	%rsi
+	movq	%rax, -8(%rsp, %rcx, 8)
+	decq	%rcx
+	jmp	12b
+13:	movq	%rdx, 384(%rsp, %r13, 8)
+	incq	%r13
+14:	testq	%rbx, %rbx
+	je	15f
+	cmpq	$0, -8(%rsp, %rbx, 8)
+	jne	11b
+	decq	%rbx
+	jmp	14b
+	# the highest group has no leading zeros
+15:	decq	%r13
+	movq	384(%rsp, %r13, 8), %rax
+	xorl	%ecx, %ecx
+	movl	$10, %r8d
+16:	incq	%rcx
+	xorl	%edx, %edx
+	divq	%r8
+	testq	%rax, %rax
+	jne	16b
+	movq	%r15, %rdi
+	movq	384(%rsp, %r13, 8), %rax
+	call	rt_put_digits
+17:	testq	%r13, %r13
+	je	19f
+	decq	%r13
+	movq	384(%rsp, %r13, 8), %rax
+	movl	$19, %ecx
+	call	rt_put_digits
+	jmp	17b
+19:	movq	%rdi, %rax
+	subq	%r15, %rax
+	movq	%r14, %rdx
+	negq	%rdx
+18:	addq	$768, %rsp
+	popq	%r15
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# rounds the %rsi digits at %rdi to the first %rdx of them, which may be 0, with halfway rounding
+# to even. Returns the number of digits kept in %rax, which is %rdx + 1 if they were all nines
+# and became 1 followed by zeros, or %rsi if there were no more than %rdx.
+rt_round_digits:
+	movq	%rdx, %rax
+	cmpq	%rsi, %rdx
+	jl	1f
+	movq	%rsi, %rax
+	ret
+1:	movzbl	(%rdi, %rdx), %ecx
+	cmpb	$53, %cl
+	jb	6f
+	ja	4f
+	leaq	1(%rdx), %r8
+2:	cmpq	%rsi, %r8
+	jge	3f
+	cmpb	$48, (%rdi, %r8)
+	jne	4f
+	incq	%r8
+	jmp	2b
+	# exactly halfway, where the digit before 0 counts as 0
+3:	testq	%rdx, %rdx
+	je	6f
+	testb	$1, -1(%rdi, %rdx)
+	je	6f
+4:	movq	%rdx, %r8
+5:	testq	%r8, %r8
+	je	7f
+	decq	%r8
+	cmpb	$57, (%rdi, %r8)
+	jne	8f
+	movb	$48, (%rdi, %r8)
+	jmp	5b
+8:	incb	(%rdi, %r8)
+6:	ret
+7:	movb	$48, (%rdi, %rdx)
+	movb	$49, (%rdi)
+	incq	%rax
+	ret
+
+
+# writes digits %rcx up to %r8 of the %rdx digits at %rsi to the file at %rdi, where the digits
+# before the first and after the last are zeros
+rt_write_digits:
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	pushq	%r15
+	movq	%rdi, %rbx
+	movq	%rsi, %r12
+	movq	%rdx, %r13
+	movq	%rcx, %r14
+	movq	%r8, %r15
+1:	cmpq	%r15, %r14
+	jge	3f
+	movl	$48, %esi
+	testq	%r14, %r14
+	js	2f
+	cmpq	%r13, %r14
+	jge	2f
+	movzbl	(%r12, %r14), %esi
+2:	movq	%rbx, %rdi
+	call	rt_putc
+	incq	%r14
+	jmp	1b
+3:	popq	%r15
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# writes the real in %xmm0 to the file at %rdi in exponent form, with a space or minus sign, one
+# digit before the decimal point, %rsi after it and an exponent of at least two digits
+rt_write_real:
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	pushq	%r15
+	subq	$800, %rsp		# digits
+	movq	%rdi, %rbx
+	movq	%rsi, %r12
+	movq	%xmm0, %r13
+	movl	$32, %esi
+	testq	%r13, %r13
+	jns	1f
+	movl	$45, %esi
+1:	call	rt_putc
+	movq	%r13, %rax
+	btrq	$63, %rax
+	movabsq	$0x7FF0000000000000, %rcx
+	cmpq	%rcx, %rax
+	jb	2f
+	movq	%rbx, %rdi
+	call	rt_write_special
+	jmp	6f
+2:	movq	%rsp, %rdi
+	movq	%r13, %xmm0
+	call	rt_decimal
+	leaq	-1(%rax, %rdx), %r15	# exponent
+	movq	%rsp, %rdi
+	movq	%rax, %rsi
+	leaq	1(%r12), %rdx
+	call	rt_round_digits
+	movq	%rax, %r14		# digits, which are followed by zeros
+	# rounding up all nines gives one more digit, and a bigger exponent
+	leaq	2(%r12), %rcx
+	cmpq	%rcx, %rax
+	jne	3f
+	incq	%r15
+	decq	%r14
+3:	movq	%rbx, %rdi
+	movzbl	(%rsp), %esi
+	call	rt_putc
+	testq	%r12, %r12
+	je	4f
+	movq	%rbx, %rdi
+	movl	$46, %esi
+	call	rt_putc
+	movq	%rbx, %rdi
+	movq	%rsp, %rsi
+	movq	%r14, %rdx
+	movl	$1, %ecx
+	leaq	1(%r12), %r8
+	call	rt_write_digits
+4:	movq	%rbx, %rdi
+	movl	$101, %esi
+	call	rt_putc
+	movl	$43, %esi
+	testq	%r15, %r15
+	jns	5f
+	negq	%r15
+	movl	$45, %esi
+5:	movq	%rbx, %rdi
+	call	rt_putc
+	movq	%rbx, %rdi
+	movq	%r15, %rsi
+	xorl	%edx, %edx
+	cmpq	$10, %r15
+	jge	7f
+	# one digit is written as two
+	addq	$48, %rsi
+	movl	$48, %edx
+	shlq	$8, %rsi
+	orq	%rdx, %rsi
+	pushq	%rsi
+	movq	%rsp, %rsi
+	movl	$2, %edx
+	call	rt_write
+	popq	%rsi
+	jmp	6f
+7:	call	rt_write_int
+6:	addq	$800, %rsp
+	popq	%r15
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# writes the real in %xmm0 to the file at %rdi with %rdx digits after the decimal point (6 if
+# that's negative), in a field %rsi wide
+#
+# Written this way, the number is a whole number of its last digit, which is its decimal digits
+# rounded to that digit, then any zeros needed to reach it.
+rt_write_fixed:
+	pushq	%rbx
+	pushq	%rbp
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	pushq	%r15
+	subq	$824, %rsp		# digits, the field width, then the index where the fraction starts
+	movq	%rdi, %rbx
+	movq	%rsi, 800(%rsp)
+	movq	%rdx, %r12
+	testq	%r12, %r12
+	jns	1f
+	movl	$6, %r12d
+1:	movq	%xmm0, %rbp
+	movq	%rbp, %rax
+	btrq	$63, %rax
+	movabsq	$0x7FF0000000000000, %rcx
+	cmpq	%rcx, %rax
+	jb	2f
+	movl	$3, %r15d		# inf or nan
+	jmp	6f
+2:	movq	%rsp, %rdi
+	movq	%rbp, %xmm0
+	call	rt_decimal
+	movq	%rax, %r13		# digits
+	leaq	(%rdx, %r12), %r14	# zeros after them, or digits to round off if negative
+	testq	%r14, %r14
+	jns	3f
+	leaq	(%rax, %r14), %rdx	# digits to keep
+	xorl	%r13d, %r13d
+	xorl	%r14d, %r14d
+	# if they'd all be rounded off, the number is too small to round up
+	testq	%rdx, %rdx
+	js	3f
+	movq	%rsp, %rdi
+	movq	%rax, %rsi
+	call	rt_round_digits
+	movq	%rax, %r13
+3:	addq	%r13, %r14		# digits with the zeros
+	# the text is the sign, the whole part of at least one digit, then the point and fraction
+	movq	%r14, %r15
+	subq	%r12, %r15
+	movq	%r15, 808(%rsp)
+	cmpq	$1, %r15
+	jge	4f
+	movl	$1, %r15d
+4:	testq	%r12, %r12
+	je	6f
+	leaq	1(%r15, %r12), %r15
+6:	testq	%rbp, %rbp
+	jns	7f
+	incq	%r15
+7:	movq	%rbx, %rdi
+	movq	800(%rsp), %rsi
+	subq	%r15, %rsi
+	call	rt_pad
+	testq	%rbp, %rbp
+	jns	8f
+	movq	%rbx, %rdi
+	movl	$45, %esi
+	call	rt_putc
+8:	movq	%rbp, %rax
+	btrq	$63, %rax
+	movabsq	$0x7FF0000000000000, %rcx
+	cmpq	%rcx, %rax
+	jb	9f
+	movq	%rbx, %rdi
+	call	rt_write_special
+	jmp	11f
+9:	movq	%rbx, %rdi
+	movq	%rsp, %rsi
+	movq	%r13, %rdx
+	xorl	%ecx, %ecx
+	movq	808(%rsp), %r8
+	testq	%r8, %r8
+	jg	10f
+	movq	$-1, %rcx		# a single 0
+	xorl	%r8d, %r8d
+10:	call	rt_write_digits
+	testq	%r12, %r12
+	je	11f
+	movq	%rbx, %rdi
+	movl	$46, %esi
+	call	rt_putc
+	movq	%rbx, %rdi
+	movq	%rsp, %rsi
+	movq	%r13, %rdx
+	movq	808(%rsp), %rcx
+	movq	%r14, %r8
+	call	rt_write_digits
+	# a negative field width pads after the text instead
+11:	movq	%rbx, %rdi
+	movq	800(%rsp), %rsi
+	negq	%rsi
+	subq	%r15, %rsi
+	call	rt_pad
+	addq	$824, %rsp
+	popq	%r15
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbp
+	popq	%rbx
+	ret
+
+# takes spaces, tabs and line breaks from the file at %rdi, which is kept
+rt_skip_space:
+	call	rt_peekc
+	cmpl	$32, %eax
+	je	1f
+	cmpl	$9, %eax
+	jb	2f
+	cmpl	$13, %eax
+	ja	2f
+1:	incq	FILE_POS(%rdi)
+	jmp	rt_skip_space
+2:	ret
+
+# takes a + or - from the file at %rdi, which is kept, if it's next. Returns 1 in %eax for -.
+rt_scan_sign:
+	call	rt_peekc
+	cmpl	$43, %eax
+	je	1f
+	cmpl	$45, %eax
+	jne	2f
+	incq	FILE_POS(%rdi)
+	movl	$1, %eax
+	ret
+1:	incq	FILE_POS(%rdi)
+2:	xorl	%eax, %eax
+	ret
+
+# takes the next digit from the file at %rdi, which is kept, returning it in %eax, or -1 if the
+# next byte isn't one
+rt_scan_digit:
+	call	rt_peekc
+	subl	$48, %eax
+	cmpl	$9, %eax
+	ja	1f
+	incq	FILE_POS(%rdi)
+	ret
+1:	movl	$-1, %eax
+	ret
+
+# reads an integer from the file at %rdi after skipping spaces, returning it in %rax, with %rdx
+# set to 0 if there were no digits, 1 if there were, and 2 if the integer was out of range, in
+# which case it becomes the nearest one in it
+rt_scan_int:
+	pushq	%rbx
+	pushq	%r12
+	pushq	%r13
+	call	rt_skip_space
+	call	rt_scan_sign
+	movl	%eax, %r12d		# 1 if negative
+	xorl	%ebx, %ebx		# magnitude, which stops at 2^64 - 1
+	xorl	%r13d, %r13d		# 1 once there's a digit
+1:	call	rt_scan_digit
+	testl	%eax, %eax
+	js	3f
+	movl	$1, %r13d
+	movl	%eax, %ecx
+	movq	%rbx, %rax
+	movl	$10, %edx
+	mulq	%rdx
+	jc	2f
+	addq	%rcx, %rax
+	jc	2f
+	movq	%rax, %rbx
+	jmp	1b
+2:	movq	$-1, %rbx
+	jmp	1b
+3:	movabsq	$0x8000000000000000, %rax
+	testl	%r12d, %r12d
+	je	4f
+	cmpq	%rax, %rbx
+	jbe	6f
+	movq	%rax, %rbx
+	movl	$2, %r13d
+6:	negq	%rbx
+	jmp	5f
+4:	decq	%rax
+	cmpq	%rax, %rbx
+	jbe	5f
+	movq	%rax, %rbx
+	movl	$2, %r13d
+5:	movq	%rbx, %rax
+	movq	%r13, %rdx
+	popq	%r13
+	popq	%r12
+	popq	%rbx
+	ret
+
+# reads a real from the file at %rdi after skipping spaces, returning it in %xmm0, with %rdx set
+# to 1 if there were any digits
+#
+# Up to 18 significant digits are kept as an integer, which is scaled by the power of ten with
+# extended precision.
+rt_scan_real:
+	pushq	%rbx
+	pushq	%rbp
+	pushq	%r12
+	pushq	%r13
+	pushq	%r14
+	pushq	%r15
+	subq	$8, %rsp
+	pxor	%xmm0, %xmm0		# the result if there are no digits
+	call	rt_skip_space
+	call	rt_scan_sign
+	movl	%eax, %ebp		# 1 if negative
+	xorl	%ebx, %ebx		# significant digits
+	xorl	%r12d, %r12d		# how many of them there are
+	xorl	%r13d, %r13d		# power of ten to scale them by
+	xorl	%r14d, %r14d		# 1 once there's a digit
+	xorl	%r15d, %r15d		# 1 after the decimal point
+1:	call	rt_scan_digit
+	testl	%eax, %eax
+	js	4f
+	movl	$1, %r14d
+	# leading zeros aren't significant, and nor are digits after the first 18
+	movl	%eax, %ecx
+	orq	%rbx, %rcx
+	je	3f
+	cmpq	$18, %r12
+	jae	2f
+	imulq	$10, %rbx
+	movl	%eax, %eax
+	addq	%rax, %rbx
+	incq	%r12
+	subq	%r15, %r13
+	jmp	1b
+2:	testq	%r15, %r15
+	jne	1b
+	incq	%r13
+	jmp	1b
+3:	subq	%r15, %r13
+	jmp	1b
+4:	testq	%r15, %r15
+	jne	5f
+	call	rt_peekc
+	cmpl	$46, %eax
+	jne	5f
+	incq	FILE_POS(%rdi)
+	movl	$1, %r15d
+	jmp	1b
+5:	testq	%r14, %r14
+	je	10f
+	call	rt_peekc
+	orl	$32, %eax
+	cmpl	$101, %eax
+	jne	8f
+	# a string is left at the e if no exponent follows it
+	pushq	FILE_POS(%rdi)
+	incq	FILE_POS(%rdi)
+	call	rt_scan_sign
+	movl	%eax, %r12d
+	xorl	%r15d, %r15d		# exponent, which stops growing past 100000
+	xorl	%ecx, %ecx		# 1 once there's a digit
+6:	pushq	%rcx
+	call	rt_scan_digit
+	popq	%rcx
+	testl	%eax, %eax
+	js	7f
+	movl	$1, %ecx
+	cmpq	$100000, %r15
+	jae	6b
+	imulq	$10, %r15
+	addq	%rax, %r15
+	jmp	6b
+7:	popq	%rax
+	testl	%ecx, %ecx
+	jne	11f
+	cmpq	$-1, FILE_FD(%rdi)
+	jne	8f
+	movq	%rax, FILE_POS(%rdi)
+	jmp	8f
+11:	testl	%r12d, %r12d
+	je	12f
+	negq	%r15
+12:	addq	%r15, %r13
+8:	# the digits are exact, as are powers of ten up to 10^27
+	movq	%rbx, (%rsp)
+	fildq	(%rsp)
+	testq	%rbx, %rbx
+	je	9f
+	movq	%r13, %rax
+	testq	%rax, %rax
+	jns	13f
+	negq	%rax
+13:	fld1
+	movq	$10, (%rsp)
+	fildq	(%rsp)
+14:	testq	$1, %rax
+	je	15f
+	fmul	%st, %st(1)
+15:	shrq	$1, %rax
+	je	16f
+	fmul	%st, %st
+	jmp	14b
+16:	fstp	%st(0)
+	fxch
+	testq	%r13, %r13
+	js	17f
+	fmul	%st(1), %st
+	jmp	18f
+17:	fdiv	%st(1), %st
+18:	fstp	%st(1)
+9:	testl	%ebp, %ebp
+	je	19f
+	fchs
+19:	fstpl	(%rsp)
+	movsd	(%rsp), %xmm0
+10:	movq	%r14, %rdx
+	addq	$8, %rsp
+	popq	%r15
+	popq	%r14
+	popq	%r13
+	popq	%r12
+	popq	%rbp
+	popq	%rbx
+	ret
+
+# reads an integer from the file at %rdi into the address in %rsi, returning 0 in %eax, or 2 if
+# it's out of range. See rt_no_number for when there isn't one.
+rt_read_int:
+	pushq	%rsi
+	call	rt_scan_int
+	popq	%rsi
+	testq	%rdx, %rdx
+	je	rt_no_number
+	movq	%rax, (%rsi)
+	xorl	%eax, %eax
+	cmpq	$2, %rdx
+	jne	1f
+	movl	$2, %eax
+1:	ret
+
+# reads a real from the file at %rdi into the address in %rsi, returning 0 in %eax, or 2 if it's
+# too large to hold. See rt_no_number for when there isn't one.
+rt_read_real:
+	pushq	%rsi
+	call	rt_scan_real
+	popq	%rsi
+	testq	%rdx, %rdx
+	je	rt_no_number
+	movsd	%xmm0, (%rsi)
+	# an infinity has every bit of the exponent set
+	movq	%xmm0, %rcx
+	shlq	$1, %rcx
+	shrq	$53, %rcx
+	xorl	%eax, %eax
+	cmpq	$0x7ff, %rcx
+	jne	1f
+	movl	$2, %eax
+1:	ret
+
+# leaves the variable of a read with no digits as it was, returning 0 in %eax at the end of the
+# file at %rdi, where there's nothing left to read, or 1 if something other than a number is next
+rt_no_number:
+	call	rt_peekc
+	cmpl	$-1, %eax
+	setne	%al
+	movzbl	%al, %eax
+	ret
+
+# returns the next char of the file at %rdi in %al, where the end of a line or the file is a space
+rt_read_char:
+	call	rt_getc
+	cmpl	$10, %eax
+	je	1f
+	cmpl	$-1, %eax
+	jne	2f
+1:	movl	$32, %eax
+2:	ret
+
+# reads the rest of the line from the file at %rdi into the string at %rsi, up to as many chars
+# as fit, leaving the line break. Returns 1 in %eax if chars were left because they didn't fit.
+rt_read_string:
+	pushq	%rbx
+	movq	%rsi, %rbx
+	movb	$0, (%rbx)
+1:	call	rt_peekc
+	cmpl	$-1, %eax
+	je	2f
+	cmpl	$10, %eax
+	je	2f
+	cmpb	$255, (%rbx)
+	je	3f
+	incq	FILE_POS(%rdi)
+	movzbl	(%rbx), %ecx
+	incl	%ecx
+	movb	%cl, (%rbx)
+	movb	%al, (%rbx, %rcx)
+	jmp	1b
+2:	xorl	%eax, %eax
+	popq	%rbx
+	ret
+3:	movl	$1, %eax
+	popq	%rbx
+	ret
+
+# takes the rest of the line from the file at %rdi, including the line break
+rt_skip_line:
+	call	rt_getc
+	cmpl	$10, %eax
+	je	1f
+	cmpl	$-1, %eax
+	jne	rt_skip_line
+1:	ret
+
+# reads %rdx bytes from the file at %rdi to %rsi, returning 1 in %rax if there were enough
+rt_read_block:
+	pushq	%rbx
+	pushq	%r12
+	movq	%rsi, %rbx
+	movq	%rdx, %r12
+1:	testq	%r12, %r12
+	je	2f
+	call	rt_getc
+	testl	%eax, %eax
+	js	3f
+	movb	%al, (%rbx)
+	incq	%rbx
+	decq	%r12
+	jmp	1b
+2:	movl	$1, %eax
+	jmp	4f
+3:	xorl	%eax, %eax
+4:	popq	%r12
+	popq	%rbx
+	ret
+
+# sets up the FILE_SIZE bytes at %rdi as a file that reads the %rdx chars at %rsi, and returns it
+rt_string_source:
+	movq	$-1, FILE_FD(%rdi)
+	movq	$0, FILE_POS(%rdi)
+	movq	%rdx, FILE_LEN(%rdi)
+	movq	%rdx, FILE_CAP(%rdi)
+	movq	%rsi, FILE_BUF(%rdi)
+	movq	$0, FILE_MODE(%rdi)
+	movq	%rdi, %rax
+	ret
+
+# sets up the FILE_SIZE + 256 bytes at %rdi as a file that writes a string of up to 256 chars,
+# one more than a string holds so a longer one can be told apart, which are kept from
+# FILE_SIZE(%rdi) on, with their number at FILE_POS(%rdi)
+rt_string_sink:
+	movq	$-1, FILE_FD(%rdi)
+	movq	$0, FILE_POS(%rdi)
+	movq	$0, FILE_LEN(%rdi)
+	movq	$256, FILE_CAP(%rdi)
+	leaq	FILE_SIZE(%rdi), %rax
+	movq	%rax, FILE_BUF(%rdi)
+	movq	$1, FILE_MODE(%rdi)
+	movq	%rdi, %rax
+	ret
+
+# reads an integer from the %rsi chars at %rdi, returning it in %rax, and storing the number of
+# chars used at the address in %rdx, which is 0 if there wasn't one
+rt_parse_int:
+	pushq	%rdx
+	movq	%rsi, %rdx
+	movq	%rdi, %rsi
+	subq	$64, %rsp
+	movq	%rsp, %rdi
+	call	rt_string_source
+	call	rt_scan_int
+	testq	%rdx, %rdx
+	je	1f
+	movq	FILE_POS(%rsp), %rdx
+1:	addq	$64, %rsp
+	popq	%rcx
+	movq	%rdx, (%rcx)
+	ret
+
+# reads a real from the %rsi chars at %rdi, returning it in %xmm0, and storing the number of
+# chars used at the address in %rdx, which is 0 if there wasn't one
+rt_parse_real:
+	pushq	%rdx
+	movq	%rsi, %rdx
+	movq	%rdi, %rsi
+	subq	$64, %rsp
+	movq	%rsp, %rdi
+	call	rt_string_source
+	call	rt_scan_real
+	testq	%rdx, %rdx
+	je	1f
+	movq	FILE_POS(%rsp), %rdx
+1:	addq	$64, %rsp
+	popq	%rcx
+	movq	%rdx, (%rcx)
+	ret
+
+# returns the position of the first occurrence of the string at %rdi in the string at %rsi,
+# counting from 1, or 0 if there isn't one or the first is empty. Both start with their length.
+rt_pos:
+	movzbl	(%rdi), %ecx
+	movzbl	(%rsi), %edx
+	testl	%ecx, %ecx
+	je	4f
+	subl	%ecx, %edx		# the last place it could start
+	jl	4f
+	xorl	%r8d, %r8d
+1:	cmpl	%edx, %r8d
+	jg	4f
+	xorl	%r9d, %r9d
+2:	cmpl	%ecx, %r9d
+	je	3f
+	movb	1(%rdi, %r9), %al
+	leaq	(%r8, %r9), %r10
+	cmpb	1(%rsi, %r10), %al
+	jne	5f
+	incl	%r9d
+	jmp	2b
+5:	incl	%r8d
+	jmp	1b
+3:	leal	1(%r8), %eax
+	ret
+4:	xorl	%eax, %eax
+	ret
+
+# orders the strings at %rdi and %rsi, which start with their length, returning -1, 0 or 1 in
+# %rax. The chars both have are compared first, as unsigned bytes, then their lengths.
+rt_compare_strings:
+	xchgq	%rdi, %rsi
+	movzbl	(%rsi), %eax
+	movzbl	(%rdi), %edx
+	movl	%eax, %ecx
+	cmpl	%edx, %ecx
+	cmoval	%edx, %ecx
+	incq	%rsi
+	incq	%rdi
+	testl	%ecx, %ecx
+	repe cmpsb
+	jne	1f
+	cmpl	%edx, %eax
+1:	seta	%cl
+	setb	%dl
+	movzbl	%cl, %eax
+	movzbl	%dl, %edx
+	subq	%rdx, %rax
+	ret
+
+# opens the file named at %rdi, which ends with a NUL, for reading, or for writing if %rsi is 1.
+# Returns it, or 0 if it couldn't be opened.
+rt_open:
+	pushq	%rbx
+	pushq	%r12
+	movq	%rsi, %r12
+	xorl	%esi, %esi		# O_RDONLY
+	testq	%r12, %r12
+	je	1f
+	movl	$0x241, %esi		# O_WRONLY | O_CREAT | O_TRUNC
+1:	movl	$2, %eax		# open
+	movl	$0666, %edx
+	syscall
+	testq	%rax, %rax
+	js	3f
+	movq	%rax, %rbx
+	movl	$FILE_SIZE + BUFFER_SIZE, %edi
+	call	rt_alloc
+	movq	%rbx, FILE_FD(%rax)
+	movq	$BUFFER_SIZE, FILE_CAP(%rax)
+	leaq	FILE_SIZE(%rax), %rcx
+	movq	%rcx, FILE_BUF(%rax)
+	movq	%r12, FILE_MODE(%rax)
+	testq	%r12, %r12
+	je	2f
+	movq	rt_open_files(%rip), %rcx
+	movq	%rcx, FILE_NEXT(%rax)
+	movq	%rax, rt_open_files(%rip)
+2:	popq	%r12
+	popq	%rbx
+	ret
+3:	xorl	%eax, %eax
+	jmp	2b
+
+# closes the file at %rdi, unless it's 0. stdin and stdout are only flushed.
+rt_close:
+	testq	%rdi, %rdi
+	je	5f
+	pushq	%rbx
+	movq	%rdi, %rbx
+	call	rt_flush
+	leaq	rt_stdin(%rip), %rax
+	cmpq	%rax, %rbx
+	je	4f
+	leaq	rt_stdout(%rip), %rax
+	cmpq	%rax, %rbx
+	je	4f
+	# unlink it from the files being written
+	leaq	rt_open_files(%rip), %rcx
+1:	movq	(%rcx), %rdx
+	testq	%rdx, %rdx
+	je	3f
+	cmpq	%rdx, %rbx
+	je	2f
+	leaq	FILE_NEXT(%rdx), %rcx
+	jmp	1b
+2:	movq	FILE_NEXT(%rbx), %rdx
+	movq	%rdx, (%rcx)
+3:	movl	$3, %eax		# close
+	movq	FILE_FD(%rbx), %rdi
+	syscall
+	movq	%rbx, %rdi
+	call	rt_free
+4:	popq	%rbx
+5:	ret
+
+# returns a copy of the %rsi chars at %rdi on the heap, followed by a NUL
+rt_strndup:
+	pushq	%rdi
+	pushq	%rsi
+	leaq	1(%rsi), %rdi
+	call	rt_alloc
+	popq	%rcx
+	popq	%rsi
+	movq	%rax, %rdi
+	rep movsb
+	ret
+
+# maps %rsi bytes of zeroed memory, returning their address
+rt_map:
+	movl	$9, %eax		# mmap
+	xorl	%edi, %edi
+	movl	$3, %edx		# PROT_READ | PROT_WRITE
+	movl	$0x22, %r10d		# MAP_PRIVATE | MAP_ANONYMOUS
+	movq	$-1, %r8
+	xorl	%r9d, %r9d
+	syscall
+	cmpq	$-4096, %rax
+	ja	1f
+	ret
+1:	leaq	rt_out_of_memory_message(%rip), %rdi
+	xorl	%esi, %esi
+	jmp	rt_error
+
+# returns the address of %rdi bytes of zeroed memory. Each block has its size in the 16 bytes
+# before it.
+rt_alloc:
+	pushq	%rbx
+	leaq	15(%rdi), %rbx
+	andq	$-16, %rbx
+	jne	1f
+	movl	$16, %ebx
+1:	cmpq	$LARGE_BLOCK, %rbx
+	ja	5f
+	# a freed block of the same size is used again
+	movq	%rbx, %rcx
+	shrq	$4, %rcx
+	leaq	rt_free_lists(%rip), %rdx
+	movq	(%rdx, %rcx, 8), %rax
+	testq	%rax, %rax
+	je	2f
+	movq	(%rax), %r8
+	movq	%r8, (%rdx, %rcx, 8)
+	movq	%rax, %rdx
+	movq	%rax, %rdi
+	movq	%rbx, %rcx
+	xorl	%eax, %eax
+	rep stosb
+	movq	%rdx, %rax
+	popq	%rbx
+	ret
+	# otherwise it's taken from the current chunk, and the rest of a full chunk is left unused
+2:	movq	rt_heap_next(%rip), %rax
+	leaq	16(%rax, %rbx), %rcx
+	cmpq	rt_heap_end(%rip), %rcx
+	jbe	3f
+	movl	$HEAP_CHUNK, %esi
+	call	rt_map
+	movq	%rax, rt_heap_next(%rip)
+	addq	$HEAP_CHUNK, %rax
+	movq	%rax, rt_heap_end(%rip)
+	jmp	2b
+3:	movq	%rcx, rt_heap_next(%rip)
+4:	movq	%rbx, (%rax)
+	addq	$16, %rax
+	popq	%rbx
+	ret
+5:	leaq	16(%rbx), %rsi
+	call	rt_map
+	jmp	4b
+
+# frees the memory at %rdi, which came from rt_alloc, unless it's 0
+rt_free:
+	testq	%rdi, %rdi
+	je	1f
+	movq	-16(%rdi), %rsi
+	cmpq	$LARGE_BLOCK, %rsi
+	ja	2f
+	shrq	$4, %rsi
+	leaq	rt_free_lists(%rip), %rdx
+	movq	(%rdx, %rsi, 8), %rax
+	movq	%rax, (%rdi)
+	movq	%rdi, (%rdx, %rsi, 8)
+1:	ret
+2:	addq	$16, %rsi
+	subq	$16, %rdi
+	movl	$11, %eax		# munmap
+	syscall
+	ret
+
+# math functions of the real in %xmm0, which are worked out with extended precision
+rt_sin:
+	movsd	%xmm0, -8(%rsp)
+	fldl	-8(%rsp)
+	fsin
+	jmp	rt_math_result
+
+rt_cos:
+	movsd	%xmm0, -8(%rsp)
+	fldl	-8(%rsp)
+	fcos
+	jmp	rt_math_result
+
+rt_arctan:
+	movsd	%xmm0, -8(%rsp)
+	fldl	-8(%rsp)
+	fld1
+	fpatan
+	jmp	rt_math_result
+
+rt_ln:
+	movsd	%xmm0, -8(%rsp)
+	fldln2
+	fldl	-8(%rsp)
+	fyl2x
+	jmp	rt_math_result
+
+# e^x is 2^(x log2 e), which is split into a whole power of two and the rest
+rt_exp:
+	movsd	%xmm0, -8(%rsp)
+	fldl2e
+	fmull	-8(%rsp)
+	fld	%st(0)
+	frndint
+	fxch
+	fsub	%st(1), %st
+	f2xm1
+	fld1
+	faddp
+	fscale
+	fstp	%st(1)
+	jmp	rt_math_result
+
+# the remainder of %xmm0 divided by %xmm1, with the sign of %xmm0
+rt_fmod:
+	movsd	%xmm1, -8(%rsp)
+	fldl	-8(%rsp)
+	movsd	%xmm0, -8(%rsp)
+	fldl	-8(%rsp)
+1:	fprem
+	fnstsw	%ax
+	testw	$0x400, %ax
+	jne	1b
+	fstp	%st(1)
+	jmp	rt_math_result
+
+rt_math_result:
+	fstpl	-8(%rsp)
+	movsd	-8(%rsp), %xmm0
+	ret
+
+# rounds the real in %xmm0 to the nearest integer in %rax, with halves rounded away from zero
+rt_round:
+	cvttsd2si	%xmm0, %rax
+	cvtsi2sd	%rax, %xmm1
+	subsd	%xmm1, %xmm0
+	comisd	rt_half(%rip), %xmm0
+	jb	1f
+	incq	%rax
+	ret
+1:	movsd	rt_minus_half(%rip), %xmm1
+	comisd	%xmm0, %xmm1
+	jb	2f
+	decq	%rax
+2:	ret
+
+	.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,7301 @@
+use crate::definitions::*;
+use crate::diagnostics::*;
+use crate::ir::{
+    self, Address, BinaryOperator, Callee, Condition, Instruction, Kind, Label, Operand, Slot,
+    Stream, Temp, Terminator, UnaryOperator,
+};
+use std::collections::{HashMap, HashSet};
+
+// signature of a user defined procedure or function
+#[derive(Clone)]
+pub struct Signature {
+    label: String,                 // name of the routine in the IR
+    parameters: Vec<(Type, bool)>, // (type, is VAR parameter)
+    return_type: Option<Type>,
+    depth: usize, // nesting depth of the body
+}
+
+// optional code generation, chosen on the command line
+#[derive(Clone, Copy, Default)]
+pub struct Options {
+    // stop with a runtime error when an index or value is out of range
+    pub range_checks: bool,
+    // stop with a runtime error when integer arithmetic overflows or anything is divided by zero
+    pub overflow_checks: bool,
+    // fill local variables with POISON and stop with a runtime error when one is read before it is
+    // assigned
+    pub uninitialized_checks: bool,
+}
+
+// names declared in a block, with a link to the scope of the enclosing block
+pub struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    // nesting depth of the block, where the main program is 0
+    depth: usize,
+    // symbol names to their slots/types/whether the slot holds their address
+    variables: HashMap<String, (Slot, Type, bool)>,
+    // symbol names to their values/types
+    constants: HashMap<String, (String, Type)>,
+    procedures: HashMap<String, Signature>,
+    // type names declared in TYPE blocks
+    types: HashMap<String, Type>,
+    // record fields opened by a WITH statement, to the slot holding the record's address/the
+    // field's offset in the record/the field's type
+    fields: HashMap<String, (Slot, isize, Type)>,
+    // labels declared in LABEL blocks to their IR label/whether a statement is prefixed by it
+    labels: HashMap<u64, (Label, bool)>,
+    // where the constants and procedures declared in the block are named, (start, end)
+    declarations: HashMap<String, (usize, usize)>,
+    options: Options,
+}
+
+// where a variable is kept: `offset` bytes into a slot of the routine `hops` static links out, or
+// `offset` bytes from the address held in the slot if `is_reference`
+#[derive(Clone, Copy)]
+struct Location {
+    hops: usize,
+    slot: Slot,
+    offset: isize,
+    is_reference: bool,
+}
+
+impl Scope<'_> {
+    // returns the scope in which `name` is declared, innermost first
+    fn resolve(&self, name: &str) -> Option<&Scope<'_>> {
+        let mut scope = self;
+        loop {
+            if scope.variables.contains_key(name)
+                || scope.fields.contains_key(name)
+                || scope.constants.contains_key(name)
+                || scope.procedures.contains_key(name)
+            {
+                return Some(scope);
+            }
+            scope = scope.parent?;
+        }
+    }
+
+    // returns where the variable is kept, and its type
+    fn get_variable(&self, name: &str) -> Option<(Location, Type)> {
+        let scope = self.resolve(name)?;
+        // static links to follow to reach the variable's stack frame
+        let hops = self.depth - scope.depth;
+        if let Some((slot, field_offset, tipe)) = scope.fields.get(name) {
+            let location = Location {
+                hops,
+                slot: *slot,
+                offset: *field_offset,
+                is_reference: true,
+            };
+            return Some((location, tipe.clone()));
+        }
+        let (slot, tipe, is_reference) = scope.variables.get(name)?;
+        let location = Location {
+            hops,
+            slot: *slot,
+            offset: 0,
+            is_reference: *is_reference,
+        };
+        Some((location, tipe.clone()))
+    }
+
+    fn get_constant(&self, name: &str) -> Option<&(String, Type)> {
+        self.resolve(name)?.constants.get(name)
+    }
+
+    // returns where the constant or procedure is declared, unless it's predefined
+    fn get_declaration(&self, name: &str) -> Option<(usize, usize)> {
+        self.resolve(name)?.declarations.get(name).copied()
+    }
+
+    // type names can't be used as values, so they are searched for separately
+    fn get_type(&self, name: &str) -> Option<&Type> {
+        let mut scope = self;
+        loop {
+            if let Some(tipe) = scope.types.get(name) {
+                return Some(tipe);
+            }
+            scope = scope.parent?;
+        }
+    }
+
+    // returns the number of static links to follow to reach the label's stack frame, along with
+    // its IR label and whether a statement is prefixed by it
+    fn get_label(&self, label: u64) -> Option<(usize, Label, bool)> {
+        let mut scope = self;
+        loop {
+            if let Some((ir_label, prefixes)) = scope.labels.get(&label) {
+                return Some((self.depth - scope.depth, *ir_label, *prefixes));
+            }
+            scope = scope.parent?;
+        }
+    }
+
+    // a function's result variable shares its name, so procedures are searched for separately
+    fn get_procedure(&self, name: &str) -> Option<&Signature> {
+        let mut scope = self;
+        loop {
+            if let Some(signature) = scope.procedures.get(name) {
+                return Some(signature);
+            }
+            scope = scope.parent?;
+        }
+    }
+
+    // returns the names `pick` finds in this scope and its parents, along with the built in
+    // `builtins`, to suggest when a name can't be found
+    fn get_names(&self, pick: fn(&Scope) -> Vec<String>, builtins: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = builtins.iter().map(|name| name.to_string()).collect();
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            names.extend(pick(current));
+            scope = current.parent;
+        }
+        names
+    }
+
+    // names that can be used in an expression
+    fn get_value_names(&self) -> Vec<String> {
+        self.get_names(
+            |scope| {
+                (scope.variables.keys())
+                    .chain(scope.fields.keys())
+                    .chain(scope.constants.keys())
+                    .chain(scope.procedures.keys())
+                    .cloned()
+                    .collect()
+            },
+            &BUILTIN_FUNCTIONS,
+        )
+    }
+
+    fn get_function_names(&self) -> Vec<String> {
+        self.get_names(
+            |scope| {
+                (scope.procedures.iter())
+                    .filter(|(_, signature)| signature.return_type.is_some())
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            },
+            &BUILTIN_FUNCTIONS,
+        )
+    }
+
+    fn get_procedure_names(&self) -> Vec<String> {
+        self.get_names(
+            |scope| scope.procedures.keys().cloned().collect(),
+            &BUILTIN_PROCEDURES,
+        )
+    }
+
+    fn get_type_names(&self) -> Vec<String> {
+        self.get_names(
+            |scope| scope.types.keys().cloned().collect(),
+            &BUILTIN_TYPES,
+        )
+    }
+}
+
+// built in names, which are suggested alongside declared ones when a name is misspelled
+const BUILTIN_FUNCTIONS: [&str; 22] = [
+    "ORD", "SUCC", "PRED", "CHR", "EOF", "EOLN", "ABS", "SQR", "SIN", "COS", "ARCTAN", "EXP", "LN",
+    "SQRT", "TRUNC", "ROUND", "ODD", "LENGTH", "COPY", "POS", "CONCAT", "UPCASE",
+];
+const BUILTIN_PROCEDURES: [&str; 16] = [
+    "WRITE", "WRITELN", "READ", "READLN", "STR", "ASSIGN", "RESET", "REWRITE", "CLOSE", "GET",
+    "PUT", "NEW", "DISPOSE", "INSERT", "DELETE", "VAL",
+];
+const BUILTIN_TYPES: [&str; 6] = ["INTEGER", "BOOLEAN", "REAL", "CHAR", "STRING", "TEXT"];
+
+// the temporaries and slots of the routine being analyzed
+#[derive(Default)]
+struct Frame {
+    temps: Vec<Kind>,
+    slots: Vec<(usize, usize)>,
+    // slots holding intermediate strings and sets, which are taken until the statement they're
+    // taken in is done, then reused
+    taken: Vec<Slot>,
+    free: Vec<Slot>,
+}
+
+impl Frame {
+    fn temp(&mut self, kind: Kind) -> Temp {
+        self.temps.push(kind);
+        Temp(self.temps.len() as u32 - 1)
+    }
+
+    fn slot(&mut self, size: usize, alignment: usize) -> Slot {
+        self.slots.push((size, alignment));
+        Slot(self.slots.len() as u32 - 1)
+    }
+
+    // returns a slot of `size` bytes that's kept until `release` is given a mark from before it
+    fn scratch(&mut self, size: usize) -> Slot {
+        let slot = match (self.free.iter()).position(|slot| self.slots[slot.0 as usize].0 == size) {
+            Some(n) => self.free.swap_remove(n),
+            None => self.slot(size, 8),
+        };
+        self.taken.push(slot);
+        slot
+    }
+
+    fn mark(&self) -> usize {
+        self.taken.len()
+    }
+
+    // frees the slots taken since `mark`
+    fn release(&mut self, mark: usize) {
+        let released = self.taken.split_off(mark);
+        self.free.extend(released);
+    }
+}
+
+// a step of a routine's code, which labels and terminators split into basic blocks
+enum Step {
+    Instruction(Instruction),
+    Label(Label),
+    Terminator(Terminator),
+}
+
+// code in the order it runs, built up as it's analyzed
+#[derive(Default)]
+struct Code(Vec<Step>);
+
+impl Code {
+    fn push(&mut self, instruction: Instruction) {
+        self.0.push(Step::Instruction(instruction));
+    }
+
+    fn unary(
+        &mut self,
+        frame: &mut Frame,
+        kind: Kind,
+        operator: UnaryOperator,
+        operand: Operand,
+    ) -> Operand {
+        let temp = frame.temp(kind);
+        self.push(Instruction::Unary(temp, operator, operand));
+        Operand::Temp(temp)
+    }
+
+    fn binary(
+        &mut self,
+        frame: &mut Frame,
+        kind: Kind,
+        operator: BinaryOperator,
+        operand1: Operand,
+        operand2: Operand,
+    ) -> Operand {
+        let temp = frame.temp(kind);
+        self.push(Instruction::Binary(temp, operator, operand1, operand2));
+        Operand::Temp(temp)
+    }
+
+    fn compare(
+        &mut self,
+        frame: &mut Frame,
+        condition: Condition,
+        kind: Kind,
+        operand1: Operand,
+        operand2: Operand,
+    ) -> Operand {
+        let temp = frame.temp(Kind::Byte);
+        self.push(Instruction::Compare(
+            temp, condition, kind, operand1, operand2,
+        ));
+        Operand::Temp(temp)
+    }
+
+    fn load(&mut self, frame: &mut Frame, kind: Kind, address: Address) -> Operand {
+        let temp = frame.temp(kind);
+        self.push(Instruction::Load(temp, address));
+        Operand::Temp(temp)
+    }
+
+    fn store(&mut self, kind: Kind, address: Address, operand: Operand) {
+        self.push(Instruction::Store(kind, address, operand));
+    }
+
+    // returns the address as a word, which a pointer already is
+    fn address(&mut self, frame: &mut Frame, address: Address) -> Operand {
+        if let Address::Pointer(operand, 0) = address {
+            return operand;
+        }
+        let temp = frame.temp(Kind::Word);
+        self.push(Instruction::LoadAddress(temp, address));
+        Operand::Temp(temp)
+    }
+
+    // returns the result, which is meaningless without a kind
+    fn call(
+        &mut self,
+        frame: &mut Frame,
+        kind: Option<Kind>,
+        callee: Callee,
+        arguments: Vec<Operand>,
+    ) -> Operand {
+        let temp = kind.map(|kind| frame.temp(kind));
+        self.push(Instruction::Call(temp, callee, arguments));
+        temp.map_or(Operand::Integer(0), Operand::Temp)
+    }
+
+    fn runtime(
+        &mut self,
+        frame: &mut Frame,
+        kind: Option<Kind>,
+        name: &'static str,
+        arguments: Vec<Operand>,
+    ) -> Operand {
+        self.call(frame, kind, Callee::Runtime(name), arguments)
+    }
+
+    fn copy(&mut self, to: Operand, from: Operand, size: Operand) {
+        self.push(Instruction::Copy(to, from, size));
+    }
+
+    fn label(&mut self, label: Label) {
+        self.0.push(Step::Label(label));
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.0.push(Step::Terminator(terminator));
+    }
+
+    fn append(&mut self, other: Code) {
+        self.0.extend(other.0);
+    }
+}
+
+// returns a label that's new to the program
+fn new_label(label_idx: &mut u32) -> Label {
+    *label_idx += 1;
+    Label(*label_idx - 1)
+}
+
+// splits the code of a routine into basic blocks, where the first is the entry. Code following a
+// terminator without a label can't be reached, but still gets a block of its own.
+fn get_blocks(code: Code, label_idx: &mut u32) -> Vec<ir::Block> {
+    let mut blocks = Vec::new();
+    let mut current = Some((new_label(label_idx), Vec::new()));
+    for step in code.0 {
+        match step {
+            Step::Instruction(instruction) => {
+                let (_, instructions) =
+                    current.get_or_insert_with(|| (new_label(label_idx), Vec::new()));
+                instructions.push(instruction);
+            }
+            Step::Label(label) => {
+                if let Some((block_label, instructions)) = current.take() {
+                    blocks.push(ir::Block {
+                        label: block_label,
+                        instructions,
+                        terminator: Terminator::Jump(label),
+                    });
+                }
+                current = Some((label, Vec::new()));
+            }
+            Step::Terminator(terminator) => {
+                let (label, instructions) = current
+                    .take()
+                    .unwrap_or_else(|| (new_label(label_idx), Vec::new()));
+                blocks.push(ir::Block {
+                    label,
+                    instructions,
+                    terminator,
+                });
+            }
+        }
+    }
+    if let Some((label, instructions)) = current {
+        blocks.push(ir::Block {
+            label,
+            instructions,
+            terminator: Terminator::Return(None),
+        });
+    }
+    blocks
+}
+
+// the result of evaluating an expression, which is either a constant, or code that leaves it in an
+// operand. Strings and sets are left as the address of a slot of their own, which can be changed in
+// place.
+enum Value {
+    Constant(String),
+    Computed(Code, Operand),
+}
+
+impl Value {
+    // nothing, for when an error has been reported
+    fn empty() -> Value {
+        Value::Computed(Code::default(), Operand::Integer(0))
+    }
+
+    fn is_constant(&self) -> bool {
+        matches!(self, Value::Constant(_))
+    }
+}
+
+// returns the kind of temporary a value of the type is loaded into
+fn get_kind(tipe: &Type) -> Kind {
+    match get_base_type(tipe) {
+        Type::Boolean | Type::Char => Kind::Byte,
+        Type::Real => Kind::Real,
+        _ => Kind::Word,
+    }
+}
+
+// returns code for a value, which puts constants in an operand, or in a slot for strings and sets
+fn evaluate_constant(value: Value, tipe: &Type, frame: &mut Frame) -> (Code, Operand) {
+    let value = match value {
+        Value::Computed(code, operand) => return (code, operand),
+        Value::Constant(value) => value,
+    };
+    let mut code = Code::default();
+    let operand = match tipe {
+        Type::Real => Operand::Real(value.parse().unwrap_or(0.0)),
+        Type::Boolean => Operand::Integer(i64::from(value == "true")),
+        Type::Stryng => {
+            let text = get_string_constant(&value, tipe);
+            let bytes: Vec<u8> = std::iter::once(text.len() as u8)
+                .chain(text.bytes())
+                .collect();
+            let temp = frame.temp(Kind::Word);
+            code.push(Instruction::Data(temp, bytes));
+            let string = scratch_address(frame.scratch(MAX_STRING_LENGTH + 1), &mut code, frame);
+            code.copy(
+                string,
+                Operand::Temp(temp),
+                Operand::Integer(text.len() as i64 + 1),
+            );
+            string
+        }
+        Type::Set(_) => {
+            let slot = frame.scratch(32);
+            for (n, word) in parse_set(&value).iter().enumerate() {
+                let address = Address::Slot {
+                    hops: 0,
+                    slot,
+                    offset: 8 * n as isize,
+                };
+                code.store(Kind::Word, address, Operand::Integer(*word as i64));
+            }
+            scratch_address(slot, &mut code, frame)
+        }
+        // chars, integers, enumerations and pointers, where an error has already been reported if
+        // it doesn't parse
+        _ => Operand::Integer(value.parse().unwrap_or(0)),
+    };
+    (code, operand)
+}
+
+// returns the address of a slot of the current routine
+fn scratch_address(slot: Slot, code: &mut Code, frame: &mut Frame) -> Operand {
+    let address = Address::Slot {
+        hops: 0,
+        slot,
+        offset: 0,
+    };
+    code.address(frame, address)
+}
+
+// returns the kind of an operand, where integers are words
+fn operand_kind(operand: &Operand, frame: &Frame) -> Kind {
+    match operand {
+        Operand::Temp(temp) => frame.temps[temp.0 as usize],
+        Operand::Integer(_) => Kind::Word,
+        Operand::Real(_) => Kind::Real,
+    }
+}
+
+// returns an integer (or real) value as a real
+fn to_real(operand: Operand, tipe: &Type, code: &mut Code, frame: &mut Frame) -> Operand {
+    match (operand, tipe) {
+        (Operand::Integer(n), Type::Integer) => Operand::Real(n as f64),
+        (_, Type::Integer) => code.unary(frame, Kind::Real, UnaryOperator::Convert, operand),
+        _ => operand,
+    }
+}
+
+// returns an ordinal value as a word, so chars and booleans are numbered like the rest
+fn extend(operand: Operand, code: &mut Code, frame: &mut Frame) -> Operand {
+    match operand_kind(&operand, frame) {
+        Kind::Byte => code.unary(frame, Kind::Word, UnaryOperator::Extend, operand),
+        _ => operand,
+    }
+}
+
+// returns the lowest byte of a value, for operations on chars
+fn narrow(operand: Operand, code: &mut Code, frame: &mut Frame) -> Operand {
+    match operand {
+        Operand::Temp(temp) if frame.temps[temp.0 as usize] == Kind::Word => {
+            code.unary(frame, Kind::Byte, UnaryOperator::Narrow, operand)
+        }
+        _ => operand,
+    }
+}
+
+// returns the address of a variable, loading the address a reference holds
+fn get_location_address(location: Location, code: &mut Code, frame: &mut Frame) -> Address {
+    if !location.is_reference {
+        return Address::Slot {
+            hops: location.hops,
+            slot: location.slot,
+            offset: location.offset,
+        };
+    }
+    let reference = Address::Slot {
+        hops: location.hops,
+        slot: location.slot,
+        offset: 0,
+    };
+    let pointer = code.load(frame, Kind::Word, reference);
+    Address::Pointer(pointer, location.offset)
+}
+
+// returns the address `offset` bytes after `address`
+fn add_offset(address: Address, offset: isize) -> Address {
+    match address {
+        Address::Slot {
+            hops,
+            slot,
+            offset: base,
+        } => Address::Slot {
+            hops,
+            slot,
+            offset: base.wrapping_add(offset),
+        },
+        Address::Pointer(pointer, base) => Address::Pointer(pointer, base.wrapping_add(offset)),
+    }
+}
+
+// returns the address of element `index` of the elements of `size` bytes that start at `address`
+fn index_address(
+    address: Address,
+    index: Operand,
+    size: usize,
+    code: &mut Code,
+    frame: &mut Frame,
+) -> Address {
+    let scaled = match index {
+        Operand::Integer(n) => {
+            return add_offset(address, (n as isize).wrapping_mul(size as isize))
+        }
+        _ if size == 1 => index,
+        _ => code.binary(
+            frame,
+            Kind::Word,
+            BinaryOperator::Multiply,
+            index,
+            Operand::Integer(size as i64),
+        ),
+    };
+    let (base, offset) = match address {
+        Address::Slot { hops, slot, offset } => {
+            let base = Address::Slot {
+                hops,
+                slot,
+                offset: 0,
+            };
+            (code.address(frame, base), offset)
+        }
+        Address::Pointer(pointer, offset) => (pointer, offset),
+    };
+    let pointer = code.binary(frame, Kind::Word, BinaryOperator::Add, base, scaled);
+    Address::Pointer(pointer, offset)
+}
+
+// returns the error for assigning to `name`, which isn't a variable, pointing at where it's
+// declared if it's a constant or procedure
+fn get_assignment_error(name: &str, start: usize, end: usize, scope: &Scope) -> Diagnostic {
+    let what = if scope.get_constant(name).is_some() {
+        "constant"
+    } else {
+        match scope.get_procedure(name) {
+            Some(signature) if signature.return_type.is_some() => "function",
+            Some(_) => "procedure",
+            None => {
+                return Diagnostic::error("P1009", start, end, "Unrecognized identifier")
+                    .with_suggestion(name, &scope.get_value_names())
+            }
+        }
+    };
+    let diagnostic = Diagnostic::error(
+        "P1071",
+        start,
+        end,
+        &format!("Cannot assign to {} {}", what, name),
+    );
+    match scope.get_declaration(name) {
+        Some((declared_start, declared_end)) => {
+            diagnostic.with_label(declared_start, declared_end, "Declared here")
+        }
+        None => diagnostic,
+    }
+}
+
+// returns the text of a runtime error found at `start` in the source, saying where it is and the
+// line it's on
+fn located_message(message: &str, start: usize, src: &str) -> String {
+    let (line_idx, char_idx) = get_position(src, start);
+    let line = src.lines().nth(line_idx).unwrap_or_default();
+    format!(
+        "Runtime error at line {}, character {}:\n{}\n{}",
+        line_idx + 1,
+        char_idx + 1,
+        message,
+        line.trim_end()
+    )
+}
+
+// adds code that stops the program with a runtime error found at `start` if the byte `failed`
+// isn't 0
+fn check(
+    failed: Operand,
+    message: &str,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+) {
+    let error = new_label(label_idx);
+    let passed = new_label(label_idx);
+    code.terminate(Terminator::Branch(failed, error, passed));
+    code.label(error);
+    code.terminate(Terminator::Error(
+        located_message(message, start, src),
+        None,
+    ));
+    code.label(passed);
+}
+
+// adds code that stops the program with a runtime error found at `start`, unless the value of type
+// `tipe` is between `first` and `last`, and returns the value as a word
+#[allow(clippy::too_many_arguments)]
+fn check_range(
+    value: Operand,
+    first: i64,
+    last: i64,
+    message: &str,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+) -> Operand {
+    let value = extend(value, code, frame);
+    let below = code.compare(
+        frame,
+        Condition::Less,
+        Kind::Word,
+        value,
+        Operand::Integer(first),
+    );
+    let above = code.compare(
+        frame,
+        Condition::Greater,
+        Kind::Word,
+        value,
+        Operand::Integer(last),
+    );
+    let failed = code.binary(frame, Kind::Byte, BinaryOperator::Or, below, above);
+    check(failed, message, start, src, label_idx, code);
+    value
+}
+
+// returns the result of an integer operation, which stops the program with a runtime error found
+// at `start` if overflow checks are on and it overflows
+#[allow(clippy::too_many_arguments)]
+fn checked_binary(
+    operator: BinaryOperator,
+    value1: Operand,
+    value2: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Operand {
+    if scope.options.overflow_checks {
+        let test = match operator {
+            BinaryOperator::Add => BinaryOperator::AddOverflows,
+            BinaryOperator::Subtract => BinaryOperator::SubtractOverflows,
+            _ => BinaryOperator::MultiplyOverflows,
+        };
+        let overflowed = code.binary(frame, Kind::Byte, test, value1, value2);
+        check(overflowed, "Integer overflow", start, src, label_idx, code);
+    }
+    code.binary(frame, Kind::Word, operator, value1, value2)
+}
+
+// returns the negated integer, checked like `checked_binary`
+fn checked_negate(
+    value: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Operand {
+    if scope.options.overflow_checks {
+        let overflowed = code.unary(frame, Kind::Byte, UnaryOperator::NegateOverflows, value);
+        check(overflowed, "Integer overflow", start, src, label_idx, code);
+    }
+    code.unary(frame, Kind::Word, UnaryOperator::Negate, value)
+}
+
+// adds code that stops the program with a runtime error found at `start` if uninitialized checks
+// are on and the variable at `address` still holds POISON. Chars aren't checked, since every byte
+// is a valid char.
+#[allow(clippy::too_many_arguments)]
+fn check_initialized(
+    tipe: &Type,
+    address: Address,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) {
+    let (kind, poison) = match tipe {
+        _ if !scope.options.uninitialized_checks => return,
+        Type::Boolean => (Kind::Byte, POISON as u8 as i64),
+        Type::Integer | Type::Enumeration(_) | Type::Pointer(_) | Type::Real => {
+            (Kind::Word, POISON as i64)
+        }
+        _ => return,
+    };
+    let value = code.load(frame, kind, address);
+    let failed = code.compare(
+        frame,
+        Condition::Equal,
+        kind,
+        value,
+        Operand::Integer(poison),
+    );
+    check(
+        failed,
+        "Variable used before it was assigned",
+        start,
+        src,
+        label_idx,
+        code,
+    );
+}
+
+// adds code that stops the program with a runtime error found at `start` if range checks are on
+// and the value doesn't fit in a variable of type `target`
+#[allow(clippy::too_many_arguments)]
+fn check_assignment(
+    value: Operand,
+    target: &Type,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) {
+    if let Type::Subrange(_, first, last) = target {
+        if scope.options.range_checks {
+            check_range(
+                value,
+                *first,
+                *last,
+                "Value out of range",
+                start,
+                src,
+                label_idx,
+                code,
+                frame,
+            );
+        }
+    }
+}
+
+// adds code that stops the program with a runtime error found at `start`, unless the index is no
+// more than the length of the string at the address. A negative index is above any length when
+// compared unsigned.
+fn check_string_index(
+    index: Operand,
+    string: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+) {
+    let length = get_string_length(string, code, frame);
+    let failed = code.compare(frame, Condition::Above, Kind::Word, index, length);
+    check(failed, "Index out of range", start, src, label_idx, code);
+}
+
+// returns the real quotient. With overflow checks, dividing by zero is a runtime error, but
+// dividing by NaN isn't.
+#[allow(clippy::too_many_arguments)]
+fn divide_reals(
+    value1: Operand,
+    value2: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Operand {
+    if scope.options.overflow_checks {
+        let zero = Operand::Real(0.0);
+        let at_least = code.compare(frame, Condition::AboveEqual, Kind::Real, value2, zero);
+        let at_most = code.compare(frame, Condition::AboveEqual, Kind::Real, zero, value2);
+        let failed = code.binary(frame, Kind::Byte, BinaryOperator::And, at_least, at_most);
+        check(failed, "Division by zero", start, src, label_idx, code);
+    }
+    code.binary(frame, Kind::Real, BinaryOperator::Divide, value1, value2)
+}
+
+// returns the quotient or remainder of integers. With overflow checks, dividing by zero or the
+// lowest integer by -1 is a runtime error.
+#[allow(clippy::too_many_arguments)]
+fn divide_integers(
+    operator: BinaryOperator,
+    value1: Operand,
+    value2: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Operand {
+    if scope.options.overflow_checks {
+        let zero = code.compare(
+            frame,
+            Condition::Equal,
+            Kind::Word,
+            value2,
+            Operand::Integer(0),
+        );
+        check(zero, "Division by zero", start, src, label_idx, code);
+        let minus_one = code.compare(
+            frame,
+            Condition::Equal,
+            Kind::Word,
+            value2,
+            Operand::Integer(-1),
+        );
+        let lowest = code.unary(frame, Kind::Byte, UnaryOperator::NegateOverflows, value1);
+        let overflowed = code.binary(frame, Kind::Byte, BinaryOperator::And, minus_one, lowest);
+        check(overflowed, "Integer overflow", start, src, label_idx, code);
+    }
+    code.binary(frame, Kind::Word, operator, value1, value2)
+}
+
+// returns the length of the string at the address, as a word
+fn get_string_length(string: Operand, code: &mut Code, frame: &mut Frame) -> Operand {
+    let length = code.load(frame, Kind::Byte, Address::Pointer(string, 0));
+    code.unary(frame, Kind::Word, UnaryOperator::Extend, length)
+}
+
+// returns code for a string (or char) value as the address of a string of its own, which can be
+// changed in place
+fn as_string(value: Value, tipe: &Type, frame: &mut Frame) -> (Code, Operand) {
+    match value {
+        Value::Constant(value) => evaluate_constant(
+            Value::Constant(get_string_constant(&value, tipe)),
+            &Type::Stryng,
+            frame,
+        ),
+        Value::Computed(mut code, operand) if *tipe == Type::Char => {
+            let slot = frame.scratch(MAX_STRING_LENGTH + 1);
+            let address = |offset| Address::Slot {
+                hops: 0,
+                slot,
+                offset,
+            };
+            code.store(Kind::Byte, address(0), Operand::Integer(1));
+            code.store(Kind::Byte, address(1), operand);
+            let string = scratch_address(slot, &mut code, frame);
+            (code, string)
+        }
+        Value::Computed(code, operand) => (code, operand),
+    }
+}
+
+// returns code to copy a string into a slot of its own, taking only the characters it uses
+fn copy_string(string: Operand, frame: &mut Frame) -> (Code, Operand) {
+    let mut code = Code::default();
+    let copy = scratch_address(frame.scratch(MAX_STRING_LENGTH + 1), &mut code, frame);
+    let length = get_string_length(string, &mut code, frame);
+    let size = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::Add,
+        length,
+        Operand::Integer(1),
+    );
+    code.copy(copy, string, size);
+    (code, copy)
+}
+
+// adds code to append `count` characters from the address `from` to the string at `string`, where
+// more than fit is a runtime error found at `start`
+#[allow(clippy::too_many_arguments)]
+fn append_characters(
+    string: Operand,
+    from: Operand,
+    count: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+) {
+    let length = get_string_length(string, code, frame);
+    let room = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::Subtract,
+        Operand::Integer(MAX_STRING_LENGTH as i64),
+        length,
+    );
+    let overflows = code.compare(frame, Condition::Above, Kind::Word, count, room);
+    check(overflows, "String too long", start, src, label_idx, code);
+    let new_length = code.binary(frame, Kind::Word, BinaryOperator::Add, length, count);
+    code.store(Kind::Byte, Address::Pointer(string, 0), new_length);
+    let end = code.binary(frame, Kind::Word, BinaryOperator::Add, string, length);
+    let to = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::Add,
+        end,
+        Operand::Integer(1),
+    );
+    code.copy(to, from, count);
+}
+
+// adds code to append the second string to the first, checked like `append_characters`
+#[allow(clippy::too_many_arguments)]
+fn concatenate_strings(
+    string1: Operand,
+    string2: Operand,
+    start: usize,
+    src: &str,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+) {
+    let length = get_string_length(string2, code, frame);
+    let characters = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::Add,
+        string2,
+        Operand::Integer(1),
+    );
+    append_characters(
+        string1, characters, length, start, src, label_idx, code, frame,
+    );
+}
+
+// returns code to copy the set at the address into a slot of its own
+fn copy_set(set: Operand, frame: &mut Frame) -> (Code, Operand) {
+    let mut code = Code::default();
+    let copy = scratch_address(frame.scratch(32), &mut code, frame);
+    code.copy(copy, set, Operand::Integer(32));
+    (code, copy)
+}
+
+// adds code to combine the words of two sets into the first, with the second complemented first
+// if `negate`
+fn combine_sets(
+    operator: BinaryOperator,
+    negate: bool,
+    set1: Operand,
+    set2: Operand,
+    code: &mut Code,
+    frame: &mut Frame,
+) {
+    for n in 0..4 {
+        let word1 = code.load(frame, Kind::Word, Address::Pointer(set1, 8 * n));
+        let mut word2 = code.load(frame, Kind::Word, Address::Pointer(set2, 8 * n));
+        if negate {
+            word2 = code.unary(frame, Kind::Word, UnaryOperator::Not, word2);
+        }
+        let word = code.binary(frame, Kind::Word, operator, word1, word2);
+        code.store(Kind::Word, Address::Pointer(set1, 8 * n), word);
+    }
+}
+
+// adds code to store a value of type `tipe` in a variable of type `target`, converting integers
+// to reals. Values of other types have had an error reported.
+fn store_value(
+    target: &Type,
+    tipe: &Type,
+    address: Address,
+    value: Operand,
+    code: &mut Code,
+    frame: &mut Frame,
+) {
+    match (get_base_type(target), tipe) {
+        (Type::Char | Type::Boolean, _) => code.store(Kind::Byte, address, value),
+        (Type::Integer | Type::Enumeration(_) | Type::Pointer(_), _) => {
+            code.store(Kind::Word, address, value)
+        }
+        (Type::Real, Type::Real | Type::Integer) => {
+            let value = to_real(value, tipe, code, frame);
+            code.store(Kind::Real, address, value);
+        }
+        _ => {}
+    }
+}
+
+fn evaluate_factor(
+    factor: &Factor,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    if let Factor::Constant(unsigned_constant) = factor {
+        let (value, tipe) = match unsigned_constant {
+            UnsignedConstant::UnsignedInteger(n) => (format!("{}", n), Type::Integer),
+            UnsignedConstant::UnsignedReal(f) => (f.to_string(), Type::Real),
+            UnsignedConstant::Quote(text) => (text.to_string(), Type::Stryng),
+            UnsignedConstant::Nil(_) => ("0".to_string(), Type::Pointer(String::new())),
+            UnsignedConstant::Char(c) => (format!("{}", c), Type::Char),
+        };
+        (Value::Constant(value), tipe)
+    } else if let Factor::Parenthetical(expression) = factor {
+        evaluate_expression(expression, src, label_idx, diagnostics, frame, scope)
+    } else if let Factor::NegatedFactor(sub_factor, start, end) = factor {
+        let (value, tipe) = evaluate_factor(sub_factor, src, label_idx, diagnostics, frame, scope);
+        match (value, tipe) {
+            (Value::Constant(value), Type::Boolean) => (
+                Value::Constant(format!("{}", value != "true")),
+                Type::Boolean,
+            ),
+            (Value::Constant(value), Type::Integer) => match value.parse::<i64>() {
+                Ok(n) => (Value::Constant(format!("{}", !n)), Type::Integer),
+                Err(_) => {
+                    diagnostics.push(Diagnostic::error("P1001", *start, *end, "Integer overflow"));
+                    (Value::Constant(String::from("0")), Type::Integer)
+                }
+            },
+            // true is 1 and false is 0, so subtracting 1 and negating swaps them
+            (Value::Computed(mut code, operand), Type::Boolean) => {
+                let operand = code.binary(
+                    frame,
+                    Kind::Byte,
+                    BinaryOperator::Subtract,
+                    operand,
+                    Operand::Integer(1),
+                );
+                let operand = code.unary(frame, Kind::Byte, UnaryOperator::Negate, operand);
+                (Value::Computed(code, operand), Type::Boolean)
+            }
+            (Value::Computed(mut code, operand), Type::Integer) => {
+                let operand = code.unary(frame, Kind::Word, UnaryOperator::Not, operand);
+                (Value::Computed(code, operand), Type::Integer)
+            }
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    "P1002",
+                    *start,
+                    *end,
+                    "Invalid use of NOT",
+                ));
+                (Value::empty(), Type::Undefined)
+            }
+        }
+    } else if let Factor::Identifier(name, arguments, start, end) = factor {
+        if name == "ORD" {
+            if arguments.len() != 1 {
+                diagnostics.push(Diagnostic::error(
+                    "P1003",
+                    *start,
+                    *end,
+                    "Expected 1 argument",
+                ));
+                return (Value::empty(), Type::Integer);
+            }
+            let (value, tipe) =
+                evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+            if !is_ordinal(&tipe) {
+                if tipe != Type::Undefined {
+                    diagnostics.push(Diagnostic::error(
+                        "P1004",
+                        *start,
+                        *end,
+                        "Expected ordinal type as argument",
+                    ));
+                }
+                return (Value::empty(), Type::Integer);
+            }
+            match value {
+                Value::Constant(value) => {
+                    let n = get_ordinal_value(&value, &tipe).unwrap_or(0);
+                    (Value::Constant(n.to_string()), Type::Integer)
+                }
+                // extend byte into full integer
+                Value::Computed(mut code, operand) => {
+                    let operand = extend(operand, &mut code, frame);
+                    (Value::Computed(code, operand), Type::Integer)
+                }
+            }
+        } else if name == "SUCC" || name == "PRED" {
+            if arguments.len() != 1 {
+                diagnostics.push(Diagnostic::error(
+                    "P1003",
+                    *start,
+                    *end,
+                    "Expected 1 argument",
+                ));
+                return (Value::empty(), Type::Undefined);
+            }
+            let (value, tipe) =
+                evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+            let step = if name == "SUCC" { 1 } else { -1 };
+            if !is_ordinal(&tipe) {
+                if tipe != Type::Undefined {
+                    diagnostics.push(Diagnostic::error(
+                        "P1004",
+                        *start,
+                        *end,
+                        "Expected ordinal type as argument",
+                    ));
+                }
+                return (Value::empty(), Type::Undefined);
+            }
+            match value {
+                Value::Constant(value) => {
+                    let constant = get_ordinal_value(&value, &tipe)
+                        .and_then(|n| n.checked_add(step))
+                        .and_then(|n| get_ordinal_constant(n, &tipe));
+                    match constant {
+                        Some(constant) => (Value::Constant(constant), tipe),
+                        None => {
+                            diagnostics.push(Diagnostic::error(
+                                "P1005",
+                                *start,
+                                *end,
+                                if step == 1 {
+                                    "Value has no successor"
+                                } else {
+                                    "Value has no predecessor"
+                                },
+                            ));
+                            (Value::empty(), Type::Undefined)
+                        }
+                    }
+                }
+                Value::Computed(mut code, operand) => {
+                    let operator = if step == 1 {
+                        BinaryOperator::Add
+                    } else {
+                        BinaryOperator::Subtract
+                    };
+                    let one = Operand::Integer(1);
+                    let operand = match tipe {
+                        Type::Boolean => {
+                            code.binary(frame, Kind::Byte, BinaryOperator::Xor, operand, one)
+                        }
+                        Type::Char => code.binary(frame, Kind::Byte, operator, operand, one),
+                        Type::Integer => checked_binary(
+                            operator, operand, one, *start, src, label_idx, &mut code, frame, scope,
+                        ),
+                        _ => code.binary(frame, Kind::Word, operator, operand, one),
+                    };
+                    (Value::Computed(code, operand), tipe)
+                }
+            }
+        } else if name == "CHR" {
+            if arguments.len() != 1 {
+                diagnostics.push(Diagnostic::error(
+                    "P1003",
+                    *start,
+                    *end,
+                    "Expected 1 argument",
+                ));
+                return (Value::empty(), Type::Char);
+            }
+            let (value, tipe) =
+                evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+            if tipe != Type::Integer {
+                diagnostics.push(Diagnostic::error(
+                    "P1004",
+                    *start,
+                    *end,
+                    "Expected integer as argument",
+                ));
+            }
+            // chars are 0 to 255
+            match value {
+                Value::Constant(value) => {
+                    if value.parse::<i64>().is_ok_and(|n| !(0..=255).contains(&n)) {
+                        diagnostics.push(Diagnostic::error(
+                            "P1006",
+                            arguments[0].start,
+                            arguments[0].end,
+                            "Value out of range",
+                        ));
+                    }
+                    (Value::Constant(value), Type::Char)
+                }
+                Value::Computed(mut code, operand) => {
+                    if scope.options.range_checks {
+                        check_range(
+                            operand,
+                            0,
+                            255,
+                            "Value out of range",
+                            arguments[0].start,
+                            src,
+                            label_idx,
+                            &mut code,
+                            frame,
+                        );
+                    }
+                    let operand = narrow(operand, &mut code, frame);
+                    (Value::Computed(code, operand), Type::Char)
+                }
+            }
+        } else if name == "EOF" || name == "EOLN" {
+            evaluate_file_test(
+                name,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            )
+        } else if [
+            "ABS", "SQR", "SIN", "COS", "ARCTAN", "EXP", "LN", "SQRT", "TRUNC", "ROUND", "ODD",
+        ]
+        .contains(&name.as_str())
+        {
+            evaluate_math_function(
+                name,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            )
+        } else if ["LENGTH", "COPY", "POS", "CONCAT", "UPCASE"].contains(&name.as_str())
+            && scope.resolve(name).is_none()
+        {
+            // these common names are only taken if the program hasn't declared them itself
+            evaluate_string_function(
+                name,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            )
+        } else if !arguments.is_empty()
+            || (scope.get_constant(name).is_none()
+                && scope.get_variable(name).is_none()
+                && scope.get_procedure(name).is_some())
+        {
+            match scope.get_procedure(name) {
+                Some(signature) => match &signature.return_type {
+                    Some(return_type) => {
+                        let (code, operand) = evaluate_call(
+                            signature,
+                            arguments,
+                            *start,
+                            *end,
+                            src,
+                            label_idx,
+                            diagnostics,
+                            frame,
+                            scope,
+                        );
+                        (Value::Computed(code, operand), get_base_type(return_type))
+                    }
+                    None => {
+                        diagnostics.push(Diagnostic::error(
+                            "P1007",
+                            *start,
+                            *end,
+                            "Procedure does not return a value",
+                        ));
+                        (Value::empty(), Type::Undefined)
+                    }
+                },
+                None => {
+                    diagnostics.push(
+                        Diagnostic::error("P1008", *start, *end, "Unrecognized function")
+                            .with_suggestion(name, &scope.get_function_names()),
+                    );
+                    (Value::empty(), Type::Undefined)
+                }
+            }
+        } else if let Some((value, tipe)) = scope.get_constant(name) {
+            (Value::Constant(value.clone()), get_base_type(tipe))
+        } else if let Some((location, tipe)) = scope.get_variable(name) {
+            let tipe = get_base_type(&tipe);
+            let mut code = Code::default();
+            let address = get_location_address(location, &mut code, frame);
+            let operand = match tipe {
+                Type::Boolean
+                | Type::Char
+                | Type::Integer
+                | Type::Enumeration(_)
+                | Type::Pointer(_)
+                | Type::Real => {
+                    let operand = code.load(frame, get_kind(&tipe), address);
+                    check_initialized(
+                        &tipe, address, *start, src, label_idx, &mut code, frame, scope,
+                    );
+                    operand
+                }
+                Type::Set(_) => {
+                    let set = code.address(frame, address);
+                    let (copy, operand) = copy_set(set, frame);
+                    code.append(copy);
+                    operand
+                }
+                Type::Stryng => {
+                    let string = code.address(frame, address);
+                    let (copy, operand) = copy_string(string, frame);
+                    code.append(copy);
+                    operand
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1010",
+                        *start,
+                        *end,
+                        "Unsupported type used",
+                    ));
+                    return (Value::empty(), Type::Undefined);
+                }
+            };
+            (Value::Computed(code, operand), tipe)
+        } else {
+            diagnostics.push(
+                Diagnostic::error("P1009", *start, *end, "Unrecognized identifier")
+                    .with_suggestion(name, &scope.get_value_names()),
+            );
+            (Value::empty(), Type::Undefined)
+        }
+    } else if let Factor::ArrayIndex(name, index, start, end) = factor {
+        let Some((location, array_tipe)) = scope.get_variable(name) else {
+            diagnostics.push(
+                Diagnostic::error("P1009", *start, *end, "Unrecognized identifier")
+                    .with_suggestion(name, &scope.get_value_names()),
+            );
+            return (Value::empty(), Type::Undefined);
+        };
+        if let Type::Array(sub_tipe, index_tipe, start_idx, end_idx) = &array_tipe {
+            let (mut code, index) = evaluate_index(
+                index,
+                index_tipe,
+                *start_idx,
+                *end_idx,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+            let size = get_size(sub_tipe);
+            let address = get_location_address(location, &mut code, frame);
+            let address = index_address(address, index, size, &mut code, frame);
+            let address = add_offset(address, -start_idx * size as isize);
+            let sub_tipe = get_base_type(sub_tipe);
+            let operand = match sub_tipe {
+                Type::Boolean
+                | Type::Char
+                | Type::Integer
+                | Type::Enumeration(_)
+                | Type::Pointer(_)
+                | Type::Real => {
+                    let operand = code.load(frame, get_kind(&sub_tipe), address);
+                    check_initialized(
+                        &sub_tipe, address, *start, src, label_idx, &mut code, frame, scope,
+                    );
+                    operand
+                }
+                Type::Set(_) => {
+                    let set = code.address(frame, address);
+                    let (copy, operand) = copy_set(set, frame);
+                    code.append(copy);
+                    operand
+                }
+                Type::Stryng => {
+                    let string = code.address(frame, address);
+                    let (copy, operand) = copy_string(string, frame);
+                    code.append(copy);
+                    operand
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1010",
+                        *start,
+                        *end,
+                        "Unsupported type used",
+                    ));
+                    return (Value::empty(), Type::Undefined);
+                }
+            };
+            (Value::Computed(code, operand), sub_tipe)
+        } else if let Type::Stryng = array_tipe {
+            let (mut code, index_value, expected_integer) =
+                evaluate_final_expression(index, src, label_idx, diagnostics, frame, scope);
+            if expected_integer != Type::Integer {
+                diagnostics.push(Diagnostic::error(
+                    "P1011",
+                    *start,
+                    *end,
+                    "Arrays must indexed with integer type",
+                ));
+            }
+            let address = get_location_address(location, &mut code, frame);
+            let string = code.address(frame, address);
+            if scope.options.range_checks {
+                check_string_index(
+                    index_value,
+                    string,
+                    index.start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                );
+            }
+            // characters are indexed from 1, after the length
+            let address = index_address(
+                Address::Pointer(string, 0),
+                index_value,
+                1,
+                &mut code,
+                frame,
+            );
+            let operand = code.load(frame, Kind::Byte, address);
+            (Value::Computed(code, operand), Type::Char)
+        } else {
+            diagnostics.push(Diagnostic::error(
+                "P1012",
+                *start,
+                *end,
+                "Not an array or string type",
+            ));
+            (Value::empty(), Type::Undefined)
+        }
+    } else if let Factor::Selection(name, selectors, start, end) = factor {
+        let (mut code, address, tipe) = evaluate_selection(
+            name,
+            selectors,
+            *start,
+            *end,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        );
+        let tipe = get_base_type(&tipe);
+        let operand = match tipe {
+            Type::Boolean
+            | Type::Char
+            | Type::Integer
+            | Type::Enumeration(_)
+            | Type::Pointer(_)
+            | Type::Real => {
+                let operand = code.load(frame, get_kind(&tipe), address);
+                check_initialized(
+                    &tipe, address, *start, src, label_idx, &mut code, frame, scope,
+                );
+                operand
+            }
+            Type::Set(_) => {
+                let set = code.address(frame, address);
+                let (copy, operand) = copy_set(set, frame);
+                code.append(copy);
+                operand
+            }
+            Type::Stryng => {
+                let string = code.address(frame, address);
+                let (copy, operand) = copy_string(string, frame);
+                code.append(copy);
+                operand
+            }
+            Type::Undefined => return (Value::empty(), Type::Undefined),
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    "P1010",
+                    *start,
+                    *end,
+                    "Unsupported type used",
+                ));
+                return (Value::empty(), Type::Undefined);
+            }
+        };
+        (Value::Computed(code, operand), tipe)
+    } else if let Factor::List(elements) = factor {
+        evaluate_set(elements, src, label_idx, diagnostics, frame, scope)
+    } else {
+        panic!("Failed to compile factor.")
+    }
+}
+
+// returns code for EOF or EOLN of a file, or stdin if there isn't one. The next character is
+// looked at without taking it, and the file is at the end of a line if it's a newline, or there
+// isn't one.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_file_test(
+    name: &str,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let mut code = Code::default();
+    let file = match arguments {
+        [] => {
+            let file = frame.temp(Kind::Word);
+            code.push(Instruction::Stream(file, Stream::Input));
+            Operand::Temp(file)
+        }
+        [file] => {
+            let address = get_expression_variable(file).and_then(|factor| {
+                evaluate_address(factor, src, label_idx, diagnostics, frame, scope)
+            });
+            match address {
+                Some((address_code, address, Type::Text)) => {
+                    code.append(address_code);
+                    code.load(frame, Kind::Word, address)
+                }
+                // a typed file is at its end when its buffer is empty
+                Some((mut code, address, Type::File(_))) if name == "EOF" => {
+                    let is_full = code.load(frame, Kind::Word, add_offset(address, 16));
+                    let operand = code.compare(
+                        frame,
+                        Condition::Equal,
+                        Kind::Word,
+                        is_full,
+                        Operand::Integer(0),
+                    );
+                    return (Value::Computed(code, operand), Type::Boolean);
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1013",
+                        file.start,
+                        file.end,
+                        if name == "EOF" {
+                            "Expected file variable"
+                        } else {
+                            "Expected text file variable"
+                        },
+                    ));
+                    return (Value::empty(), Type::Undefined);
+                }
+            }
+        }
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                "P1003",
+                start,
+                end,
+                "Expected 1 argument",
+            ));
+            return (Value::empty(), Type::Undefined);
+        }
+    };
+    let next = code.runtime(frame, Some(Kind::Word), "rt_peekc", vec![file]);
+    let mut operand = code.compare(
+        frame,
+        Condition::Equal,
+        Kind::Word,
+        next,
+        Operand::Integer(-1),
+    );
+    if name == "EOLN" {
+        let is_newline = code.compare(
+            frame,
+            Condition::Equal,
+            Kind::Word,
+            next,
+            Operand::Integer(10),
+        );
+        operand = code.binary(frame, Kind::Byte, BinaryOperator::Or, operand, is_newline);
+    }
+    (Value::Computed(code, operand), Type::Boolean)
+}
+
+// returns code for one of the required math functions, folded if the argument is constant.
+// ABS and SQR keep the type of their argument, TRUNC and ROUND turn reals into integers, ODD
+// tests an integer, and the rest take integers or reals and return reals.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_math_function(
+    name: &str,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    if arguments.len() != 1 {
+        diagnostics.push(Diagnostic::error(
+            "P1003",
+            start,
+            end,
+            "Expected 1 argument",
+        ));
+        return (Value::empty(), Type::Undefined);
+    }
+    let (value, tipe) =
+        evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+    let (is_valid, message) = match name {
+        "ODD" => (tipe == Type::Integer, "Expected integer as argument"),
+        "TRUNC" | "ROUND" => (tipe == Type::Real, "Expected real as argument"),
+        _ => (
+            tipe == Type::Integer || tipe == Type::Real,
+            "Expected integer or real as argument",
+        ),
+    };
+    if !is_valid {
+        if tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1004",
+                arguments[0].start,
+                arguments[0].end,
+                message,
+            ));
+        }
+        return (Value::empty(), Type::Undefined);
+    }
+    let result_tipe = match name {
+        "ABS" | "SQR" => tipe.clone(),
+        "TRUNC" | "ROUND" => Type::Integer,
+        "ODD" => Type::Boolean,
+        _ => Type::Real,
+    };
+
+    let (mut code, operand) = match value {
+        Value::Computed(code, operand) => (code, operand),
+        Value::Constant(value) => {
+            let folded = match (name, &tipe) {
+                ("ODD", _) => Ok((value.parse::<i64>().unwrap_or(0) % 2 != 0).to_string()),
+                ("ABS", Type::Integer) => value
+                    .parse::<i64>()
+                    .unwrap_or(0)
+                    .checked_abs()
+                    .map(|n| n.to_string())
+                    .ok_or(()),
+                ("SQR", Type::Integer) => {
+                    let n = value.parse::<i64>().unwrap_or(0);
+                    n.checked_mul(n).map(|n| n.to_string()).ok_or(())
+                }
+                _ => {
+                    let x = value.parse::<f64>().unwrap_or(0.0);
+                    let y = match name {
+                        "ABS" => x.abs(),
+                        "SQR" => x * x,
+                        "SIN" => x.sin(),
+                        "COS" => x.cos(),
+                        "ARCTAN" => x.atan(),
+                        "EXP" => x.exp(),
+                        "LN" => x.ln(),
+                        "SQRT" => x.sqrt(),
+                        "TRUNC" => x.trunc(),
+                        _ => x.round(), // halves are rounded away from zero
+                    };
+                    if !y.is_finite() {
+                        Err(())
+                    } else if result_tipe == Type::Integer {
+                        // the cast saturates, so anything out of range is caught by the comparison
+                        let n = y as i64;
+                        if n as f64 == y {
+                            Ok(n.to_string())
+                        } else {
+                            Err(())
+                        }
+                    } else {
+                        Ok(y.to_string())
+                    }
+                }
+            };
+            return match folded {
+                Ok(folded) => (Value::Constant(folded), result_tipe),
+                Err(()) => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1014",
+                        start,
+                        end,
+                        match name {
+                            "LN" => "LN of a number that isn't positive",
+                            "SQRT" => "SQRT of a negative number",
+                            _ => "Result of constant expression is out of range",
+                        },
+                    ));
+                    (Value::empty(), Type::Undefined)
+                }
+            };
+        }
+    };
+
+    let operand = match (name, &tipe) {
+        ("ODD", _) => {
+            let bit = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::And,
+                operand,
+                Operand::Integer(1),
+            );
+            code.unary(frame, Kind::Byte, UnaryOperator::Narrow, bit)
+        }
+        // only the lowest integer overflows when negated, and stays negative
+        ("ABS", Type::Integer) => {
+            let negated = checked_negate(operand, start, src, label_idx, &mut code, frame, scope);
+            code.binary(frame, Kind::Word, BinaryOperator::Maximum, operand, negated)
+        }
+        ("ABS", _) => code.unary(frame, Kind::Real, UnaryOperator::Absolute, operand),
+        ("SQR", Type::Integer) => checked_binary(
+            BinaryOperator::Multiply,
+            operand,
+            operand,
+            start,
+            src,
+            label_idx,
+            &mut code,
+            frame,
+            scope,
+        ),
+        ("SQR", _) => code.binary(
+            frame,
+            Kind::Real,
+            BinaryOperator::Multiply,
+            operand,
+            operand,
+        ),
+        ("TRUNC", _) => code.unary(frame, Kind::Word, UnaryOperator::Truncate, operand),
+        ("ROUND", _) => code.runtime(frame, Some(Kind::Word), "rt_round", vec![operand]),
+        _ => {
+            let operand = to_real(operand, &tipe, &mut code, frame);
+            let function = match name {
+                "SQRT" => {
+                    let operand = code.unary(frame, Kind::Real, UnaryOperator::SquareRoot, operand);
+                    return (Value::Computed(code, operand), result_tipe);
+                }
+                "SIN" => "rt_sin",
+                "COS" => "rt_cos",
+                "ARCTAN" => "rt_arctan",
+                "EXP" => "rt_exp",
+                _ => "rt_ln",
+            };
+            code.runtime(frame, Some(Kind::Real), function, vec![operand])
+        }
+    };
+    (Value::Computed(code, operand), result_tipe)
+}
+
+// returns code for LENGTH, COPY, POS, CONCAT or UPCASE, folded if the arguments are constant.
+// Chars can be used wherever strings can, and UPCASE returns a char when it's given one.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_string_function(
+    name: &str,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let expected = match name {
+        "COPY" => 3,
+        "POS" => 2,
+        _ => 1,
+    };
+    if (name == "CONCAT" && arguments.is_empty())
+        || (name != "CONCAT" && arguments.len() != expected)
+    {
+        let message = match (name, expected) {
+            ("CONCAT", _) => "Expected at least 1 argument".to_string(),
+            (_, 1) => "Expected 1 argument".to_string(),
+            _ => format!("Expected {} arguments", expected),
+        };
+        diagnostics.push(Diagnostic::error("P1003", start, end, &message));
+        return (Value::empty(), Type::Undefined);
+    }
+    let mut values = Vec::new();
+    let mut is_valid = true;
+    for (n, argument) in arguments.iter().enumerate() {
+        let (value, tipe) =
+            evaluate_expression(argument, src, label_idx, diagnostics, frame, scope);
+        // the index and count of COPY are integers
+        let (matches, message) = if name == "COPY" && n > 0 {
+            (tipe == Type::Integer, "Expected integer as argument")
+        } else {
+            (
+                tipe == Type::Stryng || tipe == Type::Char,
+                "Expected string as argument",
+            )
+        };
+        if !matches {
+            if tipe != Type::Undefined {
+                diagnostics.push(Diagnostic::error(
+                    "P1004",
+                    argument.start,
+                    argument.end,
+                    message,
+                ));
+            }
+            is_valid = false;
+        }
+        values.push((value, tipe));
+    }
+    if !is_valid {
+        return (Value::empty(), Type::Undefined);
+    }
+    let result_tipe = match name {
+        "LENGTH" | "POS" => Type::Integer,
+        "UPCASE" => values[0].1.clone(),
+        _ => Type::Stryng,
+    };
+
+    if values.iter().all(|(value, _)| value.is_constant()) {
+        let constants: Vec<(String, Type)> = values
+            .into_iter()
+            .map(|(value, tipe)| match value {
+                Value::Constant(value) => (value, tipe),
+                Value::Computed(_, _) => (String::new(), tipe),
+            })
+            .collect();
+        let text = |n: usize| get_string_constant(&constants[n].0, &constants[n].1);
+        let folded = match name {
+            "LENGTH" => text(0).len().to_string(),
+            "POS" => match text(1).find(&text(0)) {
+                Some(n) if !text(0).is_empty() => (n + 1).to_string(),
+                _ => "0".to_string(),
+            },
+            "CONCAT" => check_string_constant(
+                (0..constants.len()).map(text).collect(),
+                start,
+                end,
+                diagnostics,
+            ),
+            "UPCASE" if result_tipe == Type::Char => (constants[0].0.parse::<i64>().unwrap_or(0)
+                as u8)
+                .to_ascii_uppercase()
+                .to_string(),
+            "UPCASE" => text(0).to_ascii_uppercase(),
+            // an index before the first character counts as the first, and the count is cut
+            // short at the end of the string
+            _ => {
+                let bytes = text(0).into_bytes();
+                let index = constants[1].0.parse::<i64>().unwrap_or(0);
+                let count = constants[2].0.parse::<i64>().unwrap_or(0);
+                let first = (index.saturating_sub(1).max(0) as usize).min(bytes.len());
+                let count = count.clamp(0, (bytes.len() - first) as i64) as usize;
+                String::from_utf8_lossy(&bytes[first..first + count]).into_owned()
+            }
+        };
+        return (Value::Constant(folded), result_tipe);
+    }
+
+    let mut values = values.into_iter();
+    let (value, tipe) = values.next().unwrap();
+    if name == "UPCASE" && tipe == Type::Char {
+        let (mut code, operand) = evaluate_constant(value, &tipe, frame);
+        let result = frame.temp(Kind::Byte);
+        code.push(Instruction::Move(result, operand));
+        let lower = new_label(label_idx);
+        let done = new_label(label_idx);
+        let is_lower = is_lowercase(operand, &mut code, frame);
+        code.terminate(Terminator::Branch(is_lower, lower, done));
+        code.label(lower);
+        let upper = code.binary(
+            frame,
+            Kind::Byte,
+            BinaryOperator::Subtract,
+            operand,
+            Operand::Integer(32),
+        );
+        code.push(Instruction::Move(result, upper));
+        code.label(done);
+        return (Value::Computed(code, Operand::Temp(result)), result_tipe);
+    }
+    let (mut code, string) = as_string(value, &tipe, frame);
+    let operand = match name {
+        "LENGTH" => get_string_length(string, &mut code, frame),
+        "CONCAT" => {
+            for (value, tipe) in values {
+                let (value_code, operand) = as_string(value, &tipe, frame);
+                code.append(value_code);
+                concatenate_strings(string, operand, start, src, label_idx, &mut code, frame);
+            }
+            string
+        }
+        // from the last character to the first
+        "UPCASE" => {
+            let index = frame.temp(Kind::Word);
+            let length = get_string_length(string, &mut code, frame);
+            code.push(Instruction::Move(index, length));
+            let (head, body, lower, next, done) = (
+                new_label(label_idx),
+                new_label(label_idx),
+                new_label(label_idx),
+                new_label(label_idx),
+                new_label(label_idx),
+            );
+            code.label(head);
+            let is_done = code.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                Operand::Temp(index),
+                Operand::Integer(0),
+            );
+            code.terminate(Terminator::Branch(is_done, done, body));
+            code.label(body);
+            let address = index_address(
+                Address::Pointer(string, 0),
+                Operand::Temp(index),
+                1,
+                &mut code,
+                frame,
+            );
+            let c = code.load(frame, Kind::Byte, address);
+            let is_lower = is_lowercase(c, &mut code, frame);
+            code.terminate(Terminator::Branch(is_lower, lower, next));
+            code.label(lower);
+            let upper = code.binary(
+                frame,
+                Kind::Byte,
+                BinaryOperator::Subtract,
+                c,
+                Operand::Integer(32),
+            );
+            code.store(Kind::Byte, address, upper);
+            code.label(next);
+            let previous = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Subtract,
+                Operand::Temp(index),
+                Operand::Integer(1),
+            );
+            code.push(Instruction::Move(index, previous));
+            code.terminate(Terminator::Jump(head));
+            code.label(done);
+            string
+        }
+        "POS" => {
+            let (value, tipe) = values.next().unwrap();
+            let (value_code, operand) = as_string(value, &tipe, frame);
+            code.append(value_code);
+            code.runtime(frame, Some(Kind::Word), "rt_pos", vec![string, operand])
+        }
+        // COPY keeps the characters it takes at the start of the string
+        _ => {
+            let mut numbers = Vec::new();
+            for (value, tipe) in values {
+                let (value_code, operand) = evaluate_constant(value, &tipe, frame);
+                code.append(value_code);
+                numbers.push(operand);
+            }
+            let zero = Operand::Integer(0);
+            let first = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Subtract,
+                numbers[0],
+                Operand::Integer(1),
+            );
+            let first = code.binary(frame, Kind::Word, BinaryOperator::Maximum, first, zero);
+            let count = code.binary(frame, Kind::Word, BinaryOperator::Maximum, numbers[1], zero);
+            let length = get_string_length(string, &mut code, frame);
+            let left = code.binary(frame, Kind::Word, BinaryOperator::Subtract, length, first);
+            let left = code.binary(frame, Kind::Word, BinaryOperator::Maximum, left, zero);
+            let count = code.binary(frame, Kind::Word, BinaryOperator::Minimum, count, left);
+            code.store(Kind::Byte, Address::Pointer(string, 0), count);
+            let characters = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                string,
+                Operand::Integer(1),
+            );
+            let from = code.binary(frame, Kind::Word, BinaryOperator::Add, characters, first);
+            code.copy(characters, from, count);
+            string
+        }
+    };
+    (Value::Computed(code, operand), result_tipe)
+}
+
+// returns a byte that's 1 iff the char is a lowercase letter
+fn is_lowercase(c: Operand, code: &mut Code, frame: &mut Frame) -> Operand {
+    let letter = code.binary(
+        frame,
+        Kind::Byte,
+        BinaryOperator::Subtract,
+        c,
+        Operand::Integer(b'a' as i64),
+    );
+    code.compare(
+        frame,
+        Condition::BelowEqual,
+        Kind::Byte,
+        letter,
+        Operand::Integer(25),
+    )
+}
+
+// returns the address of the word of the set at `set` holding the element `n`, and the element's
+// bit in it, which is 0 to 63
+fn get_set_word(
+    set: Operand,
+    n: Operand,
+    code: &mut Code,
+    frame: &mut Frame,
+) -> (Address, Operand) {
+    let word = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::ShiftRight,
+        n,
+        Operand::Integer(6),
+    );
+    let address = index_address(Address::Pointer(set, 0), word, 8, code, frame);
+    let bit = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::And,
+        n,
+        Operand::Integer(63),
+    );
+    (address, bit)
+}
+
+// adds code to put the element `n`, which must be 0 to 255, in the set at `set`
+fn add_to_set(set: Operand, n: Operand, code: &mut Code, frame: &mut Frame) {
+    let (address, bit) = get_set_word(set, n, code, frame);
+    let word = code.load(frame, Kind::Word, address);
+    let mask = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::ShiftLeft,
+        Operand::Integer(1),
+        bit,
+    );
+    let word = code.binary(frame, Kind::Word, BinaryOperator::Or, word, mask);
+    code.store(Kind::Word, address, word);
+}
+
+// returns code to build a set from its elements, or the set itself if they're all constants
+fn evaluate_set(
+    elements: &[ExpressionOrRange],
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let mut words = [0; 4];
+    // the set the elements that aren't constant are added to, and the code adding them
+    let mut built: Option<(Slot, Operand, Code)> = None;
+    let mut element_tipe = Type::Undefined;
+    for element in elements {
+        let bounds = match element {
+            ExpressionOrRange::Expression(expression) => vec![expression],
+            ExpressionOrRange::Range(first, last) => vec![first, last],
+        };
+        let mut values = Vec::new();
+        for expression in &bounds {
+            let (value, tipe) =
+                evaluate_expression(expression, src, label_idx, diagnostics, frame, scope);
+            if tipe == Type::Undefined {
+                continue;
+            } else if !is_ordinal(&tipe) {
+                diagnostics.push(Diagnostic::error(
+                    "P1015",
+                    expression.start,
+                    expression.end,
+                    "Set elements must be ordinal",
+                ));
+                continue;
+            } else if element_tipe == Type::Undefined {
+                element_tipe = tipe.clone();
+            } else if tipe != element_tipe {
+                diagnostics.push(Diagnostic::error(
+                    "P1016",
+                    expression.start,
+                    expression.end,
+                    "Set elements must have the same type",
+                ));
+                continue;
+            }
+            values.push((value, tipe));
+        }
+        if values.len() < bounds.len() {
+            continue;
+        }
+
+        // constant elements go straight into the set
+        if values.iter().all(|(value, _)| value.is_constant()) {
+            let numbers: Vec<i64> = values
+                .iter()
+                .map(|(value, tipe)| match value {
+                    Value::Constant(value) => get_ordinal_value(value, tipe).unwrap_or(0),
+                    Value::Computed(_, _) => 0,
+                })
+                .collect();
+            let (first, last) = (numbers[0], numbers[numbers.len() - 1]);
+            if first > last {
+                continue;
+            }
+            if first < 0 || last > 255 {
+                diagnostics.push(Diagnostic::error(
+                    "P1017",
+                    bounds[0].start,
+                    bounds[bounds.len() - 1].end,
+                    "Set elements must be numbered 0 to 255",
+                ));
+                continue;
+            }
+            for n in first..=last {
+                words[n as usize / 64] |= 1 << (n % 64);
+            }
+            continue;
+        }
+
+        // elements are numbered by words
+        let mut values: Vec<(Code, Operand)> = values
+            .into_iter()
+            .map(|(value, tipe)| {
+                let (mut code, operand) = evaluate_constant(value, &tipe, frame);
+                let operand = extend(operand, &mut code, frame);
+                (code, operand)
+            })
+            .collect();
+        let (_, set, code) = built.get_or_insert_with(|| {
+            let slot = frame.scratch(32);
+            let mut code = Code::default();
+            let set = scratch_address(slot, &mut code, frame);
+            (slot, set, code)
+        });
+        let set = *set;
+        // elements outside of 0 to 255 can't be in the set, so they're left out
+        if values.len() == 1 {
+            let (value_code, n) = values.pop().unwrap();
+            code.append(value_code);
+            let add = new_label(label_idx);
+            let done = new_label(label_idx);
+            let fits = code.compare(
+                frame,
+                Condition::BelowEqual,
+                Kind::Word,
+                n,
+                Operand::Integer(255),
+            );
+            code.terminate(Terminator::Branch(fits, add, done));
+            code.label(add);
+            add_to_set(set, n, code, frame);
+            code.label(done);
+        } else {
+            // the last element is evaluated before the first
+            let (last_code, last) = values.pop().unwrap();
+            let (first_code, first) = values.pop().unwrap();
+            code.append(last_code);
+            code.append(first_code);
+            let n = frame.temp(Kind::Word);
+            let lowest = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Maximum,
+                first,
+                Operand::Integer(0),
+            );
+            code.push(Instruction::Move(n, lowest));
+            let highest = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Minimum,
+                last,
+                Operand::Integer(255),
+            );
+            let (head, body, done) = (
+                new_label(label_idx),
+                new_label(label_idx),
+                new_label(label_idx),
+            );
+            code.label(head);
+            let is_done = code.compare(
+                frame,
+                Condition::Greater,
+                Kind::Word,
+                Operand::Temp(n),
+                highest,
+            );
+            code.terminate(Terminator::Branch(is_done, done, body));
+            code.label(body);
+            add_to_set(set, Operand::Temp(n), code, frame);
+            let next = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                Operand::Temp(n),
+                Operand::Integer(1),
+            );
+            code.push(Instruction::Move(n, next));
+            code.terminate(Terminator::Jump(head));
+            code.label(done);
+        }
+    }
+
+    let tipe = Type::Set(Box::new(element_tipe));
+    match built {
+        None => (Value::Constant(format_set(words)), tipe),
+        // the constant elements are stored first
+        Some((slot, set, elements_code)) => {
+            let mut code = Code::default();
+            for (n, word) in words.iter().enumerate() {
+                let address = Address::Slot {
+                    hops: 0,
+                    slot,
+                    offset: 8 * n as isize,
+                };
+                code.store(Kind::Word, address, Operand::Integer(*word as i64));
+            }
+            code.append(elements_code);
+            (Value::Computed(code, set), tipe)
+        }
+    }
+}
+
+fn evaluate_term(
+    term: &Term,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let (mut value1, mut tipe1) =
+        evaluate_factor(&term.operands[0], src, label_idx, diagnostics, frame, scope);
+
+    for (operator, factor) in term.operators.iter().zip(&term.operands[1..]) {
+        let (value2, tipe2) = evaluate_factor(factor, src, label_idx, diagnostics, frame, scope);
+        let mut term_tipe = evaluate_type(tipe1.clone(), tipe2.clone());
+        if term_tipe == Type::Undefined {
+            if tipe1 != Type::Undefined && tipe2 != Type::Undefined {
+                diagnostics.push(Diagnostic::error(
+                    "P1018",
+                    term.start,
+                    term.end,
+                    "Mismatched types in term",
+                ));
+            }
+            (value1, tipe1) = (Value::Constant(String::new()), Type::Undefined);
+        } else if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2)
+        {
+            let real = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+            let boolean = |value: &str| value == "true";
+            let res = match term_tipe {
+                Type::Integer if matches!(operator.as_str(), "*" | "DIV" | "MOD" | "AND") => {
+                    match fold_integers(constant1, operator, constant2, term.start, term.end) {
+                        Ok(n) => n.to_string(),
+                        Err(diagnostic) => {
+                            diagnostics.push(diagnostic);
+                            String::from("0")
+                        }
+                    }
+                }
+                Type::Integer | Type::Real
+                    if (operator == "/" || operator == "DIV")
+                        && constant2.parse::<f64>() == Ok(0.0) =>
+                {
+                    diagnostics.push(Diagnostic::error(
+                        "P1019",
+                        term.start,
+                        term.end,
+                        "Division by zero",
+                    ));
+                    term_tipe = Type::Real;
+                    String::from("0")
+                }
+                Type::Real if operator == "*" => format!("{}", real(constant1) * real(constant2)),
+                Type::Real if operator == "/" => format!("{}", real(constant1) / real(constant2)),
+                Type::Real if operator == "MOD" => {
+                    format!("{}", real(constant1) % real(constant2))
+                }
+                Type::Boolean if operator == "AND" => {
+                    format!("{}", boolean(constant1) && boolean(constant2))
+                }
+                Type::Set(_) if operator == "*" => {
+                    let words2 = parse_set(constant2);
+                    let mut words = parse_set(constant1);
+                    for n in 0..4 {
+                        words[n] &= words2[n];
+                    }
+                    format_set(words)
+                }
+                Type::Integer if operator == "/" => {
+                    diagnostics.push(
+                        Diagnostic::warning("P2001", term.start, term.end, "/ is for reals")
+                            .with_note("Did you mean DIV?"),
+                    );
+                    term_tipe = Type::Real;
+                    format!("{}", real(constant1) / real(constant2))
+                }
+                Type::Real if operator == "DIV" => {
+                    diagnostics.push(
+                        Diagnostic::warning("P2002", term.start, term.end, "DIV is for integers")
+                            .with_note("Did you mean /?"),
+                    );
+                    format!("{}", real(constant1) / real(constant2))
+                }
+                _ => String::new(),
+            };
+            (value1, tipe1) = (Value::Constant(res), term_tipe);
+        } else {
+            // the second operand is evaluated first
+            let (mut code, mut operand2) = evaluate_constant(value2, &tipe2, frame);
+            let (code1, mut operand1) = evaluate_constant(value1, &tipe1, frame);
+            code.append(code1);
+            if term_tipe == Type::Real {
+                operand1 = to_real(operand1, &tipe1, &mut code, frame);
+                operand2 = to_real(operand2, &tipe2, &mut code, frame);
+            }
+
+            let operand = match operator.as_str() {
+                "*" if term_tipe == Type::Integer => checked_binary(
+                    BinaryOperator::Multiply,
+                    operand1,
+                    operand2,
+                    term.start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                    scope,
+                ),
+                "*" if term_tipe == Type::Real => code.binary(
+                    frame,
+                    Kind::Real,
+                    BinaryOperator::Multiply,
+                    operand1,
+                    operand2,
+                ),
+                "DIV" | "MOD" if term_tipe == Type::Integer => divide_integers(
+                    if operator == "DIV" {
+                        BinaryOperator::Divide
+                    } else {
+                        BinaryOperator::Remainder
+                    },
+                    operand1,
+                    operand2,
+                    term.start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                    scope,
+                ),
+                "/" if term_tipe == Type::Integer => {
+                    diagnostics.push(
+                        Diagnostic::warning("P2001", term.start, term.end, "/ is for reals")
+                            .with_note("Did you mean DIV?"),
+                    );
+                    term_tipe = Type::Real;
+                    let operand1 = to_real(operand1, &Type::Integer, &mut code, frame);
+                    let operand2 = to_real(operand2, &Type::Integer, &mut code, frame);
+                    divide_reals(
+                        operand1, operand2, term.start, src, label_idx, &mut code, frame, scope,
+                    )
+                }
+                "/" if term_tipe == Type::Real => divide_reals(
+                    operand1, operand2, term.start, src, label_idx, &mut code, frame, scope,
+                ),
+                "DIV" if term_tipe == Type::Real => {
+                    diagnostics.push(
+                        Diagnostic::warning("P2002", term.start, term.end, "DIV is for integers")
+                            .with_note("Did you mean /?"),
+                    );
+                    divide_reals(
+                        operand1, operand2, term.start, src, label_idx, &mut code, frame, scope,
+                    )
+                }
+                "MOD" if term_tipe == Type::Real => {
+                    code.runtime(frame, Some(Kind::Real), "rt_fmod", vec![operand1, operand2])
+                }
+                "AND" if term_tipe == Type::Integer => {
+                    code.binary(frame, Kind::Word, BinaryOperator::And, operand1, operand2)
+                }
+                "AND" if term_tipe == Type::Boolean => {
+                    code.binary(frame, Kind::Byte, BinaryOperator::And, operand1, operand2)
+                }
+                "*" if matches!(term_tipe, Type::Set(_)) => {
+                    combine_sets(
+                        BinaryOperator::And,
+                        false,
+                        operand1,
+                        operand2,
+                        &mut code,
+                        frame,
+                    );
+                    operand1
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1020",
+                        term.start,
+                        term.end,
+                        "Unrecognized operation in term",
+                    ));
+                    Operand::Integer(0)
+                }
+            };
+            (value1, tipe1) = (Value::Computed(code, operand), term_tipe);
+        }
+    }
+    (value1, tipe1)
+}
+
+fn evaluate_simple_expression(
+    simple_expression: &SimpleExpression,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let (mut value1, mut tipe1) = evaluate_term(
+        &simple_expression.operands[0],
+        src,
+        label_idx,
+        diagnostics,
+        frame,
+        scope,
+    );
+    let (start, end) = (simple_expression.start, simple_expression.end);
+
+    if !simple_expression.positive {
+        value1 = match (value1, &tipe1) {
+            (value, Type::Set(_)) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1021",
+                    start,
+                    end,
+                    "Unrecognized attempt to negate first term",
+                ));
+                value
+            }
+            (Value::Constant(value), _) => Value::Constant(match tipe1 {
+                // the lowest integer is only written as a negated literal
+                Type::Integer if value == "9223372036854775808" => i64::MIN.to_string(),
+                Type::Integer => match value.parse::<i64>().ok().and_then(i64::checked_neg) {
+                    Some(n) => n.to_string(),
+                    None => {
+                        diagnostics.push(Diagnostic::error(
+                            "P1001",
+                            start,
+                            end,
+                            "Integer overflow",
+                        ));
+                        String::from("0")
+                    }
+                },
+                Type::Real => (-value.parse::<f64>().unwrap_or(0.0)).to_string(),
+                _ => format!("-{}", value),
+            }),
+            (Value::Computed(mut code, operand), Type::Integer) => {
+                let operand =
+                    checked_negate(operand, start, src, label_idx, &mut code, frame, scope);
+                Value::Computed(code, operand)
+            }
+            (Value::Computed(mut code, operand), Type::Real) => {
+                let operand = code.unary(frame, Kind::Real, UnaryOperator::Negate, operand);
+                Value::Computed(code, operand)
+            }
+            (value, Type::Undefined) => value,
+            (value, _) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1021",
+                    start,
+                    end,
+                    "Unrecognized attempt to negate first term",
+                ));
+                value
+            }
+        };
+    }
+
+    for (operator, term) in simple_expression
+        .operators
+        .iter()
+        .zip(&simple_expression.operands[1..])
+    {
+        let (value2, tipe2) = evaluate_term(term, src, label_idx, diagnostics, frame, scope);
+        // chars joined together make a string
+        let simple_expression_tipe =
+            if operator == "+" && tipe1 == Type::Char && tipe2 == Type::Char {
+                Type::Stryng
+            } else {
+                evaluate_type(tipe1.clone(), tipe2.clone())
+            };
+        if simple_expression_tipe == Type::Undefined {
+            if tipe1 != Type::Undefined && tipe2 != Type::Undefined {
+                diagnostics.push(Diagnostic::error(
+                    "P1018",
+                    start,
+                    end,
+                    "Mismatched types in term",
+                ));
+            }
+            (value1, tipe1) = (Value::Constant(String::new()), Type::Undefined);
+        } else if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2)
+        {
+            let real = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+            let boolean = |value: &str| value == "true";
+            let res = match simple_expression_tipe {
+                Type::Integer if matches!(operator.as_str(), "+" | "-" | "OR") => {
+                    match fold_integers(constant1, operator, constant2, start, end) {
+                        Ok(n) => n.to_string(),
+                        Err(diagnostic) => {
+                            diagnostics.push(diagnostic);
+                            String::from("0")
+                        }
+                    }
+                }
+                Type::Real if operator == "+" => format!("{}", real(constant1) + real(constant2)),
+                Type::Stryng if operator == "+" => {
+                    let text = get_string_constant(constant1, &tipe1)
+                        + &get_string_constant(constant2, &tipe2);
+                    check_string_constant(text, start, end, diagnostics)
+                }
+                Type::Real if operator == "-" => format!("{}", real(constant1) - real(constant2)),
+                Type::Boolean if operator == "OR" => {
+                    format!("{}", boolean(constant1) || boolean(constant2))
+                }
+                Type::Set(_) if operator == "+" || operator == "-" => {
+                    let words2 = parse_set(constant2);
+                    let mut words = parse_set(constant1);
+                    for n in 0..4 {
+                        words[n] = if operator == "+" {
+                            words[n] | words2[n]
+                        } else {
+                            words[n] & !words2[n]
+                        };
+                    }
+                    format_set(words)
+                }
+                _ => String::new(),
+            };
+            (value1, tipe1) = (Value::Constant(res), simple_expression_tipe);
+        } else if simple_expression_tipe == Type::Stryng && operator == "+" {
+            // the first operand is evaluated first, and the second appended to it
+            let (mut code, string1) = as_string(value1, &tipe1, frame);
+            let (code2, string2) = as_string(value2, &tipe2, frame);
+            code.append(code2);
+            concatenate_strings(string1, string2, start, src, label_idx, &mut code, frame);
+            (value1, tipe1) = (Value::Computed(code, string1), Type::Stryng);
+        } else {
+            // the second operand is evaluated first
+            let (mut code, mut operand2) = evaluate_constant(value2, &tipe2, frame);
+            let (code1, mut operand1) = evaluate_constant(value1, &tipe1, frame);
+            code.append(code1);
+            match simple_expression_tipe {
+                Type::Real => {
+                    operand1 = to_real(operand1, &tipe1, &mut code, frame);
+                    operand2 = to_real(operand2, &tipe2, &mut code, frame);
+                }
+                Type::Char => {
+                    operand1 = narrow(operand1, &mut code, frame);
+                    operand2 = narrow(operand2, &mut code, frame);
+                }
+                _ => {}
+            }
+
+            let operand = match (operator.as_str(), &simple_expression_tipe) {
+                ("+", Type::Integer) => checked_binary(
+                    BinaryOperator::Add,
+                    operand1,
+                    operand2,
+                    start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                    scope,
+                ),
+                ("-", Type::Integer) => checked_binary(
+                    BinaryOperator::Subtract,
+                    operand1,
+                    operand2,
+                    start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                    scope,
+                ),
+                ("+", Type::Real) => {
+                    code.binary(frame, Kind::Real, BinaryOperator::Add, operand1, operand2)
+                }
+                ("-", Type::Real) => code.binary(
+                    frame,
+                    Kind::Real,
+                    BinaryOperator::Subtract,
+                    operand1,
+                    operand2,
+                ),
+                ("+", Type::Char) => {
+                    code.binary(frame, Kind::Byte, BinaryOperator::Add, operand1, operand2)
+                }
+                ("-", Type::Char) => code.binary(
+                    frame,
+                    Kind::Byte,
+                    BinaryOperator::Subtract,
+                    operand1,
+                    operand2,
+                ),
+                ("OR", Type::Integer) => {
+                    code.binary(frame, Kind::Word, BinaryOperator::Or, operand1, operand2)
+                }
+                ("OR", Type::Boolean) => {
+                    code.binary(frame, Kind::Byte, BinaryOperator::Or, operand1, operand2)
+                }
+                ("+" | "-", Type::Set(_)) => {
+                    let (operator, negate) = if operator == "+" {
+                        (BinaryOperator::Or, false)
+                    } else {
+                        (BinaryOperator::And, true)
+                    };
+                    combine_sets(operator, negate, operand1, operand2, &mut code, frame);
+                    operand1
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1022",
+                        start,
+                        end,
+                        "Unrecognized operation",
+                    ));
+                    Operand::Integer(0)
+                }
+            };
+            (value1, tipe1) = (Value::Computed(code, operand), simple_expression_tipe);
+        }
+    }
+    // literals can be too big for an integer
+    if let Value::Constant(value) = &value1 {
+        if tipe1 == Type::Integer && value.parse::<i64>().is_err() {
+            diagnostics.push(Diagnostic::error("P1001", start, end, "Integer overflow"));
+            value1 = Value::Constant(String::from("0"));
+        }
+    }
+    (value1, tipe1)
+}
+
+// returns the condition for a comparison operator, which compares reals with the unsigned
+// conditions, or None if it isn't one
+fn get_condition(operator: &str, is_real: bool) -> Option<Condition> {
+    Some(match (operator, is_real) {
+        ("<", false) => Condition::Less,
+        ("<=", false) => Condition::LessEqual,
+        (">", false) => Condition::Greater,
+        (">=", false) => Condition::GreaterEqual,
+        ("<", true) => Condition::Below,
+        ("<=", true) => Condition::BelowEqual,
+        (">", true) => Condition::Above,
+        (">=", true) => Condition::AboveEqual,
+        ("=", _) => Condition::Equal,
+        ("<>", _) => Condition::NotEqual,
+        _ => return None,
+    })
+}
+
+fn evaluate_expression(
+    expression: &Expression,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Value, Type) {
+    let (value1, tipe1) = evaluate_simple_expression(
+        &expression.operand1,
+        src,
+        label_idx,
+        diagnostics,
+        frame,
+        scope,
+    );
+    if expression.operator == "NONE" {
+        return (value1, tipe1);
+    }
+
+    let (value2, tipe2) = evaluate_simple_expression(
+        &expression.operand2,
+        src,
+        label_idx,
+        diagnostics,
+        frame,
+        scope,
+    );
+    let expression_tipe = evaluate_type(tipe1.clone(), tipe2.clone());
+
+    if expression.operator == "IN" {
+        return evaluate_membership(
+            value1,
+            &tipe1,
+            value2,
+            &tipe2,
+            expression,
+            label_idx,
+            diagnostics,
+            frame,
+        );
+    } else if let (Type::Set(_), Type::Set(_)) = (&tipe1, &tipe2) {
+        return compare_sets(
+            value1,
+            &tipe1,
+            value2,
+            &tipe2,
+            expression,
+            diagnostics,
+            frame,
+        );
+    } else if expression_tipe == Type::Stryng {
+        return compare_strings(
+            value1,
+            &tipe1,
+            value2,
+            &tipe2,
+            expression,
+            diagnostics,
+            frame,
+        );
+    }
+
+    if expression_tipe == Type::Undefined {
+        if tipe1 != Type::Undefined && tipe2 != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1024",
+                expression.start,
+                expression.end,
+                "Mismatched types in expression",
+            ));
+        }
+        return (Value::empty(), Type::Boolean);
+    }
+    let unrecognized = |diagnostics: &mut Vec<Diagnostic>| {
+        diagnostics.push(Diagnostic::error(
+            "P1023",
+            expression.start,
+            expression.end,
+            "Unrecognized operator",
+        ));
+    };
+
+    // NIL is the only pointer constant, so comparing constant pointers isn't worth folding
+    if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2) {
+        if !matches!(expression_tipe, Type::Pointer(_)) {
+            let ordering = if expression_tipe == Type::Real {
+                let real = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+                real(constant1).partial_cmp(&real(constant2))
+            } else if matches!(
+                expression_tipe,
+                Type::Integer | Type::Boolean | Type::Enumeration(_)
+            ) {
+                let n1 = get_ordinal_value(constant1, &expression_tipe).unwrap_or(0);
+                let n2 = get_ordinal_value(constant2, &expression_tipe).unwrap_or(0);
+                Some(n1.cmp(&n2))
+            } else {
+                // chars are the only other constants left, as mismatches have been reported
+                let c1 = constant1.parse::<u8>().unwrap_or(0);
+                let c2 = constant2.parse::<u8>().unwrap_or(0);
+                Some(c1.cmp(&c2))
+            };
+            let out = match expression.operator.as_str() {
+                "<" => ordering.is_some_and(|ordering| ordering.is_lt()),
+                "<=" => ordering.is_some_and(|ordering| ordering.is_le()),
+                "=" => ordering.is_some_and(|ordering| ordering.is_eq()),
+                "<>" => ordering.is_none_or(|ordering| ordering.is_ne()),
+                ">" => ordering.is_some_and(|ordering| ordering.is_gt()),
+                ">=" => ordering.is_some_and(|ordering| ordering.is_ge()),
+                _ => {
+                    unrecognized(diagnostics);
+                    return (Value::Constant(String::new()), Type::Boolean);
+                }
+            };
+            return (Value::Constant(out.to_string()), Type::Boolean);
+        }
+    }
+
+    let (mut code, mut operand1) = evaluate_constant(value1, &tipe1, frame);
+    let (code2, mut operand2) = evaluate_constant(value2, &tipe2, frame);
+    code.append(code2);
+    let is_real = expression_tipe == Type::Real;
+    let kind = match expression_tipe {
+        Type::Real => {
+            operand1 = to_real(operand1, &tipe1, &mut code, frame);
+            operand2 = to_real(operand2, &tipe2, &mut code, frame);
+            Kind::Real
+        }
+        Type::Char | Type::Boolean => {
+            operand1 = narrow(operand1, &mut code, frame);
+            operand2 = narrow(operand2, &mut code, frame);
+            Kind::Byte
+        }
+        Type::Pointer(_) if !matches!(expression.operator.as_str(), "=" | "<>") => {
+            diagnostics.push(Diagnostic::error(
+                "P1025",
+                expression.start,
+                expression.end,
+                "Pointers can only be compared with = or <>",
+            ));
+            Kind::Word
+        }
+        _ => Kind::Word,
+    };
+    let Some(condition) = get_condition(&expression.operator, is_real) else {
+        unrecognized(diagnostics);
+        return (Value::empty(), Type::Boolean);
+    };
+    let operand = code.compare(frame, condition, kind, operand1, operand2);
+    (Value::Computed(code, operand), Type::Boolean)
+}
+
+// returns code to test whether the element `value1` is in the set `value2`
+#[allow(clippy::too_many_arguments)]
+fn evaluate_membership(
+    value1: Value,
+    tipe1: &Type,
+    value2: Value,
+    tipe2: &Type,
+    expression: &Expression,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+) -> (Value, Type) {
+    if *tipe1 == Type::Undefined || *tipe2 == Type::Undefined {
+        return (Value::empty(), Type::Boolean);
+    }
+    let matches = match tipe2 {
+        Type::Set(element_tipe) => {
+            is_ordinal(tipe1) && (**element_tipe == *tipe1 || **element_tipe == Type::Undefined)
+        }
+        _ => false,
+    };
+    if !matches {
+        diagnostics.push(Diagnostic::error(
+            "P1026",
+            expression.start,
+            expression.end,
+            "IN expects an ordinal value and a set of its type",
+        ));
+        return (Value::empty(), Type::Boolean);
+    }
+
+    if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2) {
+        let n = get_ordinal_value(constant1, tipe1).unwrap_or(-1);
+        let is_member =
+            (0..=255).contains(&n) && parse_set(constant2)[n as usize / 64] & (1 << (n % 64)) != 0;
+        return (Value::Constant(is_member.to_string()), Type::Boolean);
+    }
+    // the set is evaluated first
+    let (mut code, set) = evaluate_constant(value2, tipe2, frame);
+    let (code1, n) = evaluate_constant(value1, tipe1, frame);
+    code.append(code1);
+    let n = extend(n, &mut code, frame);
+    // elements outside of 0 to 255 are never in a set
+    let result = frame.temp(Kind::Byte);
+    code.push(Instruction::Move(result, Operand::Integer(0)));
+    let test = new_label(label_idx);
+    let done = new_label(label_idx);
+    let fits = code.compare(
+        frame,
+        Condition::BelowEqual,
+        Kind::Word,
+        n,
+        Operand::Integer(255),
+    );
+    code.terminate(Terminator::Branch(fits, test, done));
+    code.label(test);
+    let (address, bit) = get_set_word(set, n, &mut code, frame);
+    let word = code.load(frame, Kind::Word, address);
+    let shifted = code.binary(frame, Kind::Word, BinaryOperator::ShiftRight, word, bit);
+    let is_member = code.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::And,
+        shifted,
+        Operand::Integer(1),
+    );
+    let is_member = code.unary(frame, Kind::Byte, UnaryOperator::Narrow, is_member);
+    code.push(Instruction::Move(result, is_member));
+    code.label(done);
+    (Value::Computed(code, Operand::Temp(result)), Type::Boolean)
+}
+
+// returns code to compare the sets `value1` and `value2`. <= tests for a subset and >= for a
+// superset.
+fn compare_sets(
+    value1: Value,
+    tipe1: &Type,
+    value2: Value,
+    tipe2: &Type,
+    expression: &Expression,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+) -> (Value, Type) {
+    let operator = expression.operator.as_str();
+    if evaluate_type(tipe1.clone(), tipe2.clone()) == Type::Undefined {
+        diagnostics.push(Diagnostic::error(
+            "P1024",
+            expression.start,
+            expression.end,
+            "Mismatched types in expression",
+        ));
+        return (Value::empty(), Type::Boolean);
+    } else if !matches!(operator, "=" | "<>" | "<=" | ">=") {
+        diagnostics.push(Diagnostic::error(
+            "P1027",
+            expression.start,
+            expression.end,
+            "Sets can only be compared with =, <>, <= or >=",
+        ));
+        return (Value::empty(), Type::Boolean);
+    }
+
+    if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2) {
+        let words1 = parse_set(constant1);
+        let words2 = parse_set(constant2);
+        let is_true = match operator {
+            "=" => words1 == words2,
+            "<>" => words1 != words2,
+            "<=" => (0..4).all(|n| words1[n] & !words2[n] == 0),
+            _ => (0..4).all(|n| words2[n] & !words1[n] == 0),
+        };
+        return (Value::Constant(is_true.to_string()), Type::Boolean);
+    }
+    let (mut code, set1) = evaluate_constant(value1, tipe1, frame);
+    let (code2, set2) = evaluate_constant(value2, tipe2, frame);
+    code.append(code2);
+    // or together the words which are nonzero iff the comparison fails (or succeeds, for <>)
+    let mut differences = Operand::Integer(0);
+    for n in 0..4 {
+        let mut word1 = code.load(frame, Kind::Word, Address::Pointer(set1, 8 * n));
+        let mut word2 = code.load(frame, Kind::Word, Address::Pointer(set2, 8 * n));
+        let operator = match operator {
+            "=" | "<>" => BinaryOperator::Xor,
+            "<=" => {
+                word2 = code.unary(frame, Kind::Word, UnaryOperator::Not, word2);
+                BinaryOperator::And
+            }
+            _ => {
+                word1 = code.unary(frame, Kind::Word, UnaryOperator::Not, word1);
+                BinaryOperator::And
+            }
+        };
+        let difference = code.binary(frame, Kind::Word, operator, word1, word2);
+        differences = code.binary(
+            frame,
+            Kind::Word,
+            BinaryOperator::Or,
+            differences,
+            difference,
+        );
+    }
+    let condition = if operator == "<>" {
+        Condition::NotEqual
+    } else {
+        Condition::Equal
+    };
+    let operand = code.compare(
+        frame,
+        condition,
+        Kind::Word,
+        differences,
+        Operand::Integer(0),
+    );
+    (Value::Computed(code, operand), Type::Boolean)
+}
+
+// returns code to compare the strings (or chars) `value1` and `value2` a character at a time. A
+// string that runs out first is the lesser.
+fn compare_strings(
+    value1: Value,
+    tipe1: &Type,
+    value2: Value,
+    tipe2: &Type,
+    expression: &Expression,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+) -> (Value, Type) {
+    let operator = expression.operator.as_str();
+    let Some(condition) = get_condition(operator, false) else {
+        diagnostics.push(Diagnostic::error(
+            "P1023",
+            expression.start,
+            expression.end,
+            "Unrecognized operator",
+        ));
+        return (Value::empty(), Type::Boolean);
+    };
+
+    if let (Value::Constant(constant1), Value::Constant(constant2)) = (&value1, &value2) {
+        let text1 = get_string_constant(constant1, tipe1);
+        let text2 = get_string_constant(constant2, tipe2);
+        let is_true = match operator {
+            "<" => text1 < text2,
+            "<=" => text1 <= text2,
+            "=" => text1 == text2,
+            "<>" => text1 != text2,
+            ">" => text1 > text2,
+            _ => text1 >= text2,
+        };
+        return (Value::Constant(is_true.to_string()), Type::Boolean);
+    }
+    let (mut code, string1) = as_string(value1, tipe1, frame);
+    let (code2, string2) = as_string(value2, tipe2, frame);
+    code.append(code2);
+    // the runtime orders the strings as -1, 0 or 1
+    let order = code.runtime(
+        frame,
+        Some(Kind::Word),
+        "rt_compare_strings",
+        vec![string1, string2],
+    );
+    let operand = code.compare(frame, condition, Kind::Word, order, Operand::Integer(0));
+    (Value::Computed(code, operand), Type::Boolean)
+}
+
+// returns code for an expression, with constants put in an operand
+fn evaluate_final_expression(
+    expression: &Expression,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Code, Operand, Type) {
+    let (value, tipe) = evaluate_expression(expression, src, label_idx, diagnostics, frame, scope);
+    let (code, operand) = evaluate_constant(value, &tipe, frame);
+    (code, operand, tipe)
+}
+
+// number of %xmm registers and integer registers used to pass arguments
+const REAL_ARGUMENT_REGISTERS: usize = 8;
+const INTEGER_ARGUMENT_REGISTERS: usize = 6;
+
+// returns whether each parameter is passed on the stack under the System V calling convention,
+// which the backend follows. VAR parameters are passed as addresses, so they are always integers.
+fn get_stack_arguments(parameters: &[(Type, bool)]) -> Vec<bool> {
+    let mut integer_count = 0;
+    let mut real_count = 0;
+    let mut result = Vec::new();
+    for (tipe, is_reference) in parameters {
+        let is_real = *tipe == Type::Real && !is_reference;
+        if is_real && real_count < REAL_ARGUMENT_REGISTERS {
+            result.push(false);
+            real_count += 1;
+        } else if !is_real && integer_count < INTEGER_ARGUMENT_REGISTERS {
+            result.push(false);
+            integer_count += 1;
+        } else {
+            result.push(true);
+        }
+    }
+    result
+}
+
+// returns code to call a user defined procedure or function, and its result. A string result is
+// written by the callee to a slot of the caller's, whose address is passed after the arguments.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_call(
+    signature: &Signature,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Code, Operand) {
+    let mut code = Code::default();
+    if arguments.len() != signature.parameters.len() {
+        diagnostics.push(Diagnostic::error(
+            "P1003",
+            start,
+            end,
+            &format!("Expected {} argument(s)", signature.parameters.len()),
+        ));
+        return (code, Operand::Integer(0));
+    }
+    let mut operands = vec![Operand::Integer(0); arguments.len()];
+
+    // sets and strings passed by value can be any expression, so they're evaluated into slots
+    // first and their addresses passed instead
+    for (idx, (argument, (parameter, is_reference))) in
+        arguments.iter().zip(&signature.parameters).enumerate()
+    {
+        if *is_reference || !matches!(parameter, Type::Set(_) | Type::Stryng) {
+            continue;
+        }
+        let (value, tipe) =
+            evaluate_expression(argument, src, label_idx, diagnostics, frame, scope);
+        if !is_assignable(parameter, &tipe) {
+            diagnostics.push(Diagnostic::error(
+                "P1028",
+                argument.start,
+                argument.end,
+                "Mismatched argument type",
+            ));
+        }
+        let (value_code, operand) = if *parameter == Type::Stryng {
+            as_string(value, &tipe, frame)
+        } else {
+            evaluate_constant(value, &tipe, frame)
+        };
+        code.append(value_code);
+        operands[idx] = operand;
+    }
+
+    // stack arguments are evaluated right to left, followed by register arguments right to left
+    let on_stack = get_stack_arguments(&signature.parameters);
+    let mut order: Vec<usize> = (0..arguments.len())
+        .filter(|idx| on_stack[*idx])
+        .rev()
+        .collect();
+    order.extend((0..arguments.len()).filter(|idx| !on_stack[*idx]).rev());
+    for idx in order {
+        let argument = &arguments[idx];
+        let (parameter, is_reference) = &signature.parameters[idx];
+        if let (Type::Set(_) | Type::Stryng, false) = (parameter, is_reference) {
+            continue;
+        }
+        if *is_reference || is_copied_by_address(parameter) {
+            // pass by address, value arrays and records are then copied by the callee
+            let address = get_expression_variable(argument).and_then(|factor| {
+                evaluate_address(factor, src, label_idx, diagnostics, frame, scope)
+            });
+            match address {
+                Some((address_code, address, tipe)) if tipe == *parameter => {
+                    code.append(address_code);
+                    operands[idx] = code.address(frame, address);
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1013",
+                        argument.start,
+                        argument.end,
+                        "Expected variable of matching type",
+                    ));
+                }
+            }
+            continue;
+        }
+        let (value_code, operand, tipe) =
+            evaluate_final_expression(argument, src, label_idx, diagnostics, frame, scope);
+        code.append(value_code);
+        check_assignment(
+            operand,
+            parameter,
+            argument.start,
+            src,
+            label_idx,
+            &mut code,
+            frame,
+            scope,
+        );
+        if !is_assignable(parameter, &tipe) {
+            diagnostics.push(Diagnostic::error(
+                "P1028",
+                argument.start,
+                argument.end,
+                "Mismatched argument type",
+            ));
+        }
+        operands[idx] = if *parameter == Type::Real {
+            to_real(operand, &tipe, &mut code, frame)
+        } else {
+            operand
+        };
+    }
+
+    let kind = match &signature.return_type {
+        Some(Type::Stryng) => {
+            let result = scratch_address(frame.scratch(MAX_STRING_LENGTH + 1), &mut code, frame);
+            operands.push(result);
+            None
+        }
+        Some(return_type) => Some(get_kind(return_type)),
+        None => None,
+    };
+    // the callee's static link is the frame of the routine it was declared in
+    let link = frame.temp(Kind::Word);
+    code.push(Instruction::Frame(link, scope.depth + 1 - signature.depth));
+    let callee = Callee::Routine(signature.label.clone(), Some(Operand::Temp(link)));
+    let result = code.call(frame, kind, callee, operands.clone());
+    match kind {
+        None if signature.return_type.is_some() => (code, *operands.last().unwrap()),
+        _ => (code, result),
+    }
+}
+
+// returns the signature of a procedure declared in the given scope
+fn get_signature(
+    procedure: &Procedure,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) -> Signature {
+    let label = format!("{}_{}", procedure.name, *label_idx);
+    *label_idx += 1;
+    let parameters = procedure
+        .parameters
+        .iter()
+        .map(|parameter| {
+            (
+                convert_supertype_to_type(&parameter.tipe, src, label_idx, diagnostics, scope),
+                parameter.by_reference,
+            )
+        })
+        .collect();
+    let return_type = procedure.return_type.as_ref().map(|return_type| {
+        convert_supertype_to_type(return_type, src, label_idx, diagnostics, scope)
+    });
+    Signature {
+        label,
+        parameters,
+        return_type,
+        depth: scope.depth + 1,
+    }
+}
+
+// adds the routine with the given name to `routines`, followed by all routines declared in it.
+// `heading` is the procedure whose body is `code`, or None for the main program, and `parent` is
+// the index of the routine it's declared in.
+#[allow(clippy::too_many_arguments)]
+fn process_block(
+    code: &Block,
+    heading: Option<&Procedure>,
+    program_parameters: &[(String, usize, usize)],
+    label: &str,
+    parent_routine: Option<usize>,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    routines: &mut Vec<ir::Routine>,
+    parent: &Scope,
+) {
+    let mut scope = Scope {
+        parent: Some(parent),
+        depth: if heading.is_some() {
+            parent.depth + 1
+        } else {
+            parent.depth
+        },
+        variables: HashMap::new(),
+        constants: HashMap::new(),
+        procedures: HashMap::new(),
+        types: HashMap::new(),
+        fields: HashMap::new(),
+        labels: HashMap::new(),
+        declarations: HashMap::new(),
+        options: parent.options,
+    };
+    add_constants(&code.constants, src, label_idx, diagnostics, &mut scope);
+    add_types(&code.types, src, label_idx, diagnostics, &mut scope);
+
+    // string results are written straight to where the caller wants them, so the result is kept
+    // as an address, like a VAR parameter
+    let returns_string = heading.is_some_and(|procedure| {
+        matches!(parent.get_procedure(&procedure.name),
+            Some(signature) if signature.return_type == Some(Type::Stryng))
+    });
+
+    // everything in the stack frame: parameters, then function result, then local variables
+    let mut frame_variables: Vec<Variable> = Vec::new();
+    let mut parameters: Vec<Parameter> = Vec::new();
+    if let Some(procedure) = heading {
+        parameters.extend(procedure.parameters.iter().cloned());
+        if let Some(return_type) = &procedure.return_type {
+            if returns_string {
+                parameters.push(Parameter {
+                    name: procedure.name.clone(),
+                    tipe: return_type.clone(),
+                    by_reference: true,
+                });
+            } else {
+                frame_variables.push(Variable {
+                    name: procedure.name.clone(),
+                    tipe: return_type.clone(),
+                });
+            }
+        }
+    }
+    frame_variables.extend(code.local_variables.iter().map(|variable| Variable {
+        name: variable.name.clone(),
+        tipe: variable.tipe.clone(),
+    }));
+
+    let mut frame = Frame::default();
+    scope.variables = get_variable_map(
+        &parameters,
+        &frame_variables,
+        src,
+        label_idx,
+        diagnostics,
+        &mut frame,
+        &scope,
+    );
+    for variable in &code.local_variables {
+        let tipe = scope.variables[&variable.name].1.clone();
+        add_enumeration_constants(&variable.tipe, &tipe, &mut scope);
+    }
+
+    let mut prefixing = HashSet::new();
+    find_labels(&code.body, diagnostics, &mut prefixing);
+    for (label, start, end) in &code.labels {
+        if scope.labels.contains_key(label) {
+            let (_, first_start, first_end) = code
+                .labels
+                .iter()
+                .find(|(other, _, _)| other == label)
+                .unwrap();
+            diagnostics.push(
+                Diagnostic::error("P1029", *start, *end, "Label declared twice").with_label(
+                    *first_start,
+                    *first_end,
+                    "First declared here",
+                ),
+            );
+        }
+        let ir_label = new_label(label_idx);
+        scope
+            .labels
+            .insert(*label, (ir_label, prefixing.contains(label)));
+    }
+
+    // procedures declared here can call each other, and themselves
+    let mut signatures = Vec::new();
+    for procedure in &code.procedures {
+        let signature = get_signature(procedure, src, label_idx, diagnostics, &scope);
+        scope
+            .procedures
+            .insert(procedure.name.clone(), signature.clone());
+        scope
+            .declarations
+            .insert(procedure.name.clone(), (procedure.start, procedure.end));
+        signatures.push(signature);
+    }
+
+    // variables assigned in the procedures declared here may be assigned by any call, so they
+    // aren't checked
+    let mut tracked: HashMap<String, bool> = code
+        .local_variables
+        .iter()
+        .filter_map(|variable| {
+            let tipe = &scope.variables[&variable.name].1;
+            let is_aggregate = matches!(tipe, Type::Array(_, _, _, _) | Type::Record(_, _));
+            (!contains_file(tipe)).then(|| (variable.name.clone(), is_aggregate))
+        })
+        .collect();
+    let assigned_in_procedures =
+        find_assigned_in_procedures(&code.procedures, &tracked, src, &scope);
+    tracked.retain(|name, _| !assigned_in_procedures.contains(name));
+    check_initialization(
+        &code.body,
+        &mut tracked,
+        &mut Assigned::default(),
+        true,
+        src,
+        diagnostics,
+        &scope,
+    );
+
+    // arguments arrive in temporaries, and are stored into their variables. Arrays and records
+    // passed by value are copied from the address passed.
+    let mut out = Code::default();
+    let mut parameter_temps = Vec::new();
+    if let Some(procedure) = heading {
+        let names = (procedure.parameters.iter().map(|parameter| &parameter.name))
+            .chain(returns_string.then_some(&procedure.name));
+        for name in names {
+            let (slot, tipe, is_reference) = &scope.variables[name];
+            let address = Address::Slot {
+                hops: 0,
+                slot: *slot,
+                offset: 0,
+            };
+            let kind = if *is_reference || is_copied_by_address(tipe) {
+                Kind::Word
+            } else {
+                get_kind(tipe)
+            };
+            let temp = frame.temp(kind);
+            parameter_temps.push(temp);
+            if !*is_reference && is_copied_by_address(tipe) {
+                let variable = out.address(&mut frame, address);
+                out.copy(
+                    variable,
+                    Operand::Temp(temp),
+                    Operand::Integer(get_size(tipe) as i64),
+                );
+            } else {
+                out.store(kind, address, Operand::Temp(temp));
+            }
+        }
+    }
+
+    if scope.options.uninitialized_checks {
+        for variable in &code.local_variables {
+            let (slot, tipe, _) = &scope.variables[&variable.name];
+            if contains_file(tipe) {
+                continue;
+            }
+            let variable = scratch_address(*slot, &mut out, &mut frame);
+            out.push(Instruction::Fill(variable, POISON as u8, get_size(tipe)));
+        }
+    }
+
+    // files are closed and unnamed until they're assigned and opened, except for input and
+    // output, which are bound to stdin and stdout, and the other program parameters, which are
+    // named after themselves
+    for (name, start, end) in program_parameters {
+        let is_declared = matches!(scope.variables.get(name), Some((_, tipe, _)) if is_file(tipe));
+        if name != "INPUT" && name != "OUTPUT" && !is_declared {
+            diagnostics.push(Diagnostic::error(
+                "P1030",
+                *start,
+                *end,
+                "Program parameters must be declared as file variables",
+            ));
+        }
+    }
+    for variable in &code.local_variables {
+        let (slot, tipe, _) = &scope.variables[&variable.name];
+        if !is_file(tipe) {
+            continue;
+        }
+        let stream = match variable.name.as_str() {
+            "INPUT" if heading.is_none() => Some(Stream::Input),
+            "OUTPUT" if heading.is_none() => Some(Stream::Output),
+            _ => None,
+        };
+        let handle = match stream {
+            Some(stream) => {
+                let temp = frame.temp(Kind::Word);
+                out.push(Instruction::Stream(temp, stream));
+                Operand::Temp(temp)
+            }
+            None => Operand::Integer(0),
+        };
+        let is_named = variable.name != "INPUT"
+            && variable.name != "OUTPUT"
+            && program_parameters
+                .iter()
+                .any(|(name, _, _)| *name == variable.name);
+        let file_name = if is_named {
+            let temp = frame.temp(Kind::Word);
+            let bytes = variable.name.to_lowercase().into_bytes();
+            out.push(Instruction::Data(
+                temp,
+                bytes.into_iter().chain([0]).collect(),
+            ));
+            Operand::Temp(temp)
+        } else {
+            Operand::Integer(0)
+        };
+        let address = |offset| Address::Slot {
+            hops: 0,
+            slot: *slot,
+            offset,
+        };
+        out.store(Kind::Word, address(0), handle);
+        out.store(Kind::Word, address(8), file_name);
+    }
+
+    if let Statement::StatementList(ref statements) = &code.body {
+        for statement in statements {
+            let mark = frame.mark();
+            out.append(process_statement(
+                statement,
+                src,
+                label_idx,
+                diagnostics,
+                &mut frame,
+                &scope,
+            ));
+            frame.release(mark);
+        }
+    } else {
+        panic!("Block type must have a StatementList as the body.");
+    }
+
+    let result = match heading {
+        Some(procedure) if procedure.return_type.is_some() => {
+            let (slot, tipe, _) = &scope.variables[&procedure.name];
+            let address = Address::Slot {
+                hops: 0,
+                slot: *slot,
+                offset: 0,
+            };
+            match get_base_type(tipe) {
+                Type::Char
+                | Type::Boolean
+                | Type::Real
+                | Type::Integer
+                | Type::Enumeration(_)
+                | Type::Pointer(_) => {
+                    let kind = get_kind(tipe);
+                    let value = out.load(&mut frame, kind, address);
+                    out.terminate(Terminator::Return(Some(value)));
+                    Some(kind)
+                }
+                // already stored by the assignments to it
+                Type::Stryng => None,
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1031",
+                        procedure.start,
+                        procedure.end,
+                        "Unsupported function return type",
+                    ));
+                    None
+                }
+            }
+        }
+        Some(_) => None,
+        None => {
+            out.terminate(Terminator::Return(Some(Operand::Integer(0))));
+            Some(Kind::Word)
+        }
+    };
+
+    let index = routines.len();
+    let blocks = get_blocks(out, label_idx);
+    routines.push(ir::Routine {
+        name: label.to_string(),
+        parent: parent_routine,
+        parameters: parameter_temps,
+        temps: frame.temps,
+        slots: frame.slots,
+        blocks,
+        result,
+    });
+
+    for (procedure, signature) in code.procedures.iter().zip(signatures) {
+        process_block(
+            &procedure.body,
+            Some(procedure),
+            &[],
+            &signature.label,
+            Some(index),
+            src,
+            label_idx,
+            diagnostics,
+            routines,
+            &scope,
+        );
+    }
+}
+
+// returns size of type
+fn get_size(tipe: &Type) -> usize {
+    match tipe {
+        Type::Integer => 8,
+        Type::Boolean => 1,
+        Type::Real => 8,
+        Type::Char => 1,
+        Type::Stryng => MAX_STRING_LENGTH + 1,
+        Type::Array(sub_tipe, _, start_idx, end_idx) => {
+            ((end_idx - start_idx + 1) as usize) * get_size(sub_tipe)
+        }
+        Type::Enumeration(_) => 8,
+        Type::Pointer(_) => 8,
+        Type::Subrange(host, _, _) => get_size(host),
+        Type::Record(_, size) => *size,
+        Type::Set(_) => 32,
+        // the runtime's file, then the name given by ASSIGN
+        Type::Text => 16,
+        Type::File(element_tipe) => 24 + get_size(element_tipe).next_multiple_of(8),
+        Type::Undefined => 0,
+    }
+}
+
+// returns the type a pointer points to, which is looked up by name when it's dereferenced so
+// types can point to themselves
+fn get_pointer_target(name: &str, scope: &Scope) -> Option<Type> {
+    match name {
+        "INTEGER" => Some(Type::Integer),
+        "BOOLEAN" => Some(Type::Boolean),
+        "REAL" => Some(Type::Real),
+        "CHAR" => Some(Type::Char),
+        "STRING" => Some(Type::Stryng),
+        _ => scope.get_type(name).cloned(),
+    }
+}
+
+// returns the alignment of a type, which its offset in a record or slot is a multiple of
+fn get_alignment(tipe: &Type) -> usize {
+    match tipe {
+        Type::Boolean | Type::Char | Type::Undefined => 1,
+        Type::Array(sub_tipe, _, _, _) | Type::Subrange(sub_tipe, _, _) => get_alignment(sub_tipe),
+        Type::Record(fields, _) => fields
+            .iter()
+            .map(|(_, tipe, _)| get_alignment(tipe))
+            .max()
+            .unwrap_or(1),
+        _ => 8,
+    }
+}
+
+// records, arrays, sets, strings and files are stored through their address, rather than loaded
+// into a temporary
+fn is_copied_by_address(tipe: &Type) -> bool {
+    matches!(
+        tipe,
+        Type::Array(_, _, _, _)
+            | Type::Record(_, _)
+            | Type::Set(_)
+            | Type::Stryng
+            | Type::Text
+            | Type::File(_)
+    )
+}
+
+// returns true iff the type is a text file or a typed file
+fn is_file(tipe: &Type) -> bool {
+    matches!(tipe, Type::Text | Type::File(_))
+}
+
+// returns true iff the type is a file, or an array or record with a file in it
+fn contains_file(tipe: &Type) -> bool {
+    match tipe {
+        Type::Array(sub_tipe, _, _, _) => contains_file(sub_tipe),
+        Type::Record(fields, _) => fields.iter().any(|(_, tipe, _)| contains_file(tipe)),
+        _ => is_file(tipe),
+    }
+}
+
+// returns the words of a constant set, lowest elements first
+fn parse_set(value: &str) -> [u64; 4] {
+    let mut words = [0; 4];
+    for (word, n) in words.iter_mut().zip(value.split(' ')) {
+        *word = n.parse().unwrap_or(0);
+    }
+    words
+}
+
+// returns a constant set made of the given words, lowest elements first
+fn format_set(words: [u64; 4]) -> String {
+    words.map(|word| word.to_string()).join(" ")
+}
+
+// longest string that fits after the length byte
+const MAX_STRING_LENGTH: usize = 255;
+
+// what local variables hold until they're assigned, with uninitialized checks, as a byte repeated
+const POISON: u64 = 0xcccc_cccc_cccc_cccc;
+
+// returns the text of a string or char constant
+fn get_string_constant(value: &str, tipe: &Type) -> String {
+    match tipe {
+        Type::Char => char::from(value.parse::<i64>().unwrap_or(0) as u8).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// returns a string folded from constants, reporting it if it's too long for a string
+fn check_string_constant(
+    text: String,
+    start: usize,
+    end: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    if text.len() > MAX_STRING_LENGTH {
+        diagnostics.push(Diagnostic::error(
+            "P1070",
+            start,
+            end,
+            &format!("String longer than {} characters", MAX_STRING_LENGTH),
+        ));
+    }
+    text
+}
+
+// constant expressions in types are folded, so any code they'd need is thrown away
+fn convert_supertype_to_type(
+    super_type: &SuperType,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) -> Type {
+    match super_type {
+        SuperType::Integer => Type::Integer,
+        SuperType::Boolean => Type::Boolean,
+        SuperType::Real => Type::Real,
+        SuperType::Char => Type::Char,
+        SuperType::Stryng => Type::Stryng,
+        SuperType::Text => Type::Text,
+        SuperType::Array(element_type, index_type, start, end) => {
+            let index_tipe =
+                convert_supertype_to_type(index_type, src, label_idx, diagnostics, scope);
+            let (index_tipe, start_index, end_index) = match index_tipe {
+                Type::Subrange(host, first, last) => (*host, first, last),
+                Type::Char => (Type::Char, 0, 255),
+                Type::Boolean => (Type::Boolean, 0, 1),
+                Type::Enumeration(identifiers) => {
+                    let last = identifiers.len() as i64 - 1;
+                    (Type::Enumeration(identifiers), 0, last)
+                }
+                Type::Undefined => (Type::Undefined, 0, -1),
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1032",
+                        *start,
+                        *end,
+                        "Arrays must be indexed by a subrange, char, boolean or enumeration",
+                    ));
+                    (Type::Undefined, 0, -1)
+                }
+            };
+            let converted_element_type =
+                convert_supertype_to_type(element_type, src, label_idx, diagnostics, scope);
+            Type::Array(
+                Box::new(converted_element_type),
+                Box::new(index_tipe),
+                start_index as isize,
+                end_index as isize,
+            )
+        }
+        SuperType::Enumeration(identifiers) => Type::Enumeration(identifiers.clone()),
+        SuperType::Subrange(start_expr, end_expr) => {
+            match evaluate_bounds(start_expr, end_expr, src, label_idx, diagnostics, scope) {
+                Some((host, first, last)) => Type::Subrange(Box::new(host), first, last),
+                None => Type::Undefined,
+            }
+        }
+        SuperType::Record(field_list) => {
+            let mut fields = Vec::new();
+            let end = layout_fields(
+                field_list,
+                0,
+                &mut fields,
+                src,
+                label_idx,
+                diagnostics,
+                scope,
+            );
+            let alignment = get_alignment(&Type::Record(fields.clone(), 0));
+            Type::Record(fields, end.next_multiple_of(alignment))
+        }
+        SuperType::Pointer(name) => Type::Pointer(name.clone()),
+        SuperType::Set(element_type, start, end) => {
+            let tipe = convert_supertype_to_type(element_type, src, label_idx, diagnostics, scope);
+            // every element needs its own bit
+            let fits = match &tipe {
+                Type::Char | Type::Boolean | Type::Undefined => true,
+                Type::Enumeration(identifiers) => identifiers.len() <= 256,
+                Type::Subrange(_, first, last) => *first >= 0 && *last <= 255,
+                _ => false,
+            };
+            if fits {
+                Type::Set(Box::new(get_base_type(&tipe)))
+            } else {
+                diagnostics.push(Diagnostic::error(
+                    "P1033",
+                    *start,
+                    *end,
+                    "Set elements must be ordinals numbered 0 to 255",
+                ));
+                Type::Undefined
+            }
+        }
+        SuperType::File(element_type, start, end) => {
+            let tipe = convert_supertype_to_type(element_type, src, label_idx, diagnostics, scope);
+            if contains_file(&tipe) {
+                diagnostics.push(Diagnostic::error(
+                    "P1034",
+                    *start,
+                    *end,
+                    "Files can't contain files",
+                ));
+                Type::Undefined
+            } else {
+                Type::File(Box::new(tipe))
+            }
+        }
+        SuperType::Named(name, start, end) => match scope.get_type(name) {
+            Some(tipe) => tipe.clone(),
+            None => {
+                diagnostics.push(
+                    Diagnostic::error("P1035", *start, *end, "Unrecognized type")
+                        .with_suggestion(name, &scope.get_type_names()),
+                );
+                Type::Undefined
+            }
+        },
+    }
+}
+
+// adds the fields of a record to `fields`, starting from `offset`, and returns the offset after
+// the last one. Every variant starts after the tag field, so the largest variant sets the size.
+fn layout_fields(
+    field_list: &FieldList,
+    mut offset: usize,
+    fields: &mut Vec<(String, Type, usize)>,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) -> usize {
+    let mut add_field = |name: &str, tipe: Type, offset: &mut usize| {
+        *offset = offset.next_multiple_of(get_alignment(&tipe));
+        fields.push((name.to_string(), tipe.clone(), *offset));
+        *offset += get_size(&tipe);
+    };
+    for field in &field_list.fields {
+        let tipe = convert_supertype_to_type(&field.tipe, src, label_idx, diagnostics, scope);
+        add_field(&field.name, tipe, &mut offset);
+    }
+    let Some(variant) = &field_list.variant else {
+        return offset;
+    };
+
+    let tag_type = convert_supertype_to_type(&variant.tag_type, src, label_idx, diagnostics, scope);
+    if let Some(tag) = &variant.tag {
+        add_field(tag, tag_type.clone(), &mut offset);
+    }
+    let mut end = offset;
+    for (labels, variant_fields) in &variant.variants {
+        for label in labels {
+            let (value, tipe) = evaluate_expression(
+                label,
+                src,
+                label_idx,
+                diagnostics,
+                &mut Frame::default(),
+                scope,
+            );
+            if tipe != Type::Undefined && (!value.is_constant() || tipe != get_base_type(&tag_type))
+            {
+                diagnostics.push(Diagnostic::error(
+                    "P1036",
+                    label.start,
+                    label.end,
+                    "Variant labels must be constants of the tag type",
+                ));
+            }
+        }
+        end = end.max(layout_fields(
+            variant_fields,
+            offset,
+            fields,
+            src,
+            label_idx,
+            diagnostics,
+            scope,
+        ));
+    }
+    end
+}
+
+// returns the host type and ordinal numbers of the bounds of a subrange or array
+fn evaluate_bounds(
+    start_expr: &Expression,
+    end_expr: &Expression,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) -> Option<(Type, i64, i64)> {
+    let mut frame = Frame::default();
+    let (start_value, start_tipe) =
+        evaluate_expression(start_expr, src, label_idx, diagnostics, &mut frame, scope);
+    let (end_value, end_tipe) =
+        evaluate_expression(end_expr, src, label_idx, diagnostics, &mut frame, scope);
+    if start_tipe == Type::Undefined || end_tipe == Type::Undefined {
+        return None;
+    }
+    let (Value::Constant(start_value), Value::Constant(end_value)) = (start_value, end_value)
+    else {
+        diagnostics.push(Diagnostic::error(
+            "P1037",
+            start_expr.start,
+            end_expr.end,
+            "Bounds must be ordinal constants",
+        ));
+        return None;
+    };
+    if !is_ordinal(&start_tipe) {
+        diagnostics.push(Diagnostic::error(
+            "P1037",
+            start_expr.start,
+            end_expr.end,
+            "Bounds must be ordinal constants",
+        ));
+        return None;
+    }
+    if start_tipe != end_tipe {
+        diagnostics.push(Diagnostic::error(
+            "P1038",
+            start_expr.start,
+            end_expr.end,
+            "Bounds must have the same type",
+        ));
+        return None;
+    }
+    let first = get_ordinal_value(&start_value, &start_tipe)?;
+    let last = get_ordinal_value(&end_value, &end_tipe)?;
+    if first > last {
+        diagnostics.push(Diagnostic::error(
+            "P1039",
+            start_expr.start,
+            end_expr.end,
+            "Lower bound exceeds upper bound",
+        ));
+        return None;
+    }
+    Some((start_tipe, first, last))
+}
+
+// returns map of symbol names to their slots/types/whether the slot holds their address. VAR
+// parameters hold the address of the variable passed in, rather than the value.
+fn get_variable_map(
+    parameters: &[Parameter],
+    variables: &[Variable],
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> HashMap<String, (Slot, Type, bool)> {
+    let mut result = HashMap::new();
+    let frame_variables = parameters
+        .iter()
+        .map(|parameter| (&parameter.name, &parameter.tipe, parameter.by_reference))
+        .chain(
+            variables
+                .iter()
+                .map(|variable| (&variable.name, &variable.tipe, false)),
+        );
+    for (name, super_type, is_reference) in frame_variables {
+        let tipe = convert_supertype_to_type(super_type, src, label_idx, diagnostics, scope);
+        let slot = if is_reference {
+            frame.slot(8, 8)
+        } else {
+            frame.slot(get_size(&tipe), get_alignment(&tipe))
+        };
+        result.insert(name.clone(), (slot, tipe, is_reference));
+    }
+    result
+}
+
+// returns code for the address of a variable or array element, along with its type
+fn evaluate_address(
+    factor: &Factor,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Option<(Code, Address, Type)> {
+    match factor {
+        Factor::Identifier(name, arguments, _, _) if arguments.is_empty() => {
+            let (location, tipe) = scope.get_variable(name)?;
+            let mut code = Code::default();
+            let address = get_location_address(location, &mut code, frame);
+            Some((code, address, tipe))
+        }
+        Factor::ArrayIndex(name, index, start, end) => {
+            let (location, array_tipe) = scope.get_variable(name)?;
+            let Type::Array(sub_tipe, index_tipe, start_idx, end_idx) = array_tipe else {
+                return None;
+            };
+            let (mut code, index) = evaluate_index(
+                index,
+                &index_tipe,
+                start_idx,
+                end_idx,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+            let size = get_size(&sub_tipe);
+            let address = get_location_address(location, &mut code, frame);
+            let address = index_address(address, index, size, &mut code, frame);
+            Some((
+                code,
+                add_offset(address, -start_idx * size as isize),
+                *sub_tipe,
+            ))
+        }
+        Factor::Selection(name, selectors, start, end) => {
+            let (code, address, tipe) = evaluate_selection(
+                name,
+                selectors,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+            if tipe == Type::Undefined {
+                None
+            } else {
+                Some((code, address, tipe))
+            }
+        }
+        _ => None,
+    }
+}
+
+// returns code for the address of the component selected from a variable, along with its type.
+// The type is Undefined if something couldn't be selected, which is reported here.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_selection(
+    name: &str,
+    selectors: &[Selector],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Code, Address, Type) {
+    let mut code = Code::default();
+    let undefined = |code| {
+        (
+            code,
+            Address::Pointer(Operand::Integer(0), 0),
+            Type::Undefined,
+        )
+    };
+    let Some((location, mut tipe)) = scope.get_variable(name) else {
+        diagnostics.push(get_assignment_error(name, start, end, scope));
+        return undefined(code);
+    };
+    let mut address = get_location_address(location, &mut code, frame);
+    for selector in selectors {
+        match (selector, tipe) {
+            (Selector::Field(field, field_start, field_end), Type::Record(fields, _)) => {
+                let Some((_, field_tipe, offset)) =
+                    fields.iter().find(|(name, _, _)| name == field)
+                else {
+                    let field_names: Vec<String> =
+                        fields.iter().map(|(name, _, _)| name.clone()).collect();
+                    diagnostics.push(
+                        Diagnostic::error("P1040", *field_start, *field_end, "Unrecognized field")
+                            .with_suggestion(field, &field_names),
+                    );
+                    return undefined(code);
+                };
+                address = add_offset(address, *offset as isize);
+                tipe = field_tipe.clone();
+            }
+            (Selector::Field(_, _, field_end), _) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1041",
+                    start,
+                    *field_end,
+                    "Not a record type",
+                ));
+                return undefined(code);
+            }
+            (Selector::Index(index), Type::Array(sub_tipe, index_tipe, start_idx, end_idx)) => {
+                let size = get_size(&sub_tipe);
+                let (index_code, index_value) = evaluate_index(
+                    index,
+                    &index_tipe,
+                    start_idx,
+                    end_idx,
+                    index.start,
+                    index.end,
+                    src,
+                    label_idx,
+                    diagnostics,
+                    frame,
+                    scope,
+                );
+                code.append(index_code);
+                address = index_address(address, index_value, size, &mut code, frame);
+                address = add_offset(address, -start_idx * size as isize);
+                tipe = *sub_tipe;
+            }
+            (Selector::Index(index), Type::Stryng) => {
+                let (index_code, index_value, index_tipe) =
+                    evaluate_final_expression(index, src, label_idx, diagnostics, frame, scope);
+                if index_tipe != Type::Integer && index_tipe != Type::Undefined {
+                    diagnostics.push(Diagnostic::error(
+                        "P1042",
+                        index.start,
+                        index.end,
+                        "Strings must be indexed with integer type",
+                    ));
+                }
+                // characters are indexed from 1, after the length
+                code.append(index_code);
+                let string = code.address(frame, address);
+                if scope.options.range_checks {
+                    check_string_index(
+                        index_value,
+                        string,
+                        index.start,
+                        src,
+                        label_idx,
+                        &mut code,
+                        frame,
+                    );
+                }
+                address = index_address(
+                    Address::Pointer(string, 0),
+                    index_value,
+                    1,
+                    &mut code,
+                    frame,
+                );
+                tipe = Type::Char;
+            }
+            (Selector::Index(index), _) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1012",
+                    start,
+                    index.end,
+                    "Not an array or string type",
+                ));
+                return undefined(code);
+            }
+            (Selector::Dereference(_, caret_end), Type::Pointer(target)) => {
+                let Some(target_tipe) = get_pointer_target(&target, scope) else {
+                    diagnostics.push(
+                        Diagnostic::error("P1035", start, *caret_end, "Unrecognized type")
+                            .with_suggestion(&target, &scope.get_type_names()),
+                    );
+                    return undefined(code);
+                };
+                check_initialized(
+                    &Type::Pointer(target),
+                    address,
+                    start,
+                    src,
+                    label_idx,
+                    &mut code,
+                    frame,
+                    scope,
+                );
+                let pointer = code.load(frame, Kind::Word, address);
+                address = Address::Pointer(pointer, 0);
+                tipe = target_tipe;
+            }
+            // the buffer of a typed file
+            (Selector::Dereference(_, _), Type::File(element_tipe)) => {
+                address = add_offset(address, 24);
+                tipe = *element_tipe;
+            }
+            (Selector::Dereference(_, caret_end), _) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1043",
+                    start,
+                    *caret_end,
+                    "Not a pointer or file type",
+                ));
+                return undefined(code);
+            }
+        }
+    }
+    (code, address, tipe)
+}
+
+// returns code to copy the variable `expression` of type `tipe` to `address`. Sets and strings
+// can be any expression of their type, and files can't be copied.
+#[allow(clippy::too_many_arguments)]
+fn copy_variable(
+    tipe: &Type,
+    address: Address,
+    expression: &Expression,
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut code = Code::default();
+    let target = code.address(frame, address);
+    if let Type::Set(_) = tipe {
+        let (value_code, set, value_tipe) =
+            evaluate_final_expression(expression, src, label_idx, diagnostics, frame, scope);
+        code.append(value_code);
+        if !is_assignable(tipe, &value_tipe) {
+            diagnostics.push(Diagnostic::error("P1044", start, end, "Mismatched types"));
+            return code;
+        }
+        code.copy(target, set, Operand::Integer(32));
+        return code;
+    } else if *tipe == Type::Stryng {
+        // only the length and the characters in use are copied
+        let (value, value_tipe) =
+            evaluate_expression(expression, src, label_idx, diagnostics, frame, scope);
+        if !is_assignable(&Type::Stryng, &value_tipe) {
+            diagnostics.push(Diagnostic::error("P1044", start, end, "Mismatched types"));
+            return code;
+        }
+        let (value_code, string) = as_string(value, &value_tipe, frame);
+        code.append(value_code);
+        let length = get_string_length(string, &mut code, frame);
+        let size = code.binary(
+            frame,
+            Kind::Word,
+            BinaryOperator::Add,
+            length,
+            Operand::Integer(1),
+        );
+        code.copy(target, string, size);
+        return code;
+    }
+    let address = get_expression_variable(expression)
+        .and_then(|factor| evaluate_address(factor, src, label_idx, diagnostics, frame, scope));
+    match address {
+        Some((address_code, address, source_tipe))
+            if source_tipe == *tipe && !contains_file(tipe) =>
+        {
+            code.append(address_code);
+            let source = code.address(frame, address);
+            code.copy(target, source, Operand::Integer(get_size(tipe) as i64));
+        }
+        _ => {
+            diagnostics.push(Diagnostic::error("P1044", start, end, "Mismatched types"));
+        }
+    }
+    code
+}
+
+// returns code to assign to a record, array, set or string variable
+#[allow(clippy::too_many_arguments)]
+fn assign_by_copy(
+    target: &Factor,
+    expression: &Expression,
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    match evaluate_address(target, src, label_idx, diagnostics, frame, scope) {
+        Some((mut code, address, tipe)) => {
+            code.append(copy_variable(
+                &tipe,
+                address,
+                expression,
+                start,
+                end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+            code
+        }
+        None => Code::default(),
+    }
+}
+
+// returns whether the expression is nothing more than an array or record variable, which can only
+// be assigned to a variable of its own type
+fn is_aggregate_variable(
+    expression: &Expression,
+    src: &str,
+    label_idx: &mut u32,
+    scope: &Scope,
+) -> bool {
+    // errors in it are reported when it's evaluated
+    get_expression_variable(expression)
+        .and_then(|factor| {
+            let frame = &mut Frame::default();
+            evaluate_address(factor, src, label_idx, &mut Vec::new(), frame, scope)
+        })
+        .is_some_and(|(_, _, tipe)| matches!(tipe, Type::Array(_, _, _, _) | Type::Record(_, _)))
+}
+
+// adds constants to the scope in order, so each can be defined using the ones before it
+fn add_constants(
+    constants: &Vec<Constant>,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &mut Scope,
+) {
+    for constant in constants {
+        let (value, tipe) = evaluate_expression(
+            &constant.value,
+            src,
+            label_idx,
+            diagnostics,
+            &mut Frame::default(),
+            scope,
+        );
+        // an error has been reported for anything that isn't constant
+        let value = match value {
+            Value::Constant(value) => value,
+            Value::Computed(_, _) => String::new(),
+        };
+        scope.constants.insert(constant.name.clone(), (value, tipe));
+        scope
+            .declarations
+            .insert(constant.name.clone(), (constant.start, constant.end));
+    }
+}
+
+// adds type definitions to the scope in order, so each can be defined using the ones before it
+fn add_types(
+    types: &Vec<TypeDefinition>,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &mut Scope,
+) {
+    for definition in types {
+        let tipe = convert_supertype_to_type(&definition.tipe, src, label_idx, diagnostics, scope);
+        add_enumeration_constants(&definition.tipe, &tipe, scope);
+        scope.types.insert(definition.name.clone(), tipe);
+    }
+}
+
+// adds the identifiers of enumerations written out in `super_type` to the scope as constants
+fn add_enumeration_constants(super_type: &SuperType, tipe: &Type, scope: &mut Scope) {
+    match (super_type, tipe) {
+        (SuperType::Enumeration(identifiers), Type::Enumeration(_)) => {
+            for (n, identifier) in identifiers.iter().enumerate() {
+                scope
+                    .constants
+                    .insert(identifier.clone(), (n.to_string(), tipe.clone()));
+            }
+        }
+        (
+            SuperType::Array(super_sub_tipe, super_index_tipe, _, _),
+            Type::Array(sub_tipe, index_tipe, _, _),
+        ) => {
+            add_enumeration_constants(super_index_tipe, index_tipe, scope);
+            add_enumeration_constants(super_sub_tipe, sub_tipe, scope)
+        }
+        (SuperType::Set(super_sub_tipe, _, _), Type::Set(sub_tipe))
+        | (SuperType::File(super_sub_tipe, _, _), Type::File(sub_tipe)) => {
+            add_enumeration_constants(super_sub_tipe, sub_tipe, scope)
+        }
+        _ => {}
+    }
+}
+
+// returns code for an index of an array indexed by `index_tipe`, as a word
+#[allow(clippy::too_many_arguments)]
+fn evaluate_index(
+    index: &Expression,
+    index_tipe: &Type,
+    first: isize,
+    last: isize,
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Code, Operand) {
+    let (value, tipe) = evaluate_expression(index, src, label_idx, diagnostics, frame, scope);
+    if !is_ordinal(&tipe) && tipe != Type::Undefined {
+        diagnostics.push(Diagnostic::error(
+            "P1045",
+            start,
+            end,
+            "Arrays must be indexed with an ordinal type",
+        ));
+    } else if tipe != *index_tipe && tipe != Type::Undefined && *index_tipe != Type::Undefined {
+        diagnostics.push(Diagnostic::error(
+            "P1046",
+            start,
+            end,
+            "Mismatched index type",
+        ));
+    }
+    if let Value::Constant(value) = &value {
+        if get_ordinal_value(value, &tipe).is_some_and(|n| n < first as i64 || n > last as i64) {
+            diagnostics.push(Diagnostic::error(
+                "P1047",
+                index.start,
+                index.end,
+                "Index out of range",
+            ));
+        }
+        let n = get_ordinal_value(value, &tipe).unwrap_or(0);
+        return (Code::default(), Operand::Integer(n));
+    }
+    let (mut code, operand) = evaluate_constant(value, &tipe, frame);
+    let mut operand = extend(operand, &mut code, frame);
+    if scope.options.range_checks {
+        operand = check_range(
+            operand,
+            first as i64,
+            last as i64,
+            "Index out of range",
+            index.start,
+            src,
+            label_idx,
+            &mut code,
+            frame,
+        );
+    }
+    (code, operand)
+}
+
+// constants available in every program
+fn get_predefined_constant_map() -> HashMap<String, (String, Type)> {
+    let mut result = HashMap::new();
+    result.insert("TRUE".to_string(), ("true".to_string(), Type::Boolean));
+    result.insert("FALSE".to_string(), ("false".to_string(), Type::Boolean));
+    result.insert(
+        "MAXINT".to_string(),
+        (format!("{}", 2_u64.pow(63) - 1), Type::Integer),
+    );
+    result
+}
+
+// returns code for a statement
+fn process_statement(
+    statement: &Statement,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut out = Code::default();
+    if let Statement::Assignment(name, expression, start, end) = statement {
+        if let Some((_, tipe)) = scope.get_variable(name) {
+            if is_copied_by_address(&tipe) {
+                let target = Factor::Identifier(name.clone(), Vec::new(), *start, *end);
+                return assign_by_copy(
+                    &target,
+                    expression,
+                    *start,
+                    *end,
+                    src,
+                    label_idx,
+                    diagnostics,
+                    frame,
+                    scope,
+                );
+            }
+        }
+        if scope.get_variable(name).is_some()
+            && is_aggregate_variable(expression, src, label_idx, scope)
+        {
+            diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+            return out;
+        }
+        let (value_code, value, tipe1) =
+            evaluate_final_expression(expression, src, label_idx, diagnostics, frame, scope);
+        out.append(value_code);
+        let Some((location, tipe2)) = scope.get_variable(name) else {
+            diagnostics.push(get_assignment_error(name, *start, *end, scope));
+            return out;
+        };
+        check_assignment(
+            value,
+            &tipe2,
+            expression.start,
+            src,
+            label_idx,
+            &mut out,
+            frame,
+            scope,
+        );
+        if !is_assignable(&get_base_type(&tipe2), &tipe1) {
+            diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+        }
+        // the mismatch, or the error in the value, has already been reported if nothing is stored
+        let address = get_location_address(location, &mut out, frame);
+        store_value(&tipe2, &tipe1, address, value, &mut out, frame);
+    } else if let Statement::ElementAssignment(name, index, expression, start, end) = statement {
+        if let Some((_, Type::Array(sub_tipe, _, _, _))) = scope.get_variable(name) {
+            if is_copied_by_address(&sub_tipe) {
+                let target = Factor::ArrayIndex(name.clone(), index.clone(), *start, *end);
+                return assign_by_copy(
+                    &target,
+                    expression,
+                    *start,
+                    *end,
+                    src,
+                    label_idx,
+                    diagnostics,
+                    frame,
+                    scope,
+                );
+            }
+        }
+        let Some((location, arr_tipe)) = scope.get_variable(name) else {
+            diagnostics.push(get_assignment_error(name, *start, *end, scope));
+            return out;
+        };
+        if let Type::Array(sub_tipe, index_tipe, start_idx, end_idx) = &arr_tipe {
+            let (index_code, index_value) = evaluate_index(
+                index,
+                index_tipe,
+                *start_idx,
+                *end_idx,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+            out.append(index_code);
+            if is_aggregate_variable(expression, src, label_idx, scope) {
+                diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+                return out;
+            }
+            let (value_code, value, tipe) =
+                evaluate_final_expression(expression, src, label_idx, diagnostics, frame, scope);
+            out.append(value_code);
+            check_assignment(
+                value,
+                sub_tipe,
+                expression.start,
+                src,
+                label_idx,
+                &mut out,
+                frame,
+                scope,
+            );
+            if !is_assignable(&get_base_type(sub_tipe), &tipe) {
+                diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+            }
+            let size = get_size(sub_tipe);
+            let address = get_location_address(location, &mut out, frame);
+            let address = index_address(address, index_value, size, &mut out, frame);
+            let address = add_offset(address, -start_idx * size as isize);
+            store_value(sub_tipe, &tipe, address, value, &mut out, frame);
+        } else if arr_tipe == Type::Stryng {
+            // characters are indexed from 1, after the length
+            let (index_code, index_value, index_tipe) =
+                evaluate_final_expression(index, src, label_idx, diagnostics, frame, scope);
+            if index_tipe != Type::Integer && index_tipe != Type::Undefined {
+                diagnostics.push(Diagnostic::error(
+                    "P1042",
+                    index.start,
+                    index.end,
+                    "Strings must be indexed with integer type",
+                ));
+            }
+            out.append(index_code);
+            if is_aggregate_variable(expression, src, label_idx, scope) {
+                diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+                return out;
+            }
+            let (value_code, value, tipe) =
+                evaluate_final_expression(expression, src, label_idx, diagnostics, frame, scope);
+            if !is_assignable(&Type::Char, &tipe) {
+                diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+            }
+            out.append(value_code);
+            let address = get_location_address(location, &mut out, frame);
+            let string = out.address(frame, address);
+            if scope.options.range_checks {
+                check_string_index(
+                    index_value,
+                    string,
+                    index.start,
+                    src,
+                    label_idx,
+                    &mut out,
+                    frame,
+                );
+            }
+            let address =
+                index_address(Address::Pointer(string, 0), index_value, 1, &mut out, frame);
+            out.store(Kind::Byte, address, value);
+        } else {
+            diagnostics.push(Diagnostic::error(
+                "P1048",
+                *start,
+                *end,
+                "Identifier does not belong to an array",
+            ));
+        }
+    } else if let Statement::SelectionAssignment(name, selectors, expression, start, end) =
+        statement
+    {
+        let (address_code, address, tipe) = evaluate_selection(
+            name,
+            selectors,
+            *start,
+            *end,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        );
+        if tipe == Type::Undefined {
+            return out;
+        }
+        out.append(address_code);
+        if is_copied_by_address(&tipe) {
+            out.append(copy_variable(
+                &tipe,
+                address,
+                expression,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+            return out;
+        }
+        if is_aggregate_variable(expression, src, label_idx, scope) {
+            diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+            return out;
+        }
+        let (value_code, value, value_tipe) =
+            evaluate_final_expression(expression, src, label_idx, diagnostics, frame, scope);
+        out.append(value_code);
+        check_assignment(
+            value,
+            &tipe,
+            expression.start,
+            src,
+            label_idx,
+            &mut out,
+            frame,
+            scope,
+        );
+        let tipe = get_base_type(&tipe);
+        if !is_assignable(&tipe, &value_tipe) {
+            diagnostics.push(Diagnostic::error("P1044", *start, *end, "Mismatched types"));
+        }
+        match tipe {
+            Type::Char
+            | Type::Boolean
+            | Type::Integer
+            | Type::Enumeration(_)
+            | Type::Pointer(_)
+            | Type::Real => store_value(&tipe, &value_tipe, address, value, &mut out, frame),
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    "P1049",
+                    *start,
+                    *end,
+                    "Unsupported type used in assignment",
+                ));
+            }
+        }
+    } else if let Statement::WithStatement(records, body) = statement {
+        out.append(process_with(
+            records,
+            body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+    } else if let Statement::WriteCall(name, arguments, start, end) = statement {
+        if name == "STR" {
+            // a STR the program declares itself takes ordinary arguments
+            if scope.get_procedure(name).is_some() {
+                let arguments = arguments
+                    .iter()
+                    .map(|(value, _, _)| value.clone())
+                    .collect();
+                return process_statement(
+                    &Statement::ProcedureCall(name.clone(), arguments, *start, *end),
+                    src,
+                    label_idx,
+                    diagnostics,
+                    frame,
+                    scope,
+                );
+            }
+            return process_str(
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+        }
+        out.append(process_write(
+            name == "WRITELN",
+            arguments,
+            *start,
+            *end,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+    } else if let Statement::ProcedureCall(name, arguments, start, end) = statement {
+        if ["ASSIGN", "RESET", "REWRITE", "CLOSE", "GET", "PUT"].contains(&name.as_str()) {
+            out.append(process_file_procedure(
+                name,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+        } else if name == "NEW" || name == "DISPOSE" {
+            if arguments.len() != 1 {
+                diagnostics.push(Diagnostic::error(
+                    "P1003",
+                    *start,
+                    *end,
+                    "Expected 1 argument",
+                ));
+                return out;
+            }
+            let address = get_expression_variable(&arguments[0]).and_then(|factor| {
+                evaluate_address(factor, src, label_idx, diagnostics, frame, scope)
+            });
+            let Some((address_code, address, Type::Pointer(target))) = address else {
+                diagnostics.push(Diagnostic::error(
+                    "P1013",
+                    arguments[0].start,
+                    arguments[0].end,
+                    "Expected pointer variable",
+                ));
+                return out;
+            };
+            if name == "NEW" {
+                let Some(target_tipe) = get_pointer_target(&target, scope) else {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            "P1035",
+                            arguments[0].start,
+                            arguments[0].end,
+                            "Unrecognized type",
+                        )
+                        .with_suggestion(&target, &scope.get_type_names()),
+                    );
+                    return out;
+                };
+                // memory is zeroed, so new strings are empty and new pointers are NIL
+                let size = Operand::Integer(get_size(&target_tipe).max(1) as i64);
+                let pointer = out.runtime(frame, Some(Kind::Word), "rt_alloc", vec![size]);
+                out.append(address_code);
+                out.store(Kind::Word, address, pointer);
+            } else {
+                out.append(address_code);
+                let pointer = out.load(frame, Kind::Word, address);
+                out.runtime(frame, None, "rt_free", vec![pointer]);
+            }
+        } else if ["INSERT", "DELETE", "VAL"].contains(&name.as_str())
+            && scope.get_procedure(name).is_none()
+        {
+            out.append(process_string_procedure(
+                name,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+        } else if let Some(signature) = scope.get_procedure(name) {
+            let (call_code, _) = evaluate_call(
+                signature,
+                arguments,
+                *start,
+                *end,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            );
+            out.append(call_code);
+        } else {
+            diagnostics.push(
+                Diagnostic::error("P1050", *start, *end, "Unrecognized procedure")
+                    .with_suggestion(name, &scope.get_procedure_names()),
+            );
+        }
+    } else if let Statement::ReadCall(name, vars, start, end) = statement {
+        let (file, vars) = match vars.split_first() {
+            Some((file, rest)) if matches!(scope.get_variable(file), Some((_, tipe)) if is_file(&tipe)) => {
+                (Some(file), rest)
+            }
+            _ => (None, &vars[..]),
+        };
+
+        // reading x from a typed file is short for x := f^; GET(f)
+        let typed_file =
+            file.filter(|file| matches!(scope.get_variable(file), Some((_, Type::File(_)))));
+        if let Some(file) = typed_file {
+            if name == "READLN" {
+                diagnostics.push(Diagnostic::error(
+                    "P1051",
+                    *start,
+                    *end,
+                    "READLN can only be used with text files",
+                ));
+            }
+            let file_expression = make_factor_expression(
+                Factor::Identifier(file.clone(), Vec::new(), *start, *end),
+                *start,
+                *end,
+            );
+            for var in vars {
+                let buffer = Factor::Selection(
+                    file.clone(),
+                    vec![Selector::Dereference(*start, *end)],
+                    *start,
+                    *end,
+                );
+                for statement in [
+                    Statement::Assignment(
+                        var.clone(),
+                        make_factor_expression(buffer, *start, *end),
+                        *start,
+                        *end,
+                    ),
+                    Statement::ProcedureCall(
+                        "GET".to_string(),
+                        vec![file_expression.clone()],
+                        *start,
+                        *end,
+                    ),
+                ] {
+                    out.append(process_statement(
+                        &statement,
+                        src,
+                        label_idx,
+                        diagnostics,
+                        frame,
+                        scope,
+                    ));
+                }
+            }
+            return out;
+        }
+
+        // the runtime's file for the text file, which is kept while the variables are read
+        let file = match file.and_then(|file| scope.get_variable(file)) {
+            Some((location, _)) => {
+                let address = get_location_address(location, &mut out, frame);
+                out.load(frame, Kind::Word, address)
+            }
+            None => {
+                let temp = frame.temp(Kind::Word);
+                out.push(Instruction::Stream(temp, Stream::Input));
+                Operand::Temp(temp)
+            }
+        };
+        for var in vars {
+            let Some((location, tipe)) = scope.get_variable(var) else {
+                diagnostics.push(get_assignment_error(var, *start, *end, scope));
+                continue;
+            };
+            match get_base_type(&tipe) {
+                // the end of a line is read as a space, as is the end of a file without a newline
+                // before it
+                Type::Char => {
+                    let value = out.runtime(frame, Some(Kind::Byte), "rt_read_char", vec![file]);
+                    check_assignment(value, &tipe, *start, src, label_idx, &mut out, frame, scope);
+                    let address = get_location_address(location, &mut out, frame);
+                    out.store(Kind::Byte, address, value);
+                }
+                // the variable is left as it was at the end of the file, and anything else that
+                // isn't a number is an error
+                Type::Integer | Type::Real => {
+                    let address = get_location_address(location, &mut out, frame);
+                    let variable = out.address(frame, address);
+                    let function = if get_base_type(&tipe) == Type::Integer {
+                        "rt_read_int"
+                    } else {
+                        "rt_read_real"
+                    };
+                    let status =
+                        out.runtime(frame, Some(Kind::Byte), function, vec![file, variable]);
+                    for (failure, message) in [
+                        (1, "Invalid number in input"),
+                        (2, "Number out of range in input"),
+                    ] {
+                        let failed = out.compare(
+                            frame,
+                            Condition::Equal,
+                            Kind::Byte,
+                            status,
+                            Operand::Integer(failure),
+                        );
+                        check(failed, message, *start, src, label_idx, &mut out);
+                    }
+                    if matches!(tipe, Type::Subrange(_, _, _)) && scope.options.range_checks {
+                        let value = out.load(frame, Kind::Word, Address::Pointer(variable, 0));
+                        check_assignment(
+                            value, &tipe, *start, src, label_idx, &mut out, frame, scope,
+                        );
+                    }
+                }
+                // the rest of the line is read, and it's an error if it doesn't fit
+                Type::Stryng => {
+                    let address = get_location_address(location, &mut out, frame);
+                    let variable = out.address(frame, address);
+                    let arguments = vec![file, variable];
+                    let left = out.runtime(frame, Some(Kind::Byte), "rt_read_string", arguments);
+                    check(left, "String too long", *start, src, label_idx, &mut out);
+                }
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        "P1052",
+                        *start,
+                        *end,
+                        "Unsupported type in read call",
+                    ));
+                }
+            }
+        }
+        if name == "READLN" {
+            out.runtime(frame, None, "rt_skip_line", vec![file]);
+        }
+    } else if let Statement::StatementList(statements) = statement {
+        for statement in statements {
+            out.append(process_statement(
+                statement,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+        }
+    } else if let Statement::IfStatement(
+        condition,
+        true_body,
+        false_body,
+        condition_start,
+        condition_end,
+    ) = statement
+    {
+        let has_else = !matches!(**false_body, Statement::DoNothing);
+        let (value_code, value, tipe) =
+            evaluate_final_expression(condition, src, label_idx, diagnostics, frame, scope);
+        if tipe != Type::Boolean && tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1053",
+                *condition_start,
+                *condition_end,
+                "Condition must be a boolean type",
+            ));
+        }
+        out.append(value_code);
+        let then_label = new_label(label_idx);
+        let else_label = new_label(label_idx);
+        out.terminate(Terminator::Branch(value, then_label, else_label));
+        out.label(then_label);
+        out.append(process_statement(
+            true_body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+        if has_else {
+            let end_label = new_label(label_idx);
+            out.terminate(Terminator::Jump(end_label));
+            out.label(else_label);
+            out.append(process_statement(
+                false_body,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+            out.label(end_label);
+        } else {
+            out.label(else_label);
+        }
+    } else if let Statement::CaseStatement(
+        selector,
+        arms,
+        otherwise,
+        selector_start,
+        selector_end,
+    ) = statement
+    {
+        let (value_code, value, tipe) =
+            evaluate_final_expression(selector, src, label_idx, diagnostics, frame, scope);
+        if !is_ordinal(&tipe) && tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1054",
+                *selector_start,
+                *selector_end,
+                "CASE selector must be an ordinal type",
+            ));
+        }
+        out.append(value_code);
+        let value = extend(value, &mut out, frame);
+
+        // (first, last, arm) for each label, which are checked against each other
+        let mut ranges: Vec<(i64, i64, usize)> = Vec::new();
+        // where each of the ranges is in the code
+        let mut spans = Vec::new();
+        for (arm, (labels, _)) in arms.iter().enumerate() {
+            for label in labels {
+                let bounds = match label {
+                    ExpressionOrRange::Expression(expression) => vec![expression],
+                    ExpressionOrRange::Range(first, last) => vec![first, last],
+                };
+                let mut numbers = Vec::new();
+                for expression in &bounds {
+                    let (label_value, label_tipe) =
+                        evaluate_expression(expression, src, label_idx, diagnostics, frame, scope);
+                    let number = match &label_value {
+                        Value::Constant(label_value) => get_ordinal_value(label_value, &label_tipe),
+                        Value::Computed(_, _) => None,
+                    };
+                    match number {
+                        Some(n) if label_tipe == tipe => numbers.push(n),
+                        _ if label_tipe == Type::Undefined || tipe == Type::Undefined => {}
+                        _ => {
+                            diagnostics.push(Diagnostic::error(
+                                "P1055",
+                                expression.start,
+                                expression.end,
+                                "CASE labels must be constants of the selector's type",
+                            ));
+                        }
+                    }
+                }
+                if numbers.len() < bounds.len() {
+                    continue;
+                }
+                let (first, last) = (numbers[0], numbers[numbers.len() - 1]);
+                let (start, end) = (bounds[0].start, bounds[bounds.len() - 1].end);
+                if first > last {
+                    diagnostics.push(Diagnostic::error(
+                        "P1056",
+                        start,
+                        end,
+                        "Empty CASE label range",
+                    ));
+                } else if let Some(n) = ranges
+                    .iter()
+                    .position(|(a, b, _)| first <= *b && *a <= last)
+                {
+                    let (first_start, first_end) = spans[n];
+                    diagnostics.push(
+                        Diagnostic::error("P1057", start, end, "Duplicate CASE label").with_label(
+                            first_start,
+                            first_end,
+                            "First used here",
+                        ),
+                    );
+                } else {
+                    ranges.push((first, last, arm));
+                    spans.push((start, end));
+                }
+            }
+        }
+
+        let arm_labels: Vec<Label> = arms.iter().map(|_| new_label(label_idx)).collect();
+        let otherwise_label = new_label(label_idx);
+        let end_label = new_label(label_idx);
+
+        // dense labels jump through a table, others are compared in turn
+        let count: i128 = ranges
+            .iter()
+            .map(|(first, last, _)| *last as i128 - *first as i128 + 1)
+            .sum();
+        let minimum = ranges.iter().map(|(first, _, _)| *first).min().unwrap_or(0);
+        let maximum = ranges.iter().map(|(_, last, _)| *last).max().unwrap_or(0);
+        let span = maximum as i128 - minimum as i128 + 1;
+        if count >= 4 && span <= 1024 && span <= 2 * count {
+            let table = (minimum..=maximum)
+                .map(|n| {
+                    ranges
+                        .iter()
+                        .find(|(first, last, _)| (*first..=*last).contains(&n))
+                        .map_or(otherwise_label, |(_, _, arm)| arm_labels[*arm])
+                })
+                .collect();
+            out.terminate(Terminator::Switch(value, minimum, table, otherwise_label));
+        } else {
+            for (first, last, arm) in &ranges {
+                let next_label = new_label(label_idx);
+                if first == last {
+                    let matches = out.compare(
+                        frame,
+                        Condition::Equal,
+                        Kind::Word,
+                        value,
+                        Operand::Integer(*first),
+                    );
+                    out.terminate(Terminator::Branch(matches, arm_labels[*arm], next_label));
+                } else {
+                    let below = out.compare(
+                        frame,
+                        Condition::Less,
+                        Kind::Word,
+                        value,
+                        Operand::Integer(*first),
+                    );
+                    let in_range_label = new_label(label_idx);
+                    out.terminate(Terminator::Branch(below, next_label, in_range_label));
+                    out.label(in_range_label);
+                    let at_most = out.compare(
+                        frame,
+                        Condition::LessEqual,
+                        Kind::Word,
+                        value,
+                        Operand::Integer(*last),
+                    );
+                    out.terminate(Terminator::Branch(at_most, arm_labels[*arm], next_label));
+                }
+                out.label(next_label);
+            }
+            out.terminate(Terminator::Jump(otherwise_label));
+        }
+
+        for ((_, body), label) in arms.iter().zip(arm_labels) {
+            out.label(label);
+            out.append(process_statement(
+                body,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+            out.terminate(Terminator::Jump(end_label));
+        }
+        // without OTHERWISE, a value matching no label does nothing
+        out.label(otherwise_label);
+        if let Some(otherwise) = otherwise {
+            out.append(process_statement(
+                otherwise,
+                src,
+                label_idx,
+                diagnostics,
+                frame,
+                scope,
+            ));
+        }
+        out.label(end_label);
+    } else if let Statement::LabeledStatement(label, body, start, end) = statement {
+        match scope.get_label(*label) {
+            Some((0, ir_label, _)) => out.label(ir_label),
+            _ => {
+                diagnostics.push(Diagnostic::error("P1058", *start, *end, "Undeclared label"));
+            }
+        }
+        out.append(process_statement(
+            body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+    } else if let Statement::Goto(label, start, end) = statement {
+        match scope.get_label(*label) {
+            // leaving a routine unwinds the stack back to the frame the label is in
+            Some((hops, ir_label, true)) if hops > 0 => {
+                out.terminate(Terminator::Unwind(hops, ir_label))
+            }
+            Some((_, ir_label, true)) => out.terminate(Terminator::Jump(ir_label)),
+            Some((_, _, false)) => {
+                diagnostics.push(Diagnostic::error(
+                    "P1059",
+                    *start,
+                    *end,
+                    "Label doesn't prefix a statement",
+                ));
+            }
+            None => {
+                diagnostics.push(Diagnostic::error("P1058", *start, *end, "Undeclared label"));
+            }
+        }
+    } else if let Statement::WhileLoop(condition, body, condition_start, condition_end) = statement
+    {
+        let test_label = new_label(label_idx);
+        out.label(test_label);
+        let (value_code, value, tipe) =
+            evaluate_final_expression(condition, src, label_idx, diagnostics, frame, scope);
+        if tipe != Type::Boolean && tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1053",
+                *condition_start,
+                *condition_end,
+                "Condition must be a boolean type",
+            ));
+        }
+        out.append(value_code);
+        let body_label = new_label(label_idx);
+        let end_label = new_label(label_idx);
+        out.terminate(Terminator::Branch(value, body_label, end_label));
+        out.label(body_label);
+        out.append(process_statement(
+            body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+        out.terminate(Terminator::Jump(test_label));
+        out.label(end_label);
+    } else if let Statement::RepeatLoop(condition, body, condition_start, condition_end) = statement
+    {
+        let body_label = new_label(label_idx);
+        let (value_code, value, tipe) =
+            evaluate_final_expression(condition, src, label_idx, diagnostics, frame, scope);
+        if tipe != Type::Boolean && tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1053",
+                *condition_start,
+                *condition_end,
+                "Condition must be a boolean type",
+            ));
+        }
+        out.label(body_label);
+        out.append(process_statement(
+            body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+        out.append(value_code);
+        let end_label = new_label(label_idx);
+        out.terminate(Terminator::Branch(value, end_label, body_label));
+        out.label(end_label);
+    } else if let Statement::ForLoop(
+        name,
+        name_start,
+        name_end,
+        start,
+        end,
+        range_start,
+        range_end,
+        ascending,
+        body,
+    ) = statement
+    {
+        let Some((location, tipe)) = scope.get_variable(name) else {
+            diagnostics.push(get_assignment_error(name, *name_start, *name_end, scope));
+            return out;
+        };
+        let tipe = get_base_type(&tipe);
+        if !is_ordinal(&tipe) {
+            diagnostics.push(Diagnostic::error(
+                "P1060",
+                *name_start,
+                *name_end,
+                "For loop iterator must be an ordinal type",
+            ));
+        }
+        let (start_code, start_value, start_tipe) =
+            evaluate_final_expression(start, src, label_idx, diagnostics, frame, scope);
+        let (end_code, end_value, end_tipe) =
+            evaluate_final_expression(end, src, label_idx, diagnostics, frame, scope);
+        if start_tipe != tipe || end_tipe != tipe {
+            diagnostics.push(Diagnostic::error(
+                "P1061",
+                *range_start,
+                *range_end,
+                "For loop range must match the iterator type",
+            ));
+        }
+        // byte sized iterators are extended so the comparison against the end works the same
+        let kind = get_kind(&tipe);
+        out.append(start_code);
+        let iterator = get_location_address(location, &mut out, frame);
+        out.store(kind, iterator, start_value);
+        out.append(end_code);
+        let end_value = extend(end_value, &mut out, frame);
+
+        // the body is skipped if the range is empty, and the loop stops after the last value so
+        // the iterator never goes past the end of its type
+        let body_label = new_label(label_idx);
+        let end_label = new_label(label_idx);
+        let (past, step) = if *ascending {
+            (Condition::Greater, BinaryOperator::Add)
+        } else {
+            (Condition::Less, BinaryOperator::Subtract)
+        };
+        let first = out.load(frame, kind, iterator);
+        let first = extend(first, &mut out, frame);
+        let is_empty = out.compare(frame, past, Kind::Word, first, end_value);
+        out.terminate(Terminator::Branch(is_empty, end_label, body_label));
+        out.label(body_label);
+        out.append(process_statement(
+            body,
+            src,
+            label_idx,
+            diagnostics,
+            frame,
+            scope,
+        ));
+        let current = out.load(frame, kind, iterator);
+        let extended = extend(current, &mut out, frame);
+        let is_last = out.compare(frame, Condition::Equal, Kind::Word, extended, end_value);
+        let step_label = new_label(label_idx);
+        out.terminate(Terminator::Branch(is_last, end_label, step_label));
+        out.label(step_label);
+        let next = out.binary(frame, kind, step, current, Operand::Integer(1));
+        out.store(kind, iterator, next);
+        out.terminate(Terminator::Jump(body_label));
+        out.label(end_label);
+    }
+    out
+}
+
+// adds code to read the next element of the typed file at `file` into its buffer. The file is at
+// its end if there isn't one.
+fn read_file_buffer(file: Operand, size: usize, code: &mut Code, frame: &mut Frame) {
+    let handle = code.load(frame, Kind::Word, Address::Pointer(file, 0));
+    let buffer = code.address(frame, Address::Pointer(file, 24));
+    let is_full = code.runtime(
+        frame,
+        Some(Kind::Word),
+        "rt_read_block",
+        vec![handle, buffer, Operand::Integer(size as i64)],
+    );
+    code.store(Kind::Word, Address::Pointer(file, 16), is_full);
+}
+
+// returns code for ASSIGN, RESET, REWRITE, CLOSE, GET or PUT. ASSIGN names a file, and RESET and
+// REWRITE (re)open it for reading or writing. input and output have no name, so they stay bound to
+// stdin and stdout. A typed file's buffer is filled by GET, which RESET calls to read ahead, and
+// written by PUT.
+#[allow(clippy::too_many_arguments)]
+fn process_file_procedure(
+    name: &str,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut out = Code::default();
+    let expected = if name == "ASSIGN" { 2 } else { 1 };
+    if arguments.len() != expected {
+        diagnostics.push(Diagnostic::error(
+            "P1003",
+            start,
+            end,
+            if expected == 1 {
+                "Expected 1 argument"
+            } else {
+                "Expected 2 arguments"
+            },
+        ));
+        return out;
+    }
+    let address = get_expression_variable(&arguments[0])
+        .and_then(|factor| evaluate_address(factor, src, label_idx, diagnostics, frame, scope));
+    let Some((address_code, address, tipe)) = address.filter(|(_, _, tipe)| is_file(tipe)) else {
+        diagnostics.push(Diagnostic::error(
+            "P1013",
+            arguments[0].start,
+            arguments[0].end,
+            "Expected file variable",
+        ));
+        return out;
+    };
+    let element_size = match &tipe {
+        Type::File(element_tipe) => Some(get_size(element_tipe)),
+        _ => None,
+    };
+    if (name == "GET" || name == "PUT") && element_size.is_none() {
+        diagnostics.push(Diagnostic::error(
+            "P1013",
+            arguments[0].start,
+            arguments[0].end,
+            "Expected typed file variable",
+        ));
+        return out;
+    }
+    out.append(address_code);
+    let file = out.address(frame, address);
+    let handle_address = Address::Pointer(file, 0);
+    let name_address = Address::Pointer(file, 8);
+    let done = new_label(label_idx);
+    match name {
+        "ASSIGN" => {
+            let (value, tipe) =
+                evaluate_expression(&arguments[1], src, label_idx, diagnostics, frame, scope);
+            if !is_assignable(&Type::Stryng, &tipe) {
+                diagnostics.push(Diagnostic::error(
+                    "P1062",
+                    arguments[1].start,
+                    arguments[1].end,
+                    "Expected string as file name",
+                ));
+                return out;
+            }
+            // opening needs the name ending in a NUL, so it's copied to the heap
+            let (value_code, string) = as_string(value, &tipe, frame);
+            out.append(value_code);
+            let characters = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                string,
+                Operand::Integer(1),
+            );
+            let length = get_string_length(string, &mut out, frame);
+            let copy = out.runtime(
+                frame,
+                Some(Kind::Word),
+                "rt_strndup",
+                vec![characters, length],
+            );
+            out.store(Kind::Word, name_address, copy);
+        }
+        "GET" => read_file_buffer(file, element_size.unwrap_or(0), &mut out, frame),
+        "PUT" => {
+            let handle = out.load(frame, Kind::Word, handle_address);
+            let buffer = out.address(frame, Address::Pointer(file, 24));
+            let size = Operand::Integer(element_size.unwrap_or(0) as i64);
+            out.runtime(frame, None, "rt_write", vec![handle, buffer, size]);
+        }
+        "CLOSE" => {
+            let file_name = out.load(frame, Kind::Word, name_address);
+            let is_unnamed = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                file_name,
+                Operand::Integer(0),
+            );
+            let named = new_label(label_idx);
+            out.terminate(Terminator::Branch(is_unnamed, done, named));
+            out.label(named);
+            let handle = out.load(frame, Kind::Word, handle_address);
+            let is_closed = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                handle,
+                Operand::Integer(0),
+            );
+            let open = new_label(label_idx);
+            out.terminate(Terminator::Branch(is_closed, done, open));
+            out.label(open);
+            out.runtime(frame, None, "rt_close", vec![handle]);
+            out.store(Kind::Word, handle_address, Operand::Integer(0));
+        }
+        _ => {
+            let unnamed = new_label(label_idx);
+            let no_name = new_label(label_idx);
+            let named = new_label(label_idx);
+            let open = new_label(label_idx);
+            let closed = new_label(label_idx);
+            let failed = new_label(label_idx);
+            let opened = new_label(label_idx);
+            let file_name = out.load(frame, Kind::Word, name_address);
+            let is_unnamed = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                file_name,
+                Operand::Integer(0),
+            );
+            out.terminate(Terminator::Branch(is_unnamed, unnamed, named));
+            out.label(unnamed);
+            let handle = out.load(frame, Kind::Word, handle_address);
+            let is_bound = out.compare(
+                frame,
+                Condition::NotEqual,
+                Kind::Word,
+                handle,
+                Operand::Integer(0),
+            );
+            out.terminate(Terminator::Branch(is_bound, done, no_name));
+            out.label(no_name);
+            out.terminate(Terminator::Error("File has no name".to_string(), None));
+            out.label(named);
+            let handle = out.load(frame, Kind::Word, handle_address);
+            let is_closed = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                handle,
+                Operand::Integer(0),
+            );
+            out.terminate(Terminator::Branch(is_closed, closed, open));
+            out.label(open);
+            out.runtime(frame, None, "rt_close", vec![handle]);
+            out.label(closed);
+            let file_name = out.load(frame, Kind::Word, name_address);
+            let mode = Operand::Integer(i64::from(name == "REWRITE"));
+            let handle = out.runtime(frame, Some(Kind::Word), "rt_open", vec![file_name, mode]);
+            out.store(Kind::Word, handle_address, handle);
+            let is_closed = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                handle,
+                Operand::Integer(0),
+            );
+            out.terminate(Terminator::Branch(is_closed, failed, opened));
+            out.label(failed);
+            out.terminate(Terminator::Error(
+                "Couldn't open file ".to_string(),
+                Some(file_name),
+            ));
+            out.label(opened);
+            // a typed file being written is always at its end
+            match (name, element_size) {
+                ("RESET", Some(size)) => read_file_buffer(file, size, &mut out, frame),
+                (_, Some(_)) => {
+                    out.store(Kind::Word, Address::Pointer(file, 16), Operand::Integer(0))
+                }
+                _ => {}
+            }
+        }
+    }
+    out.label(done);
+    out
+}
+
+// returns code for INSERT, DELETE and VAL, which change string (or, for VAL, number) variables
+// in place
+#[allow(clippy::too_many_arguments)]
+fn process_string_procedure(
+    name: &str,
+    arguments: &[Expression],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut out = Code::default();
+    if arguments.len() != 3 {
+        diagnostics.push(Diagnostic::error(
+            "P1003",
+            start,
+            end,
+            "Expected 3 arguments",
+        ));
+        return out;
+    }
+    // returns code for the address of the argument if it's a variable of one of the given types
+    let get_variable_address = |argument: &Expression,
+                                tipes: &[Type],
+                                message: &str,
+                                label_idx: &mut u32,
+                                diagnostics: &mut Vec<Diagnostic>,
+                                frame: &mut Frame| {
+        let address = get_expression_variable(argument)
+            .and_then(|factor| evaluate_address(factor, src, label_idx, diagnostics, frame, scope));
+        match address {
+            Some((code, address, tipe)) if tipes.contains(&get_base_type(&tipe)) => {
+                Some((code, address, get_base_type(&tipe)))
+            }
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    "P1013",
+                    argument.start,
+                    argument.end,
+                    message,
+                ));
+                None
+            }
+        }
+    };
+    match name {
+        "DELETE" => {
+            let Some((address_code, address, _)) = get_variable_address(
+                &arguments[0],
+                &[Type::Stryng],
+                "Expected string variable",
+                label_idx,
+                diagnostics,
+                frame,
+            ) else {
+                return out;
+            };
+            out.append(address_code);
+            let string = out.address(frame, address);
+            let mut values = Vec::new();
+            for argument in &arguments[1..] {
+                let (value_code, value, tipe) =
+                    evaluate_final_expression(argument, src, label_idx, diagnostics, frame, scope);
+                if !matches!(get_base_type(&tipe), Type::Integer | Type::Undefined) {
+                    diagnostics.push(Diagnostic::error(
+                        "P1004",
+                        argument.start,
+                        argument.end,
+                        "Expected integer as argument",
+                    ));
+                }
+                out.append(value_code);
+                values.push(value);
+            }
+            let (index, count) = (values[0], values[1]);
+            // nothing is deleted unless the index is of a character in the string, and at most
+            // the characters from there to the end are
+            let length = get_string_length(string, &mut out, frame);
+            let before = out.compare(
+                frame,
+                Condition::Less,
+                Kind::Word,
+                index,
+                Operand::Integer(1),
+            );
+            let after = out.compare(frame, Condition::Greater, Kind::Word, index, length);
+            let is_outside = out.binary(frame, Kind::Byte, BinaryOperator::Or, before, after);
+            let is_none = out.compare(
+                frame,
+                Condition::LessEqual,
+                Kind::Word,
+                count,
+                Operand::Integer(0),
+            );
+            let is_skipped = out.binary(frame, Kind::Byte, BinaryOperator::Or, is_outside, is_none);
+            let delete = new_label(label_idx);
+            let done = new_label(label_idx);
+            out.terminate(Terminator::Branch(is_skipped, done, delete));
+            out.label(delete);
+            let rest = out.binary(frame, Kind::Word, BinaryOperator::Subtract, length, index);
+            let rest = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                rest,
+                Operand::Integer(1),
+            );
+            let count = out.binary(frame, Kind::Word, BinaryOperator::Minimum, count, rest);
+            let new_length = out.binary(frame, Kind::Word, BinaryOperator::Subtract, length, count);
+            out.store(Kind::Byte, Address::Pointer(string, 0), new_length);
+            let kept = out.binary(frame, Kind::Word, BinaryOperator::Subtract, rest, count);
+            let to = out.binary(frame, Kind::Word, BinaryOperator::Add, string, index);
+            let from = out.binary(frame, Kind::Word, BinaryOperator::Add, to, count);
+            out.copy(to, from, kept);
+            out.label(done);
+        }
+        "INSERT" => {
+            let Some((address_code, address, _)) = get_variable_address(
+                &arguments[1],
+                &[Type::Stryng],
+                "Expected string variable",
+                label_idx,
+                diagnostics,
+                frame,
+            ) else {
+                return out;
+            };
+            out.append(address_code);
+            let string = out.address(frame, address);
+            let (index_code, index, index_tipe) =
+                evaluate_final_expression(&arguments[2], src, label_idx, diagnostics, frame, scope);
+            if !matches!(get_base_type(&index_tipe), Type::Integer | Type::Undefined) {
+                diagnostics.push(Diagnostic::error(
+                    "P1004",
+                    arguments[2].start,
+                    arguments[2].end,
+                    "Expected integer as argument",
+                ));
+            }
+            out.append(index_code);
+            let (value, tipe) =
+                evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+            if !is_assignable(&Type::Stryng, &tipe) {
+                diagnostics.push(Diagnostic::error(
+                    "P1004",
+                    arguments[0].start,
+                    arguments[0].end,
+                    "Expected string as argument",
+                ));
+                return out;
+            }
+            let (value_code, inserted) = as_string(value, &tipe, frame);
+            out.append(value_code);
+
+            // an index before the start counts as the start, and one after the end appends
+            let length = get_string_length(string, &mut out, frame);
+            let index = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Maximum,
+                index,
+                Operand::Integer(1),
+            );
+            let limit = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                length,
+                Operand::Integer(1),
+            );
+            let index = out.binary(frame, Kind::Word, BinaryOperator::Minimum, index, limit);
+
+            // the new string is built in a slot of its own, then copied over the old one
+            let result = scratch_address(frame.scratch(MAX_STRING_LENGTH + 1), &mut out, frame);
+            out.store(Kind::Byte, Address::Pointer(result, 0), Operand::Integer(0));
+            let characters = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                string,
+                Operand::Integer(1),
+            );
+            let before = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Subtract,
+                index,
+                Operand::Integer(1),
+            );
+            append_characters(
+                result, characters, before, start, src, label_idx, &mut out, frame,
+            );
+            concatenate_strings(result, inserted, start, src, label_idx, &mut out, frame);
+            let after = out.binary(frame, Kind::Word, BinaryOperator::Subtract, limit, index);
+            let from = out.binary(frame, Kind::Word, BinaryOperator::Add, string, index);
+            append_characters(result, from, after, start, src, label_idx, &mut out, frame);
+            let result_length = get_string_length(result, &mut out, frame);
+            let size = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                result_length,
+                Operand::Integer(1),
+            );
+            out.copy(string, result, size);
+        }
+        _ => {
+            let Some((number_code, number, number_tipe)) = get_variable_address(
+                &arguments[1],
+                &[Type::Integer, Type::Real],
+                "Expected integer or real variable",
+                label_idx,
+                diagnostics,
+                frame,
+            ) else {
+                return out;
+            };
+            let Some((code_code, code, _)) = get_variable_address(
+                &arguments[2],
+                &[Type::Integer],
+                "Expected integer variable",
+                label_idx,
+                diagnostics,
+                frame,
+            ) else {
+                return out;
+            };
+            let (value, tipe) =
+                evaluate_expression(&arguments[0], src, label_idx, diagnostics, frame, scope);
+            if !is_assignable(&Type::Stryng, &tipe) {
+                diagnostics.push(Diagnostic::error(
+                    "P1004",
+                    arguments[0].start,
+                    arguments[0].end,
+                    "Expected string as argument",
+                ));
+                return out;
+            }
+            out.append(number_code);
+            let number = out.address(frame, number);
+            out.append(code_code);
+            let code = out.address(frame, code);
+            let (value_code, string) = as_string(value, &tipe, frame);
+            out.append(value_code);
+
+            // the runtime gives the number of characters used in a slot of the caller's
+            let used_slot = frame.scratch(8);
+            let used = scratch_address(used_slot, &mut out, frame);
+            let characters = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                string,
+                Operand::Integer(1),
+            );
+            let length = get_string_length(string, &mut out, frame);
+            let arguments = vec![characters, length, used];
+            if number_tipe == Type::Integer {
+                let value = out.runtime(frame, Some(Kind::Word), "rt_parse_int", arguments);
+                out.store(Kind::Word, Address::Pointer(number, 0), value);
+            } else {
+                let value = out.runtime(frame, Some(Kind::Real), "rt_parse_real", arguments);
+                out.store(Kind::Real, Address::Pointer(number, 0), value);
+            }
+            // the code is 0 if the whole string was used, otherwise the position of the first
+            // character that wasn't
+            let used = out.load(frame, Kind::Word, Address::Pointer(used, 0));
+            let is_partial = out.compare(frame, Condition::NotEqual, Kind::Word, used, length);
+            let is_empty = out.compare(
+                frame,
+                Condition::Equal,
+                Kind::Word,
+                length,
+                Operand::Integer(0),
+            );
+            let has_error = out.binary(frame, Kind::Byte, BinaryOperator::Or, is_partial, is_empty);
+            let failed = new_label(label_idx);
+            let parsed = new_label(label_idx);
+            let done = new_label(label_idx);
+            out.terminate(Terminator::Branch(has_error, failed, parsed));
+            out.label(failed);
+            let position = out.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                used,
+                Operand::Integer(1),
+            );
+            out.store(Kind::Word, Address::Pointer(code, 0), position);
+            out.terminate(Terminator::Jump(done));
+            out.label(parsed);
+            out.store(Kind::Word, Address::Pointer(code, 0), Operand::Integer(0));
+            out.label(done);
+        }
+    }
+    out
+}
+
+// returns code for a call to WRITE or WRITELN, writing each argument to the text file given
+// first, or stdout if there isn't one. Arguments with a field width are right justified in it,
+// and strings longer than it are cut short. Reals are written in exponent form
+// unless they're given a number of fraction digits.
+#[allow(clippy::too_many_arguments)]
+fn process_write(
+    new_line: bool,
+    arguments: &[(Expression, Option<Expression>, Option<Expression>)],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut out = Code::default();
+    let (file, arguments) = match arguments.split_first() {
+        Some(((file, None, None), rest)) => match get_file_variable(file, scope) {
+            Some((file_name, tipe)) => (Some((file, file_name, tipe)), rest),
+            None => (None, arguments),
+        },
+        _ => (None, arguments),
+    };
+
+    // writing x to a typed file is short for f^ := x; PUT(f)
+    if let Some((file, file_name, Type::File(_))) = &file {
+        if new_line {
+            diagnostics.push(Diagnostic::error(
+                "P1063",
+                start,
+                end,
+                "WRITELN can only be used with text files",
+            ));
+        }
+        for (value, width, _) in arguments {
+            if let Some(width) = width {
+                diagnostics.push(Diagnostic::error(
+                    "P1064",
+                    width.start,
+                    width.end,
+                    "Field widths can only be used with text files",
+                ));
+            }
+            let buffer = vec![Selector::Dereference(value.start, value.end)];
+            for statement in [
+                Statement::SelectionAssignment(
+                    file_name.clone(),
+                    buffer,
+                    value.clone(),
+                    value.start,
+                    value.end,
+                ),
+                Statement::ProcedureCall("PUT".to_string(), vec![(*file).clone()], start, end),
+            ] {
+                out.append(process_statement(
+                    &statement,
+                    src,
+                    label_idx,
+                    diagnostics,
+                    frame,
+                    scope,
+                ));
+            }
+        }
+        return out;
+    }
+
+    // the runtime's file for the text file, which is kept while the arguments are written
+    let file_factor = file
+        .as_ref()
+        .and_then(|(file, _, _)| get_expression_variable(file));
+    let address = file_factor
+        .and_then(|factor| evaluate_address(factor, src, label_idx, diagnostics, frame, scope));
+    let file = match address {
+        Some((address_code, address, _)) => {
+            out.append(address_code);
+            out.load(frame, Kind::Word, address)
+        }
+        None => {
+            let temp = frame.temp(Kind::Word);
+            out.push(Instruction::Stream(temp, Stream::Output));
+            Operand::Temp(temp)
+        }
+    };
+    for (value, width, precision) in arguments {
+        let (width, precision) = evaluate_field_widths(
+            width,
+            precision,
+            src,
+            label_idx,
+            diagnostics,
+            &mut out,
+            frame,
+            scope,
+        );
+        let (value_code, operand, tipe) =
+            evaluate_final_expression(value, src, label_idx, diagnostics, frame, scope);
+        out.append(value_code);
+        let tipe = get_base_type(&tipe);
+        if precision.is_some() && !matches!(tipe, Type::Real | Type::Undefined) {
+            diagnostics.push(Diagnostic::error(
+                "P1065",
+                value.start,
+                value.end,
+                "Only reals can be written with fraction digits",
+            ));
+            continue;
+        }
+        let is_written = add_write_call(
+            file, operand, &tipe, width, precision, label_idx, &mut out, frame,
+        );
+        if !is_written && tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                "P1066",
+                start,
+                end,
+                "Print function not defined for all types in call",
+            ));
+        }
+    }
+    if new_line {
+        out.runtime(
+            frame,
+            None,
+            "rt_write_char",
+            vec![file, Operand::Integer(10), Operand::Integer(0)],
+        );
+    }
+    out
+}
+
+// adds code for the field width and fraction digits of a WRITE or STR argument, and returns
+// them if it has them
+#[allow(clippy::too_many_arguments)]
+fn evaluate_field_widths(
+    width: &Option<Expression>,
+    precision: &Option<Expression>,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    code: &mut Code,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> (Option<Operand>, Option<Operand>) {
+    let mut fields = [None, None];
+    for (field, operand) in [width, precision].into_iter().zip(&mut fields) {
+        let Some(field) = field else {
+            continue;
+        };
+        let (field_value, field_type) =
+            evaluate_expression(field, src, label_idx, diagnostics, frame, scope);
+        if !matches!(get_base_type(&field_type), Type::Integer | Type::Undefined) {
+            diagnostics.push(Diagnostic::error(
+                "P1067",
+                field.start,
+                field.end,
+                "Field widths must be integers",
+            ));
+        } else if let Value::Constant(n) = &field_value {
+            if n.parse::<i64>().is_ok_and(|n| n < 0) {
+                diagnostics.push(Diagnostic::error(
+                    "P1068",
+                    field.start,
+                    field.end,
+                    "Field widths can't be negative",
+                ));
+            }
+        }
+        let (field_code, field_operand) = evaluate_constant(field_value, &field_type, frame);
+        code.append(field_code);
+        *operand = Some(field_operand);
+    }
+    let [width, precision] = fields;
+    (width, precision)
+}
+
+// adds code to write a value of type `tipe` to `file`, with its width and fraction digits if it
+// has them, or returns false if the type can't be written
+#[allow(clippy::too_many_arguments)]
+fn add_write_call(
+    file: Operand,
+    value: Operand,
+    tipe: &Type,
+    width: Option<Operand>,
+    precision: Option<Operand>,
+    label_idx: &mut u32,
+    code: &mut Code,
+    frame: &mut Frame,
+) -> bool {
+    // without a width, the field is only as wide as the value
+    let field = width.unwrap_or(Operand::Integer(0));
+    match tipe {
+        Type::Integer => {
+            code.runtime(frame, None, "rt_write_int", vec![file, value, field]);
+        }
+        Type::Char => {
+            code.runtime(frame, None, "rt_write_char", vec![file, value, field]);
+        }
+        // strings may not be longer than the width
+        Type::Stryng => {
+            let characters = code.binary(
+                frame,
+                Kind::Word,
+                BinaryOperator::Add,
+                value,
+                Operand::Integer(1),
+            );
+            let mut length = get_string_length(value, code, frame);
+            if let Some(width) = width {
+                length = code.binary(
+                    frame,
+                    Kind::Word,
+                    BinaryOperator::UnsignedMinimum,
+                    length,
+                    width,
+                );
+            }
+            code.runtime(
+                frame,
+                None,
+                "rt_write_string",
+                vec![file, characters, length, field],
+            );
+        }
+        Type::Boolean => {
+            let text = frame.temp(Kind::Word);
+            let length = frame.temp(Kind::Word);
+            let true_label = new_label(label_idx);
+            let false_label = new_label(label_idx);
+            let done = new_label(label_idx);
+            code.terminate(Terminator::Branch(value, true_label, false_label));
+            for (label, name) in [(true_label, "TRUE"), (false_label, "FALSE")] {
+                code.label(label);
+                let data = frame.temp(Kind::Word);
+                code.push(Instruction::Data(data, name.as_bytes().to_vec()));
+                code.push(Instruction::Move(text, Operand::Temp(data)));
+                code.push(Instruction::Move(
+                    length,
+                    Operand::Integer(name.len() as i64),
+                ));
+                code.terminate(Terminator::Jump(done));
+            }
+            code.label(done);
+            // with a width, long names are cut short too
+            let mut length = Operand::Temp(length);
+            if let Some(width) = width {
+                length = code.binary(
+                    frame,
+                    Kind::Word,
+                    BinaryOperator::UnsignedMinimum,
+                    length,
+                    width,
+                );
+            }
+            code.runtime(
+                frame,
+                None,
+                "rt_write_string",
+                vec![file, Operand::Temp(text), length, field],
+            );
+        }
+        Type::Real => match (width, precision) {
+            // the default width is 22, which leaves 15 digits after the decimal point
+            (None, _) => {
+                code.runtime(
+                    frame,
+                    None,
+                    "rt_write_real",
+                    vec![file, value, Operand::Integer(15)],
+                );
+            }
+            (Some(width), Some(precision)) => {
+                code.runtime(
+                    frame,
+                    None,
+                    "rt_write_fixed",
+                    vec![file, value, width, precision],
+                );
+            }
+            // the sign (or a space), first digit, decimal point and exponent take up 7
+            // characters, and there's at least one digit after the decimal point
+            (Some(width), None) => {
+                let width = code.binary(
+                    frame,
+                    Kind::Word,
+                    BinaryOperator::Maximum,
+                    width,
+                    Operand::Integer(8),
+                );
+                let digits = code.binary(
+                    frame,
+                    Kind::Word,
+                    BinaryOperator::Subtract,
+                    width,
+                    Operand::Integer(7),
+                );
+                code.runtime(frame, None, "rt_write_real", vec![file, value, digits]);
+            }
+        },
+        _ => return false,
+    }
+    true
+}
+
+// returns code for STR, which writes a number to a string variable the way WRITE would
+#[allow(clippy::too_many_arguments)]
+fn process_str(
+    arguments: &[(Expression, Option<Expression>, Option<Expression>)],
+    start: usize,
+    end: usize,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let mut out = Code::default();
+    let [(value, width, precision), (target, None, None)] = arguments else {
+        diagnostics.push(Diagnostic::error(
+            "P1003",
+            start,
+            end,
+            "Expected 2 arguments",
+        ));
+        return out;
+    };
+    let address = get_expression_variable(target)
+        .and_then(|factor| evaluate_address(factor, src, label_idx, diagnostics, frame, scope));
+    let Some((address_code, address, Type::Stryng)) = address else {
+        diagnostics.push(Diagnostic::error(
+            "P1013",
+            target.start,
+            target.end,
+            "Expected string variable",
+        ));
+        return out;
+    };
+
+    // the text is written to a string file in a slot, which keeps its length 8 bytes in and its
+    // chars from 56 on
+    let sink = scratch_address(frame.scratch(320), &mut out, frame);
+    out.runtime(frame, None, "rt_string_sink", vec![sink]);
+    let (width, precision) = evaluate_field_widths(
+        width,
+        precision,
+        src,
+        label_idx,
+        diagnostics,
+        &mut out,
+        frame,
+        scope,
+    );
+    let (value_code, operand, tipe) =
+        evaluate_final_expression(value, src, label_idx, diagnostics, frame, scope);
+    out.append(value_code);
+    let tipe = get_base_type(&tipe);
+    if !matches!(tipe, Type::Integer | Type::Real) || (precision.is_some() && tipe != Type::Real) {
+        if tipe != Type::Undefined {
+            diagnostics.push(Diagnostic::error(
+                if precision.is_some() {
+                    "P1065"
+                } else {
+                    "P1004"
+                },
+                value.start,
+                value.end,
+                if precision.is_some() {
+                    "Only reals can be written with fraction digits"
+                } else {
+                    "Expected integer or real as argument"
+                },
+            ));
+        }
+        return out;
+    }
+    add_write_call(
+        sink, operand, &tipe, width, precision, label_idx, &mut out, frame,
+    );
+    // the string file takes one char more than fit, so a longer text can be told apart
+    let length = out.load(frame, Kind::Word, Address::Pointer(sink, 8));
+    let overflows = out.compare(
+        frame,
+        Condition::Above,
+        Kind::Word,
+        length,
+        Operand::Integer(MAX_STRING_LENGTH as i64),
+    );
+    check(
+        overflows,
+        "String too long",
+        start,
+        src,
+        label_idx,
+        &mut out,
+    );
+    out.append(address_code);
+    let string = out.address(frame, address);
+    out.store(Kind::Byte, Address::Pointer(string, 0), length);
+    let characters = out.binary(
+        frame,
+        Kind::Word,
+        BinaryOperator::Add,
+        string,
+        Operand::Integer(1),
+    );
+    let text = out.address(frame, Address::Pointer(sink, 56));
+    out.copy(characters, text, length);
+    out
+}
+
+// returns code for a WITH statement, opening the fields of each record in turn. The address of
+// each record is kept in a slot, so it's only evaluated once.
+fn process_with(
+    records: &[Factor],
+    body: &Statement,
+    src: &str,
+    label_idx: &mut u32,
+    diagnostics: &mut Vec<Diagnostic>,
+    frame: &mut Frame,
+    scope: &Scope,
+) -> Code {
+    let Some((record, rest)) = records.split_first() else {
+        return process_statement(body, src, label_idx, diagnostics, frame, scope);
+    };
+    let mut out = Code::default();
+    let mut with_scope = Scope {
+        parent: Some(scope),
+        depth: scope.depth,
+        variables: HashMap::new(),
+        constants: HashMap::new(),
+        procedures: HashMap::new(),
+        types: HashMap::new(),
+        fields: HashMap::new(),
+        labels: HashMap::new(),
+        declarations: HashMap::new(),
+        options: scope.options,
+    };
+    match evaluate_address(record, src, label_idx, diagnostics, frame, scope) {
+        Some((address_code, address, Type::Record(fields, _))) => {
+            out.append(address_code);
+            let slot = frame.scratch(8);
+            let record_address = out.address(frame, address);
+            let reference = Address::Slot {
+                hops: 0,
+                slot,
+                offset: 0,
+            };
+            out.store(Kind::Word, reference, record_address);
+            for (name, tipe, offset) in fields {
+                with_scope
+                    .fields
+                    .insert(name, (slot, offset as isize, tipe));
+            }
+        }
+        _ => {
+            let (start, end) = match record {
+                Factor::Identifier(_, _, start, end)
+                | Factor::ArrayIndex(_, _, start, end)
+                | Factor::Selection(_, _, start, end) => (*start, *end),
+                _ => (0, 0),
+            };
+            diagnostics.push(Diagnostic::error(
+                "P1013",
+                start,
+                end,
+                "Expected a record variable",
+            ));
+        }
+    }
+    out.append(process_with(
+        rest,
+        body,
+        src,
+        label_idx,
+        diagnostics,
+        frame,
+        &with_scope,
+    ));
+    out
+}
+
+// adds the labels prefixing statements to `labels`
+fn find_labels(
+    statement: &Statement,
+    diagnostics: &mut Vec<Diagnostic>,
+    labels: &mut HashSet<u64>,
+) {
+    match statement {
+        Statement::LabeledStatement(label, body, start, end) => {
+            if !labels.insert(*label) {
+                diagnostics.push(Diagnostic::error(
+                    "P1069",
+                    *start,
+                    *end,
+                    "Label prefixes more than one statement",
+                ));
+            }
+            find_labels(body, diagnostics, labels);
+        }
+        Statement::StatementList(statements) => {
+            for statement in statements {
+                find_labels(statement, diagnostics, labels);
+            }
+        }
+        Statement::IfStatement(_, true_body, false_body, _, _) => {
+            find_labels(true_body, diagnostics, labels);
+            find_labels(false_body, diagnostics, labels);
+        }
+        Statement::CaseStatement(_, arms, otherwise, _, _) => {
+            for (_, body) in arms {
+                find_labels(body, diagnostics, labels);
+            }
+            if let Some(body) = otherwise {
+                find_labels(body, diagnostics, labels);
+            }
+        }
+        Statement::WhileLoop(_, body, _, _)
+        | Statement::RepeatLoop(_, body, _, _)
+        | Statement::WithStatement(_, body)
+        | Statement::ForLoop(_, _, _, _, _, _, _, _, body) => {
+            find_labels(body, diagnostics, labels)
+        }
+        _ => {}
+    }
+}
+
+// TODO - these should be borrowed, not taken
+fn evaluate_type(tipe1: Type, tipe2: Type) -> Type {
+    if tipe1 == tipe2 {
+        tipe1
+    } else if tipe1 == Type::Undefined || tipe2 == Type::Undefined {
+        // an error has already been reported for the operand
+        Type::Undefined
+    } else if (tipe1 == Type::Integer && tipe2 == Type::Real)
+        || (tipe1 == Type::Real && tipe2 == Type::Integer)
+    {
+        Type::Real
+    } else if (tipe1 == Type::Integer && tipe2 == Type::Char)
+        || (tipe1 == Type::Char && tipe2 == Type::Integer)
+    {
+        Type::Char
+    } else if is_assignable(&tipe1, &tipe2) {
+        // NIL with another pointer
+        tipe1
+    } else if is_assignable(&tipe2, &tipe1) {
+        tipe2
+    } else {
+        Type::Undefined
+    }
+}
+
+// returns true iff a value of type `value` can be stored in a variable of type `target`
+// files can't be assigned, or passed by value
+fn is_assignable(target: &Type, value: &Type) -> bool {
+    let target = get_base_type(target);
+    if contains_file(&target) {
+        return false;
+    }
+    *value == target
+        || *value == Type::Undefined
+        || (*value == Type::Integer && target == Type::Real)
+        || (*value == Type::Char && target == Type::Stryng)
+        || (*value == Type::Pointer(String::new()) && matches!(target, Type::Pointer(_)))
+        || (*value == Type::Set(Box::new(Type::Undefined)) && matches!(target, Type::Set(_)))
+}
+
+// subranges behave as their host type once their value has been loaded
+fn get_base_type(tipe: &Type) -> Type {
+    match tipe {
+        Type::Subrange(host, _, _) => *host.clone(),
+        _ => tipe.clone(),
+    }
+}
+
+// returns true iff the values of the type can be counted, one after another
+fn is_ordinal(tipe: &Type) -> bool {
+    matches!(
+        get_base_type(tipe),
+        Type::Integer | Type::Char | Type::Boolean | Type::Enumeration(_)
+    )
+}
+
+// returns the ordinal number of a constant of an ordinal type
+fn get_ordinal_value(value: &str, tipe: &Type) -> Option<i64> {
+    match get_base_type(tipe) {
+        Type::Boolean => value.parse::<bool>().ok().map(i64::from),
+        Type::Integer | Type::Char | Type::Enumeration(_) => value.parse::<i64>().ok(),
+        _ => None,
+    }
+}
+
+// returns the constant of an ordinal type with the given ordinal number, if there is one
+fn get_ordinal_constant(n: i64, tipe: &Type) -> Option<String> {
+    match get_base_type(tipe) {
+        Type::Boolean if (0..=1).contains(&n) => Some((n == 1).to_string()),
+        Type::Char if (0..=255).contains(&n) => Some(n.to_string()),
+        Type::Enumeration(identifiers) if (0..identifiers.len() as i64).contains(&n) => {
+            Some(n.to_string())
+        }
+        Type::Integer => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// returns the factor if `expression` is nothing more than a variable or array element
+fn get_expression_variable(expression: &Expression) -> Option<&Factor> {
+    let simple_expression = &expression.operand1;
+    if expression.operator != "NONE"
+        || !simple_expression.positive
+        || simple_expression.operands.len() != 1
+        || simple_expression.operands[0].operands.len() != 1
+    {
+        return None;
+    }
+    match &simple_expression.operands[0].operands[0] {
+        factor @ Factor::Identifier(_, arguments, _, _) if arguments.is_empty() => Some(factor),
+        factor @ Factor::ArrayIndex(_, _, _, _) => Some(factor),
+        factor @ Factor::Selection(_, _, _, _) => Some(factor),
+        _ => None,
+    }
+}
+
+// returns the name and type of the file variable if the expression is just its name, which READ
+// and WRITE take as their first argument
+fn get_file_variable(expression: &Expression, scope: &Scope) -> Option<(String, Type)> {
+    match get_expression_variable(expression) {
+        Some(Factor::Identifier(name, arguments, _, _)) if arguments.is_empty() => {
+            let (_, tipe) = scope.get_variable(name)?;
+            is_file(&tipe).then(|| (name.clone(), tipe))
+        }
+        _ => None,
+    }
+}
+
+// wraps a factor in an expression of its own
+fn make_factor_expression(factor: Factor, start: usize, end: usize) -> Expression {
+    let simple_expression = SimpleExpression {
+        start,
+        end,
+        positive: true,
+        operands: vec![Term {
+            start,
+            end,
+            operands: vec![factor],
+            operators: Vec::new(),
+        }],
+        operators: Vec::new(),
+    };
+    Expression {
+        start,
+        end,
+        operand1: simple_expression.clone(),
+        operand2: simple_expression,
+        operator: "NONE".to_string(),
+    }
+}
+
+// returns the result of an integer operation on two constants, or the error it causes
+fn fold_integers(
+    value1: &str,
+    operator: &str,
+    value2: &str,
+    start: usize,
+    end: usize,
+) -> Result<i64, Diagnostic> {
+    let overflow = || Diagnostic::error("P1001", start, end, "Integer overflow");
+    // literals too big for an integer don't parse
+    let (Ok(n1), Ok(n2)) = (value1.parse::<i64>(), value2.parse::<i64>()) else {
+        return Err(overflow());
+    };
+    if (operator == "DIV" || operator == "MOD") && n2 == 0 {
+        return Err(Diagnostic::error("P1019", start, end, "Division by zero"));
+    }
+    match operator {
+        "+" => n1.checked_add(n2),
+        "-" => n1.checked_sub(n2),
+        "*" => n1.checked_mul(n2),
+        "DIV" => n1.checked_div(n2),
+        "MOD" => n1.checked_rem(n2),
+        "AND" => Some(n1 & n2),
+        _ => Some(n1 | n2),
+    }
+    .ok_or_else(overflow)
+}
+
+// local variables known to be assigned at a point in a block's body
+#[derive(Clone, Default)]
+struct Assigned {
+    // assigned on every path to the point
+    definitely: HashSet<String>,
+    // assigned on some path, which is enough for arrays and records since they are assigned an
+    // element at a time
+    possibly: HashSet<String>,
+}
+
+impl Assigned {
+    fn insert(&mut self, name: &str) {
+        self.definitely.insert(name.to_string());
+        self.possibly.insert(name.to_string());
+    }
+
+    // combines the variables assigned along two paths that meet
+    fn merge(&mut self, other: Assigned) {
+        self.definitely
+            .retain(|name| other.definitely.contains(name));
+        self.possibly.extend(other.possibly);
+    }
+}
+
+// warns about local variables that may be read before they are assigned, where `tracked` holds the
+// variables to check and whether they are arrays or records. Variables are only reported once, and
+// reads aren't reported at all when `report_reads` is false. Jumps by GOTO are ignored.
+fn check_initialization(
+    statement: &Statement,
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) {
+    match statement {
+        Statement::Assignment(name, expression, _, _) => {
+            check_expression_reads(
+                expression,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.insert(name);
+        }
+        Statement::ElementAssignment(name, index, expression, _, _) => {
+            check_expression_reads(
+                index,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            check_expression_reads(
+                expression,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.insert(name);
+        }
+        Statement::SelectionAssignment(name, selectors, expression, start, end) => {
+            check_expression_reads(
+                expression,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            let target = Factor::Selection(name.clone(), selectors.clone(), *start, *end);
+            check_variable_assignment(
+                &target,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Statement::ProcedureCall(name, arguments, _, _) => {
+            // variables passed to be assigned
+            let is_assigned: Vec<bool> = match scope.get_procedure(name) {
+                Some(signature) => signature
+                    .parameters
+                    .iter()
+                    .map(|(_, by_reference)| *by_reference)
+                    .collect(),
+                None if name == "NEW" => vec![true],
+                None if name == "VAL" => vec![false, true, true],
+                None => Vec::new(),
+            };
+            check_arguments(
+                arguments,
+                &is_assigned,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Statement::WriteCall(name, arguments, _, _) => {
+            for (n, (value, width, fraction_digits)) in arguments.iter().enumerate() {
+                for expression in width.iter().chain(fraction_digits) {
+                    check_expression_reads(
+                        expression,
+                        tracked,
+                        assigned,
+                        report_reads,
+                        src,
+                        diagnostics,
+                        scope,
+                    );
+                }
+                // STR writes to its last argument
+                let is_assigned = name == "STR" && n == arguments.len() - 1;
+                check_arguments(
+                    std::slice::from_ref(value),
+                    &[is_assigned],
+                    tracked,
+                    assigned,
+                    report_reads,
+                    src,
+                    diagnostics,
+                    scope,
+                );
+            }
+        }
+        Statement::ReadCall(_, variables, _, _) => {
+            for name in variables {
+                assigned.insert(name);
+            }
+        }
+        Statement::IfStatement(condition, true_body, false_body, _, _) => {
+            check_expression_reads(
+                condition,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            let mut false_assigned = assigned.clone();
+            check_initialization(
+                true_body,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            check_initialization(
+                false_body,
+                tracked,
+                &mut false_assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.merge(false_assigned);
+        }
+        // the body may not run
+        Statement::WhileLoop(condition, body, _, _) => {
+            check_expression_reads(
+                condition,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            let mut body_assigned = assigned.clone();
+            check_initialization(
+                body,
+                tracked,
+                &mut body_assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.merge(body_assigned);
+        }
+        Statement::RepeatLoop(condition, body, _, _) => {
+            check_initialization(
+                body,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            check_expression_reads(
+                condition,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Statement::ForLoop(name, _, _, first, last, _, _, _, body) => {
+            check_expression_reads(
+                first,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            check_expression_reads(
+                last,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.insert(name);
+            let mut body_assigned = assigned.clone();
+            check_initialization(
+                body,
+                tracked,
+                &mut body_assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.merge(body_assigned);
+        }
+        Statement::CaseStatement(selector, arms, otherwise, _, _) => {
+            check_expression_reads(
+                selector,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            // without OTHERWISE, no arm may run
+            let mut result = if otherwise.is_some() {
+                None
+            } else {
+                Some(assigned.clone())
+            };
+            for body in arms
+                .iter()
+                .map(|(_, body)| body)
+                .chain(otherwise.as_deref())
+            {
+                let mut arm_assigned = assigned.clone();
+                check_initialization(
+                    body,
+                    tracked,
+                    &mut arm_assigned,
+                    report_reads,
+                    src,
+                    diagnostics,
+                    scope,
+                );
+                match &mut result {
+                    Some(result) => result.merge(arm_assigned),
+                    None => result = Some(arm_assigned),
+                }
+            }
+            if let Some(result) = result {
+                *assigned = result;
+            }
+        }
+        // the records are usually opened to assign their fields, and names in the body may be
+        // fields rather than variables
+        Statement::WithStatement(records, body) => {
+            for record in records {
+                let name = match record {
+                    Factor::Identifier(name, _, _, _)
+                    | Factor::ArrayIndex(name, _, _, _)
+                    | Factor::Selection(name, _, _, _) => name,
+                    _ => continue,
+                };
+                assigned.insert(name);
+            }
+            check_initialization(body, tracked, assigned, false, src, diagnostics, scope);
+        }
+        Statement::LabeledStatement(_, body, _, _) => check_initialization(
+            body,
+            tracked,
+            assigned,
+            report_reads,
+            src,
+            diagnostics,
+            scope,
+        ),
+        Statement::StatementList(statements) => {
+            for statement in statements {
+                check_initialization(
+                    statement,
+                    tracked,
+                    assigned,
+                    report_reads,
+                    src,
+                    diagnostics,
+                    scope,
+                );
+            }
+        }
+        Statement::DoNothing | Statement::Goto(_, _, _) => {}
+    }
+}
+
+// checks the arguments of a call, where `is_assigned` holds whether each is passed to be assigned
+#[allow(clippy::too_many_arguments)]
+fn check_arguments(
+    arguments: &[Expression],
+    is_assigned: &[bool],
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) {
+    for (n, argument) in arguments.iter().enumerate() {
+        match get_expression_variable(argument) {
+            Some(variable) if is_assigned.get(n) == Some(&true) => check_variable_assignment(
+                variable,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            ),
+            _ => check_expression_reads(
+                argument,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            ),
+        }
+    }
+}
+
+// checks a variable being assigned, which is read instead when the assignment is through a pointer
+fn check_variable_assignment(
+    variable: &Factor,
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) {
+    match variable {
+        Factor::Identifier(name, _, _, _) => assigned.insert(name),
+        Factor::ArrayIndex(name, index, _, _) => {
+            check_expression_reads(
+                index,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            assigned.insert(name);
+        }
+        Factor::Selection(name, selectors, start, _) => {
+            for selector in selectors {
+                if let Selector::Index(index) = selector {
+                    check_expression_reads(
+                        index,
+                        tracked,
+                        assigned,
+                        report_reads,
+                        src,
+                        diagnostics,
+                        scope,
+                    );
+                }
+            }
+            if selectors
+                .iter()
+                .any(|selector| matches!(selector, Selector::Dereference(_, _)))
+            {
+                check_variable_read(
+                    name,
+                    *start,
+                    *start + name.len(),
+                    tracked,
+                    assigned,
+                    report_reads,
+                    diagnostics,
+                );
+            } else {
+                assigned.insert(name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_expression_reads(
+    expression: &Expression,
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) {
+    let mut simple_expressions = vec![&expression.operand1];
+    if expression.operator != "NONE" {
+        simple_expressions.push(&expression.operand2);
+    }
+    for simple_expression in simple_expressions {
+        for term in &simple_expression.operands {
+            for factor in &term.operands {
+                check_factor_reads(
+                    factor,
+                    tracked,
+                    assigned,
+                    report_reads,
+                    src,
+                    diagnostics,
+                    scope,
+                );
+            }
+        }
+    }
+}
+
+fn check_factor_reads(
+    factor: &Factor,
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    scope: &Scope,
+) {
+    match factor {
+        Factor::Identifier(name, arguments, start, end) if arguments.is_empty() => {
+            check_variable_read(
+                name,
+                *start,
+                *end,
+                tracked,
+                assigned,
+                report_reads,
+                diagnostics,
+            )
+        }
+        Factor::Identifier(name, arguments, _, _) => {
+            let is_assigned: Vec<bool> = match scope.get_procedure(name) {
+                Some(signature) => signature
+                    .parameters
+                    .iter()
+                    .map(|(_, by_reference)| *by_reference)
+                    .collect(),
+                None => Vec::new(),
+            };
+            check_arguments(
+                arguments,
+                &is_assigned,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Factor::ArrayIndex(name, index, start, end) => {
+            check_expression_reads(
+                index,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+            check_variable_read(
+                name,
+                *start,
+                *end,
+                tracked,
+                assigned,
+                report_reads,
+                diagnostics,
+            );
+        }
+        Factor::Selection(name, selectors, start, end) => {
+            for selector in selectors {
+                if let Selector::Index(index) = selector {
+                    check_expression_reads(
+                        index,
+                        tracked,
+                        assigned,
+                        report_reads,
+                        src,
+                        diagnostics,
+                        scope,
+                    );
+                }
+            }
+            check_variable_read(
+                name,
+                *start,
+                *end,
+                tracked,
+                assigned,
+                report_reads,
+                diagnostics,
+            );
+        }
+        Factor::Parenthetical(expression) => {
+            check_expression_reads(
+                expression,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Factor::NegatedFactor(factor, _, _) => {
+            check_factor_reads(
+                factor,
+                tracked,
+                assigned,
+                report_reads,
+                src,
+                diagnostics,
+                scope,
+            );
+        }
+        Factor::List(elements) => {
+            for element in elements {
+                let expressions = match element {
+                    ExpressionOrRange::Expression(expression) => vec![expression],
+                    ExpressionOrRange::Range(first, last) => vec![first, last],
+                };
+                for expression in expressions {
+                    check_expression_reads(
+                        expression,
+                        tracked,
+                        assigned,
+                        report_reads,
+                        src,
+                        diagnostics,
+                        scope,
+                    );
+                }
+            }
+        }
+        Factor::Constant(_) => {}
+    }
+}
+
+// warns if a tracked variable may not be assigned before it is read, then stops tracking it
+fn check_variable_read(
+    name: &str,
+    start: usize,
+    end: usize,
+    tracked: &mut HashMap<String, bool>,
+    assigned: &mut Assigned,
+    report_reads: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(&is_aggregate) = tracked.get(name) else {
+        return;
+    };
+    let is_assigned = if is_aggregate {
+        assigned.possibly.contains(name)
+    } else {
+        assigned.definitely.contains(name)
+    };
+    if report_reads && !is_assigned {
+        diagnostics.push(Diagnostic::warning(
+            "P2003",
+            start,
+            end,
+            "Variable may be used before it is assigned",
+        ));
+        tracked.remove(name);
+    }
+}
+
+// returns the names assigned anywhere in the procedures, and the procedures declared in them
+fn find_assigned_in_procedures(
+    procedures: &[Procedure],
+    tracked: &HashMap<String, bool>,
+    src: &str,
+    scope: &Scope,
+) -> HashSet<String> {
+    let mut assigned = Assigned::default();
+    for procedure in procedures {
+        let mut diagnostics = Vec::new();
+        check_initialization(
+            &procedure.body.body,
+            &mut tracked.clone(),
+            &mut assigned,
+            false,
+            src,
+            &mut diagnostics,
+            scope,
+        );
+        assigned.possibly.extend(find_assigned_in_procedures(
+            &procedure.body.procedures,
+            tracked,
+            src,
+            scope,
+        ));
+    }
+    assigned.possibly
+}
+
+// returns the IR of the program, which is only complete if there are no errors among the
+// diagnostics
+pub fn analyze(mut code: Program, src: &str, options: Options) -> (ir::Program, Vec<Diagnostic>) {
+    // input and output are always declared, whether or not they're in the program heading
+    for name in ["INPUT", "OUTPUT"] {
+        if !code
+            .body
+            .local_variables
+            .iter()
+            .any(|variable| variable.name == name)
+        {
+            code.body.local_variables.push(Variable {
+                name: name.to_string(),
+                tipe: SuperType::Text,
+            });
+        }
+    }
+
+    let mut label_idx = 0;
+    let mut diagnostics = Vec::new();
+    let mut routines = Vec::new();
+    let predefined = Scope {
+        parent: None,
+        depth: 0,
+        variables: HashMap::new(),
+        constants: get_predefined_constant_map(),
+        procedures: HashMap::new(),
+        types: HashMap::new(),
+        fields: HashMap::new(),
+        labels: HashMap::new(),
+        declarations: HashMap::new(),
+        options,
+    };
+    process_block(
+        &code.body,
+        None,
+        &code.parameters,
+        "main",
+        None,
+        src,
+        &mut label_idx,
+        &mut diagnostics,
+        &mut routines,
+        &predefined,
+    );
+    (ir::Program { routines }, diagnostics)
+}
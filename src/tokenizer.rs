@@ -1,5 +1,4 @@
 use std::collections::HashSet;
-use std::process;
 
 // get symbol at location i, if non-existent return ""
 fn get_symbol(code: &str, i: usize) -> String {
@@ -9,7 +8,7 @@ fn get_symbol(code: &str, i: usize) -> String {
     let symbols: HashSet<&str> = vec![
         "+", "-", "*", "/", "<", "≤", "<=", "=", "≠", "<>", "≥", ">=", ">", "∧", "∨", "¬", "~",
         ":=", ",", ";", ",", ";", ":", "'", ".", "..", "(", ")", "[", "(.", "]", ".)", "//", "{",
-        "(*", "}", "*)",
+        "(*", "}", "*)", "^",
     ]
     .into_iter()
     .collect();
@@ -46,13 +45,25 @@ fn next_token_runner(code: &str, i: &mut usize, ignore_special: bool) -> String
     match token.as_str() {
         // handle block comment type 1
         "{" => {
-            while *i < code.len() && next_token_runner(code, i, true) != "}" {}
-            token.clear();
+            let mut closed = false;
+            while *i < code.len() && !closed {
+                closed = next_token_runner(code, i, true) == "}";
+            }
+            // an unclosed comment is left as a token, so the parser can report it
+            if closed {
+                token.clear();
+            }
         }
         // handle block comment type 2
         "(*" => {
-            while *i < code.len() && next_token_runner(code, i, true) != "*)" {}
-            token.clear();
+            let mut closed = false;
+            while *i < code.len() && !closed {
+                closed = next_token_runner(code, i, true) == "*)";
+            }
+            // an unclosed comment is left as a token, so the parser can report it
+            if closed {
+                token.clear();
+            }
         }
 
         // handle inline comment
@@ -74,10 +85,6 @@ fn next_token_runner(code: &str, i: &mut usize, ignore_special: bool) -> String
                     break;
                 }
             }
-            assert!(
-                code[*i - 1..].chars().next().unwrap() == '\'',
-                "Unmatched ' found"
-            )
         }
 
         // pascal synonyms
@@ -122,7 +129,7 @@ fn next_token_runner(code: &str, i: &mut usize, ignore_special: bool) -> String
         _ => {}
     }
 
-    if token.len() > 0 {
+    if !token.is_empty() {
         token
     } else if *i < code.len() {
         next_token_runner(code, i, false)
@@ -131,16 +138,9 @@ fn next_token_runner(code: &str, i: &mut usize, ignore_special: bool) -> String
     }
 }
 
-// get next token from source code at index i
+// get next token from source code at index i, or "" at the end of the input
 pub fn next_token(code: &str, i: &mut usize) -> String {
-    let result = next_token_runner(code, i, false);
-
-    if result.is_empty() {
-        println!("\x1b[31mUnexpected end of input\x1b[0m");
-        process::exit(1)
-    } else {
-        result
-    }
+    next_token_runner(code, i, false)
 }
 
 // get next token without updating i
@@ -0,0 +1,93 @@
+// tests for the command line itself: the exit status of each way of running the compiler, and
+// what it writes
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_pascal");
+
+// runs the compiler with `args` in a directory of its own, with the program `text` in test.pas
+fn pascal(name: &str, text: &str, args: &[&str]) -> Output {
+    pascal_to(name, text, args, Stdio::piped())
+}
+
+// the same, with what's printed going to `stdout`
+fn pascal_to(name: &str, text: &str, args: &[&str], stdout: Stdio) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("cli")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("test.pas"), text).unwrap();
+    Command::new(COMPILER)
+        .args(args)
+        .current_dir(&dir)
+        .stdout(stdout)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn run_exits_with_the_status_of_the_program() {
+    let output = pascal(
+        "run",
+        "PROGRAM Test; VAR s: string; BEGIN WRITELN('before'); s := 'ab'; \
+         REPEAT s := s + s UNTIL false END.",
+        &["run", "test.pas"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("String too long"));
+}
+
+#[test]
+fn errors_in_the_program_exit_with_1() {
+    let output = pascal(
+        "errors",
+        "PROGRAM Test; BEGIN x := 1 END.",
+        &["build", "test.pas"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn invalid_arguments_exit_with_2() {
+    let output = pascal("arguments", "", &["build", "--no-such-option", "test.pas"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn failing_to_write_output_is_an_error() {
+    let full = fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/full")
+        .unwrap();
+    let output = pascal_to(
+        "full",
+        "PROGRAM Test; BEGIN WRITELN('lost') END.",
+        &["run", "test.pas"],
+        Stdio::from(full),
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to write output"));
+}
+
+#[test]
+fn emit_ir_writes_the_listing() {
+    let output = pascal(
+        "ir",
+        "PROGRAM Test; VAR n: integer; BEGIN n := 2; WRITELN(n * 3) END.",
+        &["build", "test.pas", "--emit-ir"],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("cli")
+        .join("ir");
+    let listing = fs::read_to_string(dir.join("test.ir")).unwrap();
+    assert!(listing.starts_with("main():\n"));
+    assert!(listing.contains("Multiply"));
+    assert!(listing.contains("call rt_write_int"));
+    assert!(!dir.join("test").exists());
+}
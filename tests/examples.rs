@@ -0,0 +1,242 @@
+// golden tests for the programs in examples/: each one is compiled, with the flags in
+// tests/examples/<name>.args if there are any, and if that works, assembled, linked and run in a
+// directory of its own, with tests/examples/<name>.in as input if there is one. What the program
+// prints is compared with tests/examples/<name>.out, what the compiler and then the program print
+// as messages (without colors) with tests/examples/<name>.err, and the exit status of the program,
+// or of the compiler if it didn't compile, with tests/examples/<name>.status. Run with
+// UPDATE_EXPECTED=1 to write the expected files again.
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_pascal");
+
+// longer than any example takes, so a program stuck in a loop fails instead of hanging the tests
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn examples() {
+    check_programs("");
+}
+
+#[test]
+fn grogono_examples() {
+    check_programs("grogono");
+}
+
+#[test]
+fn bad_examples() {
+    check_programs("bad");
+}
+
+// what running an example printed, and how it ended
+struct Outcome {
+    out: Vec<u8>,
+    err: Vec<u8>,
+    status: String,
+}
+
+// compiles and runs every program in examples/<dir>
+fn check_programs(dir: &str) {
+    let mut failures = Vec::new();
+    for src in find_programs(dir) {
+        match compile_and_run(&src, &work_dir(dir, &src)) {
+            Ok(outcome) => {
+                compare(&src, "out", &outcome.out, &mut failures);
+                compare(&src, "err", &outcome.err, &mut failures);
+                compare(&src, "status", outcome.status.as_bytes(), &mut failures);
+            }
+            Err(message) => failures.push(format!("{} {}", src.display(), message)),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+fn compile_and_run(src: &Path, dir: &Path) -> Result<Outcome, String> {
+    let assembly = dir.join("program.s");
+    let flags = fs::read_to_string(expected(src, "args")).unwrap_or_default();
+    let compiler = Command::new(COMPILER)
+        .args(flags.split_whitespace())
+        .arg(src)
+        .arg(&assembly)
+        .output()
+        .map_err(|error| format!("couldn't run the compiler: {}", error))?;
+    // the line naming the assembly file differs between runs
+    let mut err: Vec<u8> = strip_colors(&[compiler.stdout, compiler.stderr].concat())
+        .split_inclusive(|byte| *byte == b'\n')
+        .filter(|line| !line.starts_with(b"Successfully written to "))
+        .flatten()
+        .copied()
+        .collect();
+    if !compiler.status.success() || !assembly.exists() {
+        return Ok(Outcome {
+            out: Vec::new(),
+            err,
+            status: describe(compiler.status),
+        });
+    }
+
+    let object = dir.join("program.o");
+    for tool in [
+        Command::new("as").arg("-o").arg(&object).arg(&assembly),
+        Command::new("ld")
+            .arg("-o")
+            .arg(dir.join("program"))
+            .arg(&object),
+    ] {
+        let name = tool.get_program().to_string_lossy().into_owned();
+        let output = tool
+            .output()
+            .map_err(|error| format!("couldn't run {}: {}", name, error))?;
+        if !output.status.success() {
+            return Err(format!(
+                "didn't assemble and link:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+    }
+
+    let input = fs::read(expected(src, "in")).unwrap_or_default();
+    let (status, out, program_err) = run(&dir.join("program"), dir, input)?;
+    err.extend(program_err);
+    Ok(Outcome {
+        out,
+        err,
+        status: describe(status),
+    })
+}
+
+// the .pas files in examples/<dir>, in order
+fn find_programs(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("examples")
+        .join(dir);
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("couldn't read the examples")
+        .map(|entry| entry.expect("couldn't read the examples").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "pas"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no programs in {}", dir.display());
+    programs
+}
+
+// an empty directory to build and run examples/<dir>/<name>.pas in, since programs may make files
+fn work_dir(dir: &str, src: &Path) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("examples")
+        .join(dir)
+        .join(src.file_stem().unwrap());
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).expect("couldn't make the work directory");
+    path
+}
+
+// tests/examples/<dir>/<name>.<extension> for examples/<dir>/<name>.pas
+fn expected(src: &Path, extension: &str) -> PathBuf {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let relative = src
+        .strip_prefix(examples)
+        .unwrap()
+        .with_extension(extension);
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("examples")
+        .join(relative)
+}
+
+// runs the program in `dir` with `input` as stdin, killing it if it takes too long, and returns
+// how it ended along with what it printed to stdout and stderr
+fn run(
+    executable: &Path,
+    dir: &Path,
+    input: Vec<u8>,
+) -> Result<(ExitStatus, Vec<u8>, Vec<u8>), String> {
+    let mut child = Command::new(executable)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("couldn't run: {}", error))?;
+    let mut stdin = child.stdin.take().unwrap();
+    // a program may stop before reading all of its input
+    thread::spawn(move || stdin.write_all(&input));
+    // read while waiting, so a program that prints a lot doesn't block on a full pipe
+    let stdout = read_all(child.stdout.take().unwrap());
+    let stderr = read_all(child.stderr.take().unwrap());
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|error| error.to_string())? {
+            break status;
+        }
+        if start.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(String::from("timed out"));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok((status, stdout.join().unwrap(), stderr.join().unwrap()))
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+fn describe(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("{}\n", code),
+        (None, Some(signal)) => format!("signal {}\n", signal),
+        (None, None) => format!("{}\n", status),
+    }
+}
+
+// removes the escape sequences that color diagnostics
+fn strip_colors(text: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut bytes = text.iter();
+    while let Some(byte) = bytes.next() {
+        if *byte == 0x1b {
+            // ESC [ parameters, ended by a letter
+            for byte in bytes.by_ref() {
+                if byte.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(*byte);
+        }
+    }
+    result
+}
+
+fn compare(src: &Path, extension: &str, actual: &[u8], failures: &mut Vec<String>) {
+    let path = expected(src, extension);
+    if env::var_os("UPDATE_EXPECTED").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).expect("couldn't make the expected directory");
+        fs::write(&path, actual).expect("couldn't write the expected output");
+        return;
+    }
+    match fs::read(&path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) => failures.push(format!(
+            "{} gave this {}:\n{}\ninstead of:\n{}",
+            src.display(),
+            extension,
+            String::from_utf8_lossy(actual),
+            String::from_utf8_lossy(&expected)
+        )),
+        Err(error) => failures.push(format!("couldn't read {}: {}", path.display(), error)),
+    }
+}